@@ -0,0 +1,196 @@
+//! Which pieces attack and defend each square, and the pins and checks they make.
+//!
+//! A piece attacks a square when it could take an opposing piece standing there,
+//! whatever stands on the square now. Pins are not taken into account, so a
+//! pinned piece still attacks the squares it could move to.
+
+use super::{check_position, code_moves, decode_position, get_diagonal_pin, get_king_pos, get_king_takes, get_knight_takes, get_line, get_pawn_takes, get_straight_pin, opponent, Colour, Game, Piece};
+
+/// A piece that cannot leave the line between its king and an opposing sliding piece.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pin {
+    pub pinned: String,
+    pub pinner: String,
+    /// The squares from next to the king up to and including the pinner, which the pinned piece can still move along.
+    pub ray: Vec<String>
+}
+
+pub(crate) static STRAIGHT: [(isize, isize); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+pub(crate) static DIAGONAL: [(isize, isize); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+// get the directions a piece slides in
+pub(crate) fn directions(_piece: Piece) -> Vec<(isize, isize)> {
+    match _piece {
+        Piece::Rook(_) => STRAIGHT.to_vec(),
+        Piece::Bishop(_) => DIAGONAL.to_vec(),
+        Piece::Queen(_) => STRAIGHT.iter().chain(DIAGONAL.iter()).copied().collect(),
+        _ => Vec::default()
+    }
+}
+
+// get the squares of the pieces of a colour that attack a square, and of the sliding pieces
+// that would attack it if the one piece between them and the square were gone
+pub(crate) fn attackers(_game: &Game, _position: &[usize], _colour: Colour) -> (Vec<Vec<usize>>, Vec<Vec<usize>>) {
+    let position = _position.to_vec();
+    let at = |p: &Vec<usize>| _game.board[p[0]][p[1]];
+
+    // a pawn attacks the squares an opposing pawn on the square would take on,
+    // which may be off the board behind the last rank
+    let mut direct: Vec<Vec<usize>> = get_pawn_takes(&position, opponent(_colour)).into_iter()
+        .filter(|p| p[0] < 8 && at(p) == Some(Piece::Pawn(_colour)))
+        .collect();
+    direct.extend(get_knight_takes(&position).into_iter().filter(|p| at(p) == Some(Piece::Knight(_colour))));
+    direct.extend(get_king_takes(&position).into_iter().filter(|p| at(p) == Some(Piece::King(_colour))));
+
+    // follow each line out from the square, to the first piece and to the one behind it
+    let mut xray: Vec<Vec<usize>> = Vec::default();
+    for direction in STRAIGHT.iter().chain(DIAGONAL.iter()) {
+        let line = get_line(&position, direction.0, direction.1);
+        for (blockers, p) in line[1..].iter().filter(|p| at(p).is_some()).take(2).enumerate() {
+            let piece = at(p).unwrap();
+            // the direction from the piece to the square is the opposite one
+            if piece.unwrap() == _colour && directions(piece).contains(&(-direction.0, -direction.1)) {
+                match blockers {
+                    0 => direct.push(p.clone()),
+                    _ => xray.push(p.clone())
+                }
+            }
+        }
+    }
+    (direct, xray)
+}
+
+impl Game {
+    /// Get the pieces of a colour that are pinned to their king.
+    pub fn pins(&self, _colour: Colour) -> Vec<Pin> {
+        let king = get_king_pos(self, _colour);
+        let mut pieces: Vec<(Vec<usize>, Piece)> = Vec::default();
+        for (rank, row) in self.board.iter().enumerate() {
+            for (file, piece) in row.iter().enumerate() {
+                if let Some(p) = piece {
+                    pieces.push((vec![rank, file], *p));
+                }
+            }
+        }
+
+        let mut pins: Vec<Pin> = Vec::default();
+        for (pinned, _) in pieces.iter().filter(|(_, p)| p.unwrap() == _colour && !matches!(p, Piece::King(_))) {
+            for (pinner, piece) in pieces.iter().filter(|(_, p)| p.unwrap() != _colour) {
+                // the same pins that keep pieces on their line when moves are generated
+                let line = match piece {
+                    Piece::Rook(_) => get_straight_pin(pinner, pinned, &king, self, 0),
+                    Piece::Bishop(_) => get_diagonal_pin(pinner, pinned, &king, self, 0),
+                    Piece::Queen(_) => get_straight_pin(pinner, pinned, &king, self, 0)
+                        .or_else(|| get_diagonal_pin(pinner, pinned, &king, self, 0)),
+                    _ => None
+                };
+                // the line runs from the pinner up to the king, without the pinned piece
+                if let Some(mut ray) = line {
+                    ray.push(pinned.clone());
+                    ray.sort_by_key(|p| p[0].abs_diff(king[0]).max(p[1].abs_diff(king[1])));
+                    pins.push(Pin {
+                        pinned: code_moves(&vec![pinned.clone()])[0].clone(),
+                        pinner: code_moves(&vec![pinner.clone()])[0].clone(),
+                        ray: code_moves(&ray)
+                    });
+                }
+            }
+        }
+        pins
+    }
+
+    /// Get the squares of the pieces giving check to the side to move.
+    pub fn checkers(&self) -> Vec<String> {
+        let king = get_king_pos(self, self.active);
+        code_moves(&attackers(self, &king, opponent(self.active)).0)
+    }
+
+    /// Check whether the side to move is in check from two pieces at once, which only a king move can answer.
+    pub fn is_double_check(&self) -> bool {
+        self.checkers().len() > 1
+    }
+
+    /// Get the squares of the pieces of a colour that attack a square, or None if
+    /// the square is malformed. Pieces attacking a square held by their own side defend it.
+    pub fn attackers_of(&self, _square: String, _colour: Colour) -> Option<Vec<String>> {
+        if !check_position(&_square) { return None }
+        Some(code_moves(&attackers(self, &decode_position(&_square), _colour).0))
+    }
+
+    /// Get the squares of the sliding pieces of a colour that attack a square through
+    /// one other piece, of either colour, or None if the square is malformed.
+    pub fn xray_attackers(&self, _square: String, _colour: Colour) -> Option<Vec<String>> {
+        if !check_position(&_square) { return None }
+        Some(code_moves(&attackers(self, &decode_position(&_square), _colour).1))
+    }
+
+    /// Get the number of pieces of a colour that attack each square, laid out like the board.
+    pub fn control_map(&self, _colour: Colour) -> [[u8; 8]; 8] {
+        let mut map = [[0; 8]; 8];
+        for (rank, row) in map.iter_mut().enumerate() {
+            for (file, count) in row.iter_mut().enumerate() {
+                *count = attackers(self, &[rank, file], _colour).0.len() as u8;
+            }
+        }
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Colour, Game};
+    use super::Pin;
+
+    #[test]
+    fn attack_maps() {
+        let game = Game::new();
+        let mut attackers = game.attackers_of("f3".to_string(), Colour::White).unwrap();
+        attackers.sort();
+        assert_eq!(attackers, vec!["e2", "g1", "g2"]);
+        assert_eq!(game.attackers_of("e4".to_string(), Colour::White).unwrap(), Vec::<String>::new());
+        assert_eq!(game.attackers_of("e9".to_string(), Colour::White), None);
+
+        // the rooks and queen defend each other along the back rank
+        let map = game.control_map(Colour::White);
+        assert_eq!(map[0][0], 0);
+        assert_eq!(map[0][3], 1);
+        assert_eq!(map[2], [2, 2, 3, 2, 2, 3, 2, 2]);
+        assert_eq!(map[3], [0; 8]);
+
+        // a battery of queen and rook, and a rook seeing through an opposing piece
+        let game = Game::from_fen("3rk3/8/8/8/8/3n4/3Q4/3RK3 w - - 0 1").unwrap();
+        assert_eq!(game.attackers_of("d3".to_string(), Colour::White).unwrap(), vec!["d2"]);
+        assert_eq!(game.xray_attackers("d3".to_string(), Colour::White).unwrap(), vec!["d1"]);
+        assert_eq!(game.xray_attackers("d2".to_string(), Colour::Black).unwrap(), vec!["d8"]);
+        assert_eq!(game.attackers_of("d2".to_string(), Colour::Black).unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn pins_and_checks() {
+        // a knight pinned by a rook and a bishop pinned by a queen
+        let game = Game::from_fen("4k3/8/8/q7/1B6/4r3/4N3/4K3 w - - 0 1").unwrap();
+        let squares = |s: &[&str]| s.iter().map(|x| x.to_string()).collect::<Vec<String>>();
+        assert_eq!(game.pins(Colour::White), vec![
+            Pin { pinned: "e2".to_string(), pinner: "e3".to_string(), ray: squares(&["e2", "e3"]) },
+            Pin { pinned: "b4".to_string(), pinner: "a5".to_string(), ray: squares(&["d2", "c3", "b4", "a5"]) }
+        ]);
+        assert!(game.pins(Colour::Black).is_empty());
+        assert!(game.checkers().is_empty());
+
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/q3K3 w - - 0 1").unwrap();
+        assert_eq!(game.checkers(), vec!["a1"]);
+        assert!(!game.is_double_check());
+        let game = Game::from_fen("4k3/8/8/8/8/3n4/8/4K2r w - - 0 1").unwrap();
+        assert_eq!(game.checkers().len(), 2);
+        assert!(game.is_double_check());
+    }
+
+    #[test]
+    fn pins_need_one_piece_between() {
+        // two pieces between the rook and the king, and a rook that cannot slide diagonally
+        let game = Game::from_fen("4k3/4r3/8/8/4N3/4B3/3P4/r3K3 w - - 0 1").unwrap();
+        assert!(game.pins(Colour::White).is_empty());
+        let game = Game::from_fen("4k3/8/8/8/8/2r5/3P4/4K3 w - - 0 1").unwrap();
+        assert!(game.pins(Colour::White).is_empty());
+    }
+}
@@ -0,0 +1,417 @@
+//! A compact binary encoding of positions and games, for archiving many games.
+//!
+//! A position takes about 30 bytes:
+//!
+//! - the squares that are occupied, as 8 bytes with a bit for each square from a1
+//!   to h8, a1 being the highest bit of the first byte
+//! - a nibble for each occupied piece in the same order, two to a byte and high
+//!   nibble first: 0 to 5 for a pawn, knight, bishop, rook, queen and king, plus 8
+//!   for black
+//! - a byte of flags: 1 if black is to move, 2 for Chess960
+//! - a byte for each colour's castling rights, white first, with the file of the
+//!   king side rook in the high nibble and of the queen side rook in the low one,
+//!   or 15 if the side cannot castle
+//! - the en passant square from 0 for a1 to 63 for h8, or 255 for none
+//! - the halfmove clock and the fullmove number as LEB128 varints
+//!
+//! A game starts with the version of the format, then a byte that is 1 if the
+//! starting position follows and 0 for the standard starting position. The moves
+//! come next, as a varint count and a byte for each move. The byte is the index
+//! of the move in the legal moves of the position, ordered by the square moved
+//! from, then the square moved to, then the promotion piece as queen, rook,
+//! bishop, knight. A position never has more than 218 legal moves, so every move
+//! fits in a byte. The last byte gives how the game ended, if it ended off the
+//! board: 0 for not at all, otherwise the termination followed by a byte for the
+//! winner, 0 for a draw, 1 for white and 2 for black.
+//!
+//! Only version 1 exists so far. Should the format change, later versions are
+//! meant to keep reading archives written by this one.
+
+use super::fen::START_FEN;
+use super::{colour_index, legal_moves, play_promotion, Colour, Game, Piece, Termination, FILES};
+
+/// The version of the format written by `Game::to_bytes`.
+pub const VERSION: u8 = 1;
+
+static PIECES: [char; 6] = ['P', 'N', 'B', 'R', 'Q', 'K'];
+static PROMOTIONS: [char; 4] = ['Q', 'R', 'B', 'N'];
+
+// reads bytes from the front of a slice
+struct Reader<'a> {
+    bytes: &'a [u8]
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Option<u8> {
+        let (first, rest) = self.bytes.split_first()?;
+        self.bytes = rest;
+        Some(*first)
+    }
+
+    fn varint(&mut self) -> Option<u32> {
+        let mut value: u32 = 0;
+        for shift in (0..35).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u32).checked_shl(shift)?;
+            if byte & 0x80 == 0 { return Some(value) }
+        }
+        None
+    }
+}
+
+fn write_varint(_bytes: &mut Vec<u8>, _value: u32) {
+    let mut value = _value;
+    while value >= 0x80 {
+        _bytes.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    _bytes.push(value as u8);
+}
+
+fn termination_code(_termination: Termination) -> u8 {
+    match _termination {
+        Termination::Checkmate => 1,
+        Termination::Stalemate => 2,
+        Termination::Resignation => 3,
+        Termination::Timeout => 4,
+        Termination::Agreement => 5,
+        Termination::Repetition => 6,
+        Termination::FiftyMoves => 7,
+        Termination::InsufficientMaterial => 8,
+        Termination::Abandonment => 9,
+        Termination::Adjudication => 10
+    }
+}
+
+fn termination_from_code(_code: u8) -> Option<Termination> {
+    match _code {
+        1 => Some(Termination::Checkmate),
+        2 => Some(Termination::Stalemate),
+        3 => Some(Termination::Resignation),
+        4 => Some(Termination::Timeout),
+        5 => Some(Termination::Agreement),
+        6 => Some(Termination::Repetition),
+        7 => Some(Termination::FiftyMoves),
+        8 => Some(Termination::InsufficientMaterial),
+        9 => Some(Termination::Abandonment),
+        10 => Some(Termination::Adjudication),
+        _ => None
+    }
+}
+
+// get the legal moves of a game in the order their indices refer to
+fn ordered_moves(_game: &Game) -> Vec<(Vec<usize>, Vec<usize>, Piece)> {
+    let mut moves = legal_moves(_game);
+    moves.sort_by_key(|(from, to, piece)| {
+        let promotion = match _game.board[from[0]][from[1]] == Some(*piece) {
+            true => 0,
+            false => PROMOTIONS.iter().position(|&c| c == piece.letter()).unwrap_or_default()
+        };
+        (from[0] * 8 + from[1], to[0] * 8 + to[1], promotion)
+    });
+    moves
+}
+
+fn write_position(_game: &Game, _bytes: &mut Vec<u8>) {
+    let mut occupied: u64 = 0;
+    let mut nibbles: Vec<u8> = Vec::default();
+    for (rank, row) in _game.board.iter().enumerate() {
+        for (file, square) in row.iter().enumerate() {
+            if let Some(piece) = square {
+                occupied |= 1 << (63 - (rank * 8 + file));
+                let colour = match piece.unwrap() {
+                    Colour::White => 0,
+                    Colour::Black => 8
+                };
+                nibbles.push(PIECES.iter().position(|&c| c == piece.letter()).unwrap() as u8 + colour);
+            }
+        }
+    }
+    _bytes.extend_from_slice(&occupied.to_be_bytes());
+    for pair in nibbles.chunks(2) {
+        _bytes.push(pair[0] << 4 | pair.get(1).copied().unwrap_or_default());
+    }
+
+    let mut flags = 0;
+    if _game.active == Colour::Black {
+        flags |= 1;
+    }
+    if _game.chess960 {
+        flags |= 2;
+    }
+    _bytes.push(flags);
+    for colour in [Colour::White, Colour::Black] {
+        let rights = _game.castling[colour_index(colour)];
+        _bytes.push((rights[0].unwrap_or(15) as u8) << 4 | rights[1].unwrap_or(15) as u8);
+    }
+    _bytes.push(match &_game.en_passant {
+        Some(square) => (square[0] * 8 + square[1]) as u8,
+        None => 255
+    });
+    write_varint(_bytes, _game.halfmove_clock);
+    write_varint(_bytes, _game.fullmove_number);
+}
+
+// read a position by building its Shredder-FEN, so that it is checked like any other position
+fn read_position(_reader: &mut Reader) -> Option<Game> {
+    let mut occupied = 0;
+    for _ in 0..8 {
+        occupied = occupied << 8 | _reader.byte()? as u64;
+    }
+    let count = occupied.count_ones() as usize;
+    let mut nibbles: Vec<u8> = Vec::default();
+    for _ in 0..count.div_ceil(2) {
+        let byte = _reader.byte()?;
+        nibbles.push(byte >> 4);
+        nibbles.push(byte & 15);
+    }
+
+    let mut board = [[None; 8]; 8];
+    let mut next = nibbles.iter();
+    for (square, row) in board.iter_mut().flatten().enumerate() {
+        if occupied & 1 << (63 - square) == 0 { continue }
+        let nibble = *next.next()?;
+        let letter = *PIECES.get((nibble & 7) as usize)?;
+        *row = Some(match nibble & 8 {
+            0 => letter,
+            _ => letter.to_ascii_lowercase()
+        });
+    }
+    let mut placement: Vec<String> = Vec::default();
+    for row in board.iter().rev() {
+        let mut rank = String::default();
+        let mut empty = 0;
+        for square in row {
+            match square {
+                Some(c) => {
+                    if empty > 0 {
+                        rank += &empty.to_string();
+                        empty = 0;
+                    }
+                    rank.push(*c);
+                },
+                None => empty += 1
+            }
+        }
+        if empty > 0 {
+            rank += &empty.to_string();
+        }
+        placement.push(rank);
+    }
+
+    let flags = _reader.byte()?;
+    let mut castling = String::default();
+    for colour in [Colour::White, Colour::Black] {
+        let byte = _reader.byte()?;
+        for file in [byte >> 4, byte & 15] {
+            if file == 15 { continue }
+            let letter = *FILES.get(file as usize)?;
+            castling.push(match colour {
+                Colour::White => letter.to_ascii_uppercase(),
+                Colour::Black => letter
+            });
+        }
+    }
+    if castling.is_empty() {
+        castling.push('-');
+    }
+    let en_passant = match _reader.byte()? {
+        255 => "-".to_owned(),
+        square if square < 64 => format!("{}{}", FILES[square as usize % 8], square / 8 + 1),
+        _ => return None
+    };
+    let halfmove = _reader.varint()?;
+    let fullmove = _reader.varint()?;
+
+    let fen = format!("{} {} {} {} {} {}", placement.join("/"), if flags & 1 == 0 { "w" } else { "b" }, castling, en_passant, halfmove, fullmove);
+    let mut game = Game::from_fen(&fen)?;
+    game.chess960 = flags & 2 != 0;
+    Some(game)
+}
+
+impl Game {
+    /// Encode the current position in the binary format.
+    pub fn position_to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::default();
+        write_position(self, &mut bytes);
+        bytes
+    }
+
+    /// Decode a position in the binary format. Returns None if the bytes are
+    /// malformed or the position is not playable.
+    pub fn position_from_bytes(_bytes: &[u8]) -> Option<Game> {
+        let mut reader = Reader { bytes: _bytes };
+        let game = read_position(&mut reader)?;
+        match reader.bytes.is_empty() {
+            true => Some(game),
+            false => None
+        }
+    }
+
+    /// Encode the game, from its starting position, in the binary format.
+    /// Returns None if a move in its history is not legal in the position it was played in.
+    pub fn to_bytes(&self) -> Option<Vec<u8>> {
+        let start = match self.history.first() {
+            Some(r) => &r.before,
+            None => self
+        };
+        let mut bytes = vec![VERSION];
+        match !start.chess960 && start.to_fen() == START_FEN {
+            true => bytes.push(0),
+            false => {
+                bytes.push(1);
+                write_position(start, &mut bytes);
+            }
+        }
+
+        write_varint(&mut bytes, self.history.len() as u32);
+        for record in &self.history {
+            let (from, to) = (super::decode_position(&record.played.from), super::decode_position(&record.played.to));
+            let promotion = record.played.promotion.or(record.before.board[from[0]][from[1]])?;
+            let index = ordered_moves(&record.before).iter().position(|m| m.0 == from && m.1 == to && m.2 == promotion)?;
+            bytes.push(index as u8);
+        }
+
+        // endings on the board are found again by replaying the moves
+        match self.outcome {
+            Some(outcome) => {
+                bytes.push(termination_code(outcome.termination));
+                bytes.push(match outcome.winner {
+                    None => 0,
+                    Some(Colour::White) => 1,
+                    Some(Colour::Black) => 2
+                });
+            },
+            None => bytes.push(0)
+        }
+        Some(bytes)
+    }
+
+    /// Decode a game in the binary format.
+    /// Returns None if the bytes are malformed or a move is not legal.
+    pub fn from_bytes(_bytes: &[u8]) -> Option<Game> {
+        let mut reader = Reader { bytes: _bytes };
+        match reader.byte()? {
+            1 => (),
+            _ => return None
+        }
+        let mut game = match reader.byte()? {
+            0 => Game::new(),
+            1 => read_position(&mut reader)?,
+            _ => return None
+        };
+
+        for _ in 0..reader.varint()? {
+            let (from, to, piece) = ordered_moves(&game).get(reader.byte()? as usize)?.clone();
+            // the piece of a move is the piece moved, unless a pawn is promoted
+            let promotion = match game.board[from[0]][from[1]] == Some(piece) {
+                true => None,
+                false => Some(piece)
+            };
+            let squares = super::code_moves(&vec![from, to]);
+            play_promotion(&mut game, squares[0].clone(), squares[1].clone(), promotion)?;
+        }
+
+        let termination = reader.byte()?;
+        if termination != 0 {
+            let termination = termination_from_code(termination)?;
+            let winner = match reader.byte()? {
+                0 => None,
+                1 => Some(Colour::White),
+                2 => Some(Colour::Black),
+                _ => return None
+            };
+            if game.get_outcome().is_none() {
+                game.end(winner, termination);
+            }
+        }
+        match reader.bytes.is_empty() {
+            true => Some(game),
+            false => None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Colour, Game, Piece};
+    use super::VERSION;
+
+    // a pawn promoting to the given piece, with a black pawn left so that no promotion
+    // ends the game on material
+    fn promoting(_piece: &str) -> Game {
+        let mut game = Game::from_fen("8/1P5p/8/8/8/8/k7/4K3 w - - 0 1").unwrap();
+        game.set_promotion(_piece.to_string());
+        game.make_move("b7".to_string(), "b8".to_string()).unwrap();
+        game
+    }
+
+    #[test]
+    fn game_round_trip() {
+        let mut game = promoting("kn");
+        game.resign(Colour::Black);
+        // the version, the position that follows, one move and a resignation won by white
+        let bytes = game.to_bytes().unwrap();
+        let position = Game::from_fen("8/1P5p/8/8/8/8/k7/4K3 w - - 0 1").unwrap().position_to_bytes();
+        assert_eq!(bytes[..2], [VERSION, 1]);
+        assert_eq!(bytes[2..2 + position.len()], position[..]);
+        assert_eq!(bytes[2 + position.len()], 1);
+        assert_eq!(bytes[bytes.len() - 2..], [3, 1]);
+        assert_eq!(bytes.len(), 2 + position.len() + 2 + 2);
+        let read = Game::from_bytes(&bytes).unwrap();
+        assert_eq!(read.to_fen(), game.to_fen());
+        assert_eq!(read.get_history(), game.get_history());
+        assert_eq!(read.get_outcome(), game.get_outcome());
+    }
+
+    #[test]
+    fn promotion_order() {
+        // the four promotions of a pawn take consecutive indices, queen first
+        let index = |_piece: &str| {
+            let bytes = promoting(_piece).to_bytes().unwrap();
+            bytes[bytes.len() - 2]
+        };
+        let queen = index("q");
+        assert_eq!([index("r"), index("b"), index("kn")], [queen + 1, queen + 2, queen + 3]);
+    }
+
+    #[test]
+    fn chess960_round_trip() {
+        // castling onto the rook, from the game's own starting position
+        let mut game = Game::new_chess960(0).unwrap();
+        for m in ["e1d3", "e8d6", "d1e3", "d8e6", "b2b3", "b7b6", "c1b2", "c8b7", "g1f1"] {
+            assert_ne!(game.make_move(m[0..2].to_string(), m[2..4].to_string()), None, "{}", m);
+        }
+        let read = Game::from_bytes(&game.to_bytes().unwrap()).unwrap();
+        assert_eq!(read.to_shredder_fen(), game.to_shredder_fen());
+        assert_eq!(read.get_history(), game.get_history());
+    }
+
+    #[test]
+    fn promotion_setting() {
+        // decoding moves that do not promote leaves pawns to be promoted to queens
+        let mut game = Game::from_fen("8/1P6/8/8/8/8/k7/4K3 w - - 0 1").unwrap();
+        game.make_move("e1".to_string(), "e2".to_string()).unwrap();
+        game.make_move("a2".to_string(), "a3".to_string()).unwrap();
+        let mut read = Game::from_bytes(&game.to_bytes().unwrap()).unwrap();
+        read.make_move("b7".to_string(), "b8".to_string()).unwrap();
+        assert_eq!(read.get_history()[2].promotion, Some(Piece::Queen(Colour::White)));
+    }
+
+    #[test]
+    fn position_round_trip() {
+        let game = Game::from_fen("r3k2r/8/8/3pP3/8/8/8/R3K2R w Qk d6 12 40").unwrap();
+        let bytes = game.position_to_bytes();
+        assert_eq!(bytes.len(), 8 + 4 + 1 + 2 + 1 + 1 + 1);
+        assert_eq!(Game::position_from_bytes(&bytes).unwrap().to_fen(), game.to_fen());
+    }
+
+    #[test]
+    fn malformed_games() {
+        // unknown versions, illegal move indices and trailing bytes are rejected
+        assert!(Game::from_bytes(&[2, 0, 0, 0]).is_none());
+        assert!(Game::from_bytes(&[1, 0, 1, 20, 0]).is_none());
+        assert!(Game::from_bytes(&[1, 0, 0, 0, 0]).is_none());
+        assert_eq!(Game::from_bytes(&[1, 0, 0, 0]).unwrap().to_fen(), Game::new().to_fen());
+    }
+}
@@ -0,0 +1,395 @@
+//! Playing against a chess interface over the Chess Engine Communication
+//! Protocol (CECP), spoken by XBoard and WinBoard.
+//!
+//! The built-in engine plays one side, black after "new", and answers the moves
+//! of the interface as they come. In force mode it only keeps the game, checking
+//! and playing the moves it is sent, until "go" tells it to play the side to move.
+//! Its thinking time is the time per move set by "st", or a share of the time
+//! left given by "time" over the moves to the next time control of "level". The
+//! search depth is limited by "sd", and is 3 when there is no time to go by. The
+//! interface keeps the clocks and ends the game, so "otim" is accepted and
+//! ignored. Analysis mode is not supported.
+
+use std::time::{Duration, Instant};
+
+use super::engine::{Analysis, Engine};
+use super::uci::{Reply, Score};
+use super::{opponent, play_promotion, Colour, Game, Outcome};
+
+/// The features asked for in answer to "protover 2".
+pub static FEATURES: &str = "feature myname=\"osveijer-chess\" ping=1 setboard=1 usermove=1 playother=1 san=0 analyze=0 sigint=0 sigterm=0 colors=0 done=1";
+
+// the deepest the engine searches when it has a time limit to stop it
+const MAX_DEPTH: u32 = 64;
+
+/// The state of a conversation with an interface.
+#[derive(Clone)]
+pub struct Cecp {
+    game: Game,
+    // whether moves are only played as sent, without the engine answering them
+    force: bool,
+    // the side the engine plays when not in force mode
+    engine_side: Colour,
+    // the limits set by the interface: the search depth, the time for each move,
+    // the moves and increment of each time control, and the time left on the engine's clock
+    depth: Option<u32>,
+    move_time: Option<Duration>,
+    level: Option<(u32, Duration)>,
+    time_left: Option<Duration>,
+    // whether to show the engine's thinking
+    post: bool
+}
+
+impl Default for Cecp {
+    fn default() -> Cecp {
+        Cecp {
+            game: Game::new(),
+            force: false,
+            engine_side: Colour::Black,
+            depth: None,
+            move_time: None,
+            level: None,
+            time_left: None,
+            post: false
+        }
+    }
+}
+
+// get the result line for a game that has ended, e.g. "1-0 {White wins by checkmate}"
+fn result(_outcome: &Outcome) -> String {
+    match _outcome.winner {
+        Some(Colour::White) => format!("1-0 {{White wins by {}}}", _outcome.termination),
+        Some(Colour::Black) => format!("0-1 {{Black wins by {}}}", _outcome.termination),
+        None => format!("1/2-1/2 {{Draw by {}}}", _outcome.termination)
+    }
+}
+
+fn parse_seconds(_seconds: &str) -> Option<Duration> {
+    Duration::try_from_secs_f64(_seconds.parse().ok()?).ok()
+}
+
+// read a time in minutes, or minutes and seconds, e.g. "5" or "0:30"
+fn parse_time(_time: &str) -> Option<Duration> {
+    match _time.split_once(':') {
+        Some((minutes, seconds)) => Some(parse_seconds(minutes)? * 60 + parse_seconds(seconds)?),
+        None => Some(parse_seconds(_time)? * 60)
+    }
+}
+
+// get a score in centipawns, with mates as 100000 and more, as thinking output wants them
+fn centipawns(_score: Score) -> i32 {
+    match _score {
+        Score::Centipawns(cp) => cp,
+        Score::Mate(moves) if moves > 0 => 100000 + moves,
+        Score::Mate(moves) => -100000 + moves
+    }
+}
+
+impl Cecp {
+    /// Start with a new game, in which the engine plays black.
+    pub fn new() -> Cecp {
+        Cecp::default()
+    }
+
+    /// Get the game as it stands.
+    pub fn get_game(&self) -> &Game {
+        &self.game
+    }
+
+    /// Handle a command from the interface and get the lines to answer it with,
+    /// or None when the interface has quit.
+    pub fn handle(&mut self, _command: &str) -> Option<Vec<String>> {
+        let line = _command.trim();
+        let (command, argument) = match line.split_once(' ') {
+            Some((c, a)) => (c, a.trim()),
+            None => (line, "")
+        };
+
+        match command {
+            "quit" => return None,
+            "protover" => return Some(vec![FEATURES.to_owned()]),
+            "ping" => return Some(vec![format!("pong {}", argument)]),
+            "new" => {
+                self.game = Game::new();
+                self.force = false;
+                self.engine_side = Colour::Black;
+                self.depth = None;
+            },
+            "force" | "result" => self.force = true,
+            "go" => {
+                self.force = false;
+                self.engine_side = self.game.active;
+                return Some(self.engine_move())
+            },
+            "playother" => {
+                self.force = false;
+                self.engine_side = opponent(self.game.active);
+            },
+            "usermove" => return Some(self.user_move(argument)),
+            "hint" => {
+                let hint = self.search().map(|a| format!("Hint: {}", a.line[0].to_uci()));
+                return Some(hint.into_iter().collect())
+            },
+            "undo" => {
+                self.game.undo_move();
+            },
+            "remove" => {
+                self.game.undo_move();
+                self.game.undo_move();
+            },
+            "setboard" => {
+                match Game::from_fen(argument) {
+                    Some(g) => self.game = g,
+                    None => return Some(vec!["tellusererror Illegal position".to_owned()])
+                }
+            },
+            // the moves of each time control, its time, and the increment in seconds, e.g. "40 0:30 0";
+            // the time itself comes with "time" before every move
+            "level" => {
+                let fields: Vec<&str> = argument.split_whitespace().collect();
+                let level = match fields.as_slice() {
+                    [moves, base, increment] if parse_time(base).is_some() => moves.parse().ok().zip(parse_seconds(increment)),
+                    _ => None
+                };
+                match level {
+                    Some(l) => self.level = Some(l),
+                    None => return Some(vec![format!("Error (bad time control): {}", line)])
+                }
+            },
+            "st" => {
+                match parse_seconds(argument) {
+                    Some(t) => self.move_time = Some(t),
+                    None => return Some(vec![format!("Error (bad time): {}", line)])
+                }
+            },
+            "sd" => {
+                match argument.parse::<u32>() {
+                    Ok(d) if d > 0 => self.depth = Some(d),
+                    _ => return Some(vec![format!("Error (bad depth): {}", line)])
+                }
+            },
+            // the clock of the engine is given in centiseconds
+            "time" => {
+                match argument.parse::<u64>() {
+                    Ok(cs) => self.time_left = Some(Duration::from_millis(10 * cs)),
+                    Err(_) => return Some(vec![format!("Error (bad time): {}", line)])
+                }
+            },
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            "analyze" => return Some(vec![format!("Error (not supported): {}", line)]),
+            // the interface keeps the clocks and ends the game, and the engine always moves at once
+            "xboard" | "accepted" | "rejected" | "otim" | "nps" | "random" | "hard" | "easy" | "computer" | "name" | "rating" | "ics"
+                | "draw" | "?" | "" => (),
+            _ => return Some(vec![format!("Error (unknown command): {}", line)])
+        }
+        Some(Vec::default())
+    }
+
+    // play a move in coordinate notation, e.g. "e2e4" or "e7e8q", and answer it if it is the engine's turn
+    fn user_move(&mut self, _move: &str) -> Vec<String> {
+        let played = Reply::from_uci(_move, self.game.active)
+            .and_then(|m| play_promotion(&mut self.game, m.from, m.to, m.promotion));
+        match (played, self.game.get_outcome()) {
+            (None, _) => vec![format!("Illegal move: {}", _move)],
+            (Some(_), Some(outcome)) => vec![result(&outcome)],
+            (Some(_), None) if !self.force && self.game.active == self.engine_side => self.engine_move(),
+            (Some(_), None) => Vec::default()
+        }
+    }
+
+    // get the time the engine has to think about its move, if it has a limit
+    fn think_time(&self) -> Option<Duration> {
+        if self.move_time.is_some() { return self.move_time }
+        let left = self.time_left?;
+        let (moves, increment) = self.level.unwrap_or((0, Duration::ZERO));
+        // the moves to the next time control, or a share of the rest of the game when there is none
+        let to_go = match moves {
+            0 => 30,
+            n => n - (self.game.fullmove_number - 1) % n
+        };
+        // never more than half of what is left, so that a move is always given in time
+        Some((left / to_go + increment / 2).min(left / 2))
+    }
+
+    // search for the engine's move, within the limits set by the interface
+    fn search(&self) -> Option<Analysis> {
+        let think_time = self.think_time();
+        let depth = match think_time {
+            Some(_) => self.depth.unwrap_or(MAX_DEPTH),
+            None => self.depth.unwrap_or(Engine::default().depth)
+        };
+        Engine::new(depth).search_until(&self.game, think_time.map(|t| Instant::now() + t))
+    }
+
+    // let the engine play the side to move, and get the lines that tell the interface
+    fn engine_move(&mut self) -> Vec<String> {
+        if self.game.get_outcome().is_some() { return Vec::default() }
+        let started = Instant::now();
+        let analysis = match self.search() {
+            Some(a) => a,
+            None => return Vec::default()
+        };
+        let mut lines: Vec<String> = Vec::default();
+        if self.post {
+            let line: Vec<&str> = analysis.line.iter().map(|m| m.san.as_str()).collect();
+            lines.push(format!("{} {} {} {} {}", analysis.depth, centipawns(analysis.score), started.elapsed().as_millis() / 10, analysis.nodes, line.join(" ")));
+        }
+        let best = analysis.line[0].clone();
+        lines.push(format!("move {}", best.to_uci()));
+        play_promotion(&mut self.game, best.from, best.to, best.promotion);
+        if let Some(outcome) = self.game.get_outcome() {
+            lines.push(result(&outcome));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::super::{Colour, Piece};
+    use super::{Cecp, FEATURES};
+
+    #[test]
+    fn features() {
+        let mut cecp = Cecp::new();
+        assert_eq!(cecp.handle("xboard"), Some(vec![]));
+        assert_eq!(cecp.handle("protover 2"), Some(vec![FEATURES.to_string()]));
+        assert_eq!(cecp.handle("accepted usermove"), Some(vec![]));
+        assert_eq!(cecp.handle("ping 7"), Some(vec!["pong 7".to_string()]));
+        assert_eq!(cecp.handle("quit"), None);
+    }
+
+    #[test]
+    fn user_moves() {
+        let mut cecp = Cecp::new();
+        cecp.handle("force");
+        assert_eq!(cecp.handle("usermove e2e4"), Some(vec![]));
+        assert_eq!(cecp.handle("usermove e7e5"), Some(vec![]));
+        assert_eq!(cecp.handle("usermove e4e5"), Some(vec!["Illegal move: e4e5".to_string()]));
+        assert_eq!(cecp.handle("usermove e1"), Some(vec!["Illegal move: e1".to_string()]));
+        assert_eq!(cecp.get_game().get_history().len(), 2);
+    }
+
+    #[test]
+    fn promotions() {
+        let mut cecp = Cecp::new();
+        cecp.handle("force");
+        cecp.handle("setboard 8/1P6/8/8/8/8/k7/4K3 w - - 0 1");
+        // a promotion piece is not accepted for a move that is not a promotion
+        assert_eq!(cecp.handle("usermove e1e2q"), Some(vec!["Illegal move: e1e2q".to_string()]));
+        assert_eq!(cecp.handle("usermove b7b8r"), Some(vec![]));
+        assert_eq!(cecp.get_game().get_history()[0].promotion, Some(Piece::Rook(Colour::White)));
+    }
+
+    #[test]
+    fn take_backs() {
+        let mut cecp = Cecp::new();
+        cecp.handle("force");
+        for m in ["e2e4", "e7e5", "g1f3"] {
+            cecp.handle(&format!("usermove {}", m));
+        }
+        cecp.handle("undo");
+        assert_eq!(cecp.get_game().get_history().len(), 2);
+        cecp.handle("remove");
+        assert!(cecp.get_game().get_history().is_empty());
+        cecp.handle("remove");
+        assert!(cecp.get_game().get_history().is_empty());
+    }
+
+    #[test]
+    fn set_up_positions() {
+        let mut cecp = Cecp::new();
+        let fen = "4k3/8/8/8/8/8/8/4K2R w K - 0 1";
+        assert_eq!(cecp.handle(&format!("setboard {}", fen)), Some(vec![]));
+        assert_eq!(cecp.get_game().to_fen(), fen);
+        assert_eq!(cecp.handle("setboard 8/8/8 w"), Some(vec!["tellusererror Illegal position".to_string()]));
+        assert_eq!(cecp.get_game().to_fen(), fen);
+        cecp.handle("new");
+        assert_eq!(cecp.get_game().to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    }
+
+    #[test]
+    fn results() {
+        let mut cecp = Cecp::new();
+        cecp.handle("force");
+        for m in ["f2f3", "e7e5", "g2g4"] {
+            assert_eq!(cecp.handle(&format!("usermove {}", m)), Some(vec![]));
+        }
+        assert_eq!(cecp.handle("usermove d8h4"), Some(vec!["0-1 {Black wins by checkmate}".to_string()]));
+        assert_eq!(cecp.handle("result 0-1 {Black mates}"), Some(vec![]));
+    }
+
+    #[test]
+    fn engine_answers() {
+        // after "new" the engine plays black and answers every move
+        let mut cecp = Cecp::new();
+        cecp.handle("new");
+        cecp.handle("sd 1");
+        let answer = cecp.handle("usermove e2e4").unwrap();
+        assert_eq!(answer.len(), 1);
+        assert!(answer[0].starts_with("move "));
+        assert_eq!(cecp.get_game().get_history().len(), 2);
+        assert_eq!(cecp.handle("usermove e2e4"), Some(vec!["Illegal move: e2e4".to_string()]));
+    }
+
+    #[test]
+    fn go_and_force() {
+        let mut cecp = Cecp::new();
+        cecp.handle("force");
+        cecp.handle("sd 1");
+        assert_eq!(cecp.handle("usermove e2e4"), Some(vec![]));
+        // the engine takes the side to move, and keeps it
+        assert_eq!(cecp.handle("go").unwrap().len(), 1);
+        assert_eq!(cecp.handle("usermove d2d4").unwrap().len(), 1);
+        assert_eq!(cecp.get_game().get_history().len(), 4);
+        // with "playother" the engine takes the side not to move
+        cecp.handle("new");
+        cecp.handle("force");
+        cecp.handle("usermove e2e4");
+        assert_eq!(cecp.handle("playother"), Some(vec![]));
+        assert_eq!(cecp.handle("usermove e7e5").unwrap().len(), 1);
+        assert_eq!(cecp.get_game().get_history().len(), 3);
+    }
+
+    #[test]
+    fn engine_results() {
+        let mut cecp = Cecp::new();
+        cecp.handle("setboard 6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1");
+        cecp.handle("post");
+        let answer = cecp.handle("go").unwrap();
+        assert_eq!(&answer[1..], ["move a1a8", "1-0 {White wins by checkmate}"]);
+        // the thinking is shown as depth, score, time, nodes and the line expected
+        let thinking: Vec<&str> = answer[0].split_whitespace().collect();
+        assert_eq!((thinking[0], thinking[1], thinking[4]), ("1", "100001", "Ra8#"));
+        assert_eq!(cecp.handle("hint"), Some(vec![]));
+    }
+
+    #[test]
+    fn time_controls() {
+        let mut cecp = Cecp::new();
+        assert_eq!(cecp.think_time(), None);
+        // the time left is shared out over the moves to the next time control
+        cecp.handle("level 40 0:30 2");
+        cecp.handle("time 3000");
+        assert_eq!(cecp.think_time(), Some(Duration::from_millis(1750)));
+        cecp.handle("level 0 5 0");
+        assert_eq!(cecp.think_time(), Some(Duration::from_secs(1)));
+        cecp.handle("st 5");
+        assert_eq!(cecp.think_time(), Some(Duration::from_secs(5)));
+        assert_eq!(cecp.handle("level 40"), Some(vec!["Error (bad time control): level 40".to_string()]));
+        assert_eq!(cecp.handle("sd 0"), Some(vec!["Error (bad depth): sd 0".to_string()]));
+    }
+
+    #[test]
+    fn engine_commands() {
+        let mut cecp = Cecp::new();
+        assert_eq!(cecp.handle("otim 30000"), Some(vec![]));
+        assert_eq!(cecp.handle("sd 1"), Some(vec![]));
+        assert!(cecp.handle("hint").unwrap()[0].starts_with("Hint: "));
+        assert_eq!(cecp.handle("analyze"), Some(vec!["Error (not supported): analyze".to_string()]));
+        assert_eq!(cecp.handle("bogus 1"), Some(vec!["Error (unknown command): bogus 1".to_string()]));
+    }
+}
@@ -0,0 +1,83 @@
+//! Chess960 (Fischer random chess) starting positions.
+//!
+//! The 960 positions are numbered 0–959 as described by Reinhard Scharnagl, where
+//! position 518 is the standard starting position.
+
+use super::{Colour, Game, Piece};
+
+// the files of the two knights among the five squares left after the bishops and the queen
+static KNIGHTS: [(usize, usize); 10] = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+
+// get the back rank pieces of a starting position, from the a-file to the h-file
+fn back_rank(_index: usize, _colour: Colour) -> [Piece; 8] {
+    let mut rank: [Option<Piece>; 8] = [None; 8];
+    let mut n = _index;
+
+    // the bishops stand on squares of different colours, b1 being light and a1 dark
+    rank[n % 4 * 2 + 1] = Some(Piece::Bishop(_colour));
+    n /= 4;
+    rank[n % 4 * 2] = Some(Piece::Bishop(_colour));
+    n /= 4;
+
+    // the remaining pieces are placed on the empty squares counted from the a-file
+    let empty = |rank: &[Option<Piece>; 8]| -> Vec<usize> { (0..8).filter(|&f| rank[f].is_none()).collect() };
+    rank[empty(&rank)[n % 6]] = Some(Piece::Queen(_colour));
+    n /= 6;
+    let (first, second) = KNIGHTS[n];
+    let squares = empty(&rank);
+    rank[squares[first]] = Some(Piece::Knight(_colour));
+    rank[squares[second]] = Some(Piece::Knight(_colour));
+
+    // the king always stands between the rooks
+    let squares = empty(&rank);
+    rank[squares[0]] = Some(Piece::Rook(_colour));
+    rank[squares[1]] = Some(Piece::King(_colour));
+    rank[squares[2]] = Some(Piece::Rook(_colour));
+
+    rank.map(|p| p.unwrap())
+}
+
+impl Game {
+    /// Initialises a Chess960 game from the number of its starting position.
+    /// Returns None if the number is not in 0–959.
+    pub fn new_chess960(_index: usize) -> Option<Game> {
+        if _index >= 960 { return None }
+        let mut game = Game::new();
+        let white = back_rank(_index, Colour::White);
+        let black = back_rank(_index, Colour::Black);
+        game.board[0] = white.map(Some);
+        game.board[7] = black.map(Some);
+
+        let rooks: Vec<usize> = (0..8).filter(|&f| white[f] == Piece::Rook(Colour::White)).collect();
+        game.castling = [[Some(rooks[1]), Some(rooks[0])], [Some(rooks[1]), Some(rooks[0])]];
+        game.chess960 = true;
+        Some(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Game;
+
+    #[test]
+    fn chess960_positions() {
+        assert_eq!(Game::new_chess960(518).unwrap().to_fen(), Game::new().to_fen());
+        assert_eq!(Game::new_chess960(0).unwrap().to_fen(), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1");
+        assert_eq!(Game::new_chess960(959).unwrap().to_fen(), "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w KQkq - 0 1");
+        assert!(Game::new_chess960(960).is_none());
+
+        let game = Game::from_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9").unwrap();
+        assert_eq!(game.perft(1), 21);
+        assert_eq!(game.perft(2), 528);
+        assert_eq!(game.perft(3), 12189);
+
+        // castling moves the king onto its own rook
+        let mut game = Game::from_fen("r3k2r/8/8/8/8/8/8/1R2K1R1 w GBkq - 0 1").unwrap();
+        let mut moves = game.get_possible_moves("e1".to_string()).unwrap();
+        moves.sort();
+        assert_eq!(moves, vec!["b1", "d1", "d2", "e2", "f1", "f2", "g1"]);
+        game.make_move("e1".to_string(), "g1".to_string());
+        game.make_move("e8".to_string(), "a8".to_string());
+        assert_eq!(game.to_fen(), "2kr3r/8/8/8/8/8/8/1R3RK1 w - - 2 2");
+    }
+}
@@ -0,0 +1,317 @@
+//! Chess clocks and time controls.
+//!
+//! A time control is made of stages, e.g. 40 moves in 90 minutes followed by
+//! 30 minutes for the rest of the game. The time of the next stage is added to a
+//! player's clock when they complete the moves of the current one. Each stage can
+//! give time back on every move with a Fischer increment, a Bronstein delay or a
+//! simple delay.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use super::{colour_index, opponent, Colour};
+
+/// Time given to a player on each move of a stage.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Bonus {
+    None,
+    /// Added to the clock after every move.
+    Fischer(Duration),
+    /// Added back after every move, but never more than the time the move took.
+    Bronstein(Duration),
+    /// Waited out at the start of every move before the clock starts running.
+    Delay(Duration)
+}
+
+/// A stage of a time control.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Stage {
+    /// The number of moves to play in the stage, or None if it lasts for the rest of the game.
+    pub moves: Option<u32>,
+    pub time: Duration,
+    pub bonus: Bonus
+}
+
+/// The stages of a time control, played in order. A last stage with a number
+/// of moves is repeated until the game ends.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimeControl {
+    pub stages: Vec<Stage>
+}
+
+impl TimeControl {
+    /// A single stage for the whole game, without any time given back.
+    pub fn sudden_death(_time: Duration) -> TimeControl {
+        TimeControl::single(_time, Bonus::None)
+    }
+
+    /// A single stage for the whole game, with an increment after every move.
+    pub fn fischer(_time: Duration, _increment: Duration) -> TimeControl {
+        TimeControl::single(_time, Bonus::Fischer(_increment))
+    }
+
+    /// A single stage for the whole game, with a Bronstein delay on every move.
+    pub fn bronstein(_time: Duration, _delay: Duration) -> TimeControl {
+        TimeControl::single(_time, Bonus::Bronstein(_delay))
+    }
+
+    /// A single stage for the whole game, with a simple delay on every move.
+    pub fn delay(_time: Duration, _delay: Duration) -> TimeControl {
+        TimeControl::single(_time, Bonus::Delay(_delay))
+    }
+
+    fn single(_time: Duration, _bonus: Bonus) -> TimeControl {
+        TimeControl { stages: vec![Stage { moves: None, time: _time, bonus: _bonus }] }
+    }
+
+    /// Parse a time control in the format of the PGN TimeControl tag, with times in
+    /// seconds, e.g. "300+3" or "40/5400+30:1800+30". Returns None if the format is
+    /// not understood or the game is untimed.
+    pub fn parse(_control: &str) -> Option<TimeControl> {
+        let mut stages: Vec<Stage> = Vec::default();
+        for field in _control.trim().split(':') {
+            let (moves, rest) = match field.split_once('/') {
+                Some((m, r)) => (Some(m.parse().ok().filter(|&m| m > 0)?), r),
+                None => (None, field)
+            };
+            let (time, bonus) = match rest.split_once('+') {
+                Some((t, i)) => (t, Bonus::Fischer(Duration::from_secs(i.parse().ok()?))),
+                None => (rest, Bonus::None)
+            };
+            stages.push(Stage { moves, time: Duration::from_secs(time.parse().ok()?), bonus });
+        }
+        // only the last stage can last for the rest of the game
+        if stages[..stages.len() - 1].iter().any(|s| s.moves.is_none()) { return None }
+        Some(TimeControl { stages })
+    }
+}
+
+/// Write the time control in the format of the PGN TimeControl tag. Delays
+/// have no place in the format and are left out.
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, stage) in self.stages.iter().enumerate() {
+            if i > 0 {
+                write!(f, ":")?;
+            }
+            if let Some(moves) = stage.moves {
+                write!(f, "{}/", moves)?;
+            }
+            write!(f, "{}", stage.time.as_secs())?;
+            if let Bonus::Fischer(increment) = stage.bonus {
+                write!(f, "+{}", increment.as_secs())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A chess clock for both players.
+///
+/// The methods ending in `_at` take the time of the event, which makes the clock
+/// usable with times from elsewhere, e.g. from a server, and in tests. The others
+/// use the current time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Clock {
+    control: TimeControl,
+    remaining: [Duration; 2],
+    // the stage each player is in, and the moves they have played in it
+    stage: [usize; 2],
+    moves: [u32; 2],
+    // the player whose time is running, and since when
+    running: Option<(Colour, Instant)>
+}
+
+impl Clock {
+    /// Set up a clock for a time control, with neither player's time running.
+    pub fn new(_control: TimeControl) -> Clock {
+        let time = _control.stages.first().map(|s| s.time).unwrap_or_default();
+        Clock {
+            control: _control,
+            remaining: [time, time],
+            stage: [0, 0],
+            moves: [0, 0],
+            running: None
+        }
+    }
+
+    /// Get the time control the clock was set up with.
+    pub fn get_time_control(&self) -> &TimeControl {
+        &self.control
+    }
+
+    /// Get the player whose time is running, if any.
+    pub fn get_running(&self) -> Option<Colour> {
+        self.running.map(|(c, _)| c)
+    }
+
+    // get the bonus of the stage a player is in
+    fn bonus(&self, _colour: Colour) -> Bonus {
+        let stages = &self.control.stages;
+        match stages.get(self.stage[colour_index(_colour)]).or(stages.last()) {
+            Some(s) => s.bonus,
+            None => Bonus::None
+        }
+    }
+
+    /// Get the time a player is given back on each move of the stage they are in.
+    pub fn get_bonus(&self, _colour: Colour) -> Bonus {
+        self.bonus(_colour)
+    }
+
+    /// Get the number of moves a player has left to play in the stage they are in,
+    /// or None if the stage lasts for the rest of the game.
+    pub fn moves_to_go(&self, _colour: Colour) -> Option<u32> {
+        let index = colour_index(_colour);
+        let stages = &self.control.stages;
+        stages.get(self.stage[index]).or(stages.last())?.moves.map(|m| m - self.moves[index])
+    }
+
+    // get the time a move has used of a player's clock, after any delay
+    fn used(&self, _colour: Colour, _elapsed: Duration) -> Duration {
+        match self.bonus(_colour) {
+            Bonus::Delay(delay) => _elapsed.saturating_sub(delay),
+            _ => _elapsed
+        }
+    }
+
+    /// Get the time a player has left at the given time.
+    pub fn remaining_at(&self, _colour: Colour, _now: Instant) -> Duration {
+        let remaining = self.remaining[colour_index(_colour)];
+        match self.running {
+            Some((c, since)) if c == _colour => remaining.saturating_sub(self.used(c, _now.saturating_duration_since(since))),
+            _ => remaining
+        }
+    }
+
+    /// Get the time a player has left.
+    pub fn remaining(&self, _colour: Colour) -> Duration {
+        self.remaining_at(_colour, Instant::now())
+    }
+
+    /// Get the player whose flag has fallen at the given time, if any.
+    pub fn flagged_at(&self, _now: Instant) -> Option<Colour> {
+        [Colour::White, Colour::Black].iter().copied().find(|&c| self.remaining_at(c, _now).is_zero())
+    }
+
+    /// Get the player whose flag has fallen, if any.
+    pub fn flagged(&self) -> Option<Colour> {
+        self.flagged_at(Instant::now())
+    }
+
+    /// Stop the clock at the given time, charging the running player for the time used.
+    pub fn stop_at(&mut self, _now: Instant) {
+        if let Some((colour, _)) = self.running {
+            self.remaining[colour_index(colour)] = self.remaining_at(colour, _now);
+            self.running = None;
+        }
+    }
+
+    /// Stop the clock, charging the running player for the time used.
+    pub fn stop(&mut self) {
+        self.stop_at(Instant::now())
+    }
+
+    /// Start a player's time at the given time, stopping the other player's time first.
+    pub fn start_at(&mut self, _colour: Colour, _now: Instant) {
+        self.stop_at(_now);
+        self.running = Some((_colour, _now));
+    }
+
+    /// Start a player's time, stopping the other player's time first.
+    pub fn start(&mut self, _colour: Colour) {
+        self.start_at(_colour, Instant::now())
+    }
+
+    /// End the running player's move at the given time and start the opponent's time.
+    /// Returns false if the running player's flag had already fallen, or no time was running.
+    pub fn press_at(&mut self, _now: Instant) -> bool {
+        let (colour, since) = match self.running {
+            Some(r) => r,
+            None => return false
+        };
+        let remaining = self.remaining_at(colour, _now);
+        if remaining.is_zero() { return false }
+
+        let index = colour_index(colour);
+        let elapsed = _now.saturating_duration_since(since);
+        self.remaining[index] = remaining + match self.bonus(colour) {
+            Bonus::Fischer(increment) => increment,
+            Bonus::Bronstein(delay) => delay.min(elapsed),
+            _ => Duration::ZERO
+        };
+
+        // completing the moves of a stage adds the time of the next one, and the last stage repeats
+        self.moves[index] += 1;
+        let stages = &self.control.stages;
+        if let Some(Stage { moves: Some(moves), .. }) = stages.get(self.stage[index]) {
+            if self.moves[index] == *moves {
+                self.stage[index] = (self.stage[index] + 1).min(stages.len() - 1);
+                self.moves[index] = 0;
+                self.remaining[index] += stages[self.stage[index]].time;
+            }
+        }
+
+        self.running = Some((opponent(colour), _now));
+        true
+    }
+
+    /// End the running player's move and start the opponent's time.
+    /// Returns false if the running player's flag had already fallen, or no time was running.
+    pub fn press(&mut self) -> bool {
+        self.press_at(Instant::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::super::{Colour, Game, Termination};
+    use super::{Bonus, Clock, TimeControl};
+
+    #[test]
+    fn clock() {
+        let secs = Duration::from_secs;
+        let start = Instant::now();
+
+        let control = TimeControl::parse("40/5400+30:1800+30").unwrap();
+        assert_eq!(control.stages.len(), 2);
+        assert_eq!(control.stages[0].bonus, Bonus::Fischer(secs(30)));
+        assert_eq!(control.to_string(), "40/5400+30:1800+30");
+        assert!(TimeControl::parse("?").is_none());
+
+        // the next stage starts after the 40th move
+        let mut clock = Clock::new(control);
+        clock.start_at(Colour::White, start);
+        for i in 1..=40 {
+            clock.press_at(start + secs(100 * i));
+            clock.press_at(start + secs(100 * i));
+        }
+        assert_eq!(clock.remaining_at(Colour::White, start + secs(4000)), secs(5400 - 4000 + 40 * 30 + 1800));
+
+        // a Bronstein delay gives back at most the time used, a simple delay is waited out first
+        let mut clock = Clock::new(TimeControl::bronstein(secs(60), secs(5)));
+        clock.start_at(Colour::White, start);
+        clock.press_at(start + secs(3));
+        clock.press_at(start + secs(13));
+        assert_eq!(clock.remaining_at(Colour::White, start + secs(13)), secs(60));
+        assert_eq!(clock.remaining_at(Colour::Black, start + secs(13)), secs(55));
+        let mut clock = Clock::new(TimeControl::delay(secs(60), secs(5)));
+        clock.start_at(Colour::White, start);
+        assert_eq!(clock.remaining_at(Colour::White, start + secs(4)), secs(60));
+        assert_eq!(clock.remaining_at(Colour::White, start + secs(10)), secs(55));
+        assert_eq!(clock.flagged_at(start + secs(65)), Some(Colour::White));
+        assert!(!clock.press_at(start + secs(65)));
+
+        // a flag fall loses, unless the opponent has nothing left to mate with
+        let mut game = Game::new();
+        game.set_clock(TimeControl::sudden_death(Duration::ZERO));
+        assert_eq!(game.make_move("e2".to_string(), "e4".to_string()), None);
+        let outcome = game.get_outcome().unwrap();
+        assert_eq!((outcome.winner, outcome.termination), (Some(Colour::Black), Termination::Timeout));
+        let mut game = Game::from_fen("8/8/8/3k4/8/8/4K3/Q7 w - - 0 1").unwrap();
+        game.set_clock(TimeControl::sudden_death(Duration::ZERO));
+        assert_eq!(game.check_time().unwrap().winner, None);
+    }
+}
@@ -0,0 +1,273 @@
+//! Searching a collection of games for positions.
+//!
+//! Every position of every game is kept with its Polyglot key, about 130 bytes a
+//! position, so that searches do not need to replay the games. Exact positions
+//! are also indexed by key, so they are found without going through the games.
+
+use std::collections::HashMap;
+
+use super::pgn::Pgn;
+use super::{check_position, decode_position, Colour, Game, Piece, FILES};
+
+/// The pieces of one side, other than the king.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Material {
+    pub pawns: u8,
+    pub knights: u8,
+    pub bishops: u8,
+    pub rooks: u8,
+    pub queens: u8
+}
+
+impl Material {
+    fn from_board(_board: &[[Option<Piece>; 8]; 8], _colour: Colour) -> Material {
+        let mut material = Material::default();
+        for piece in _board.iter().flatten().flatten().filter(|p| p.unwrap() == _colour) {
+            match piece {
+                Piece::Pawn(_) => material.pawns += 1,
+                Piece::Knight(_) => material.knights += 1,
+                Piece::Bishop(_) => material.bishops += 1,
+                Piece::Rook(_) => material.rooks += 1,
+                Piece::Queen(_) => material.queens += 1,
+                Piece::King(_) => ()
+            }
+        }
+        material
+    }
+
+    /// Get the material of a colour in the current position of a game.
+    pub fn of(_game: &Game, _colour: Colour) -> Material {
+        Material::from_board(&_game.board, _colour)
+    }
+
+    /// Get the value of the material in pawns, counting 3 for a knight or bishop, 5 for a rook and 9 for a queen.
+    pub fn value(&self) -> i32 {
+        self.pawns as i32 + 3 * (self.knights + self.bishops) as i32 + 5 * self.rooks as i32 + 9 * self.queens as i32
+    }
+}
+
+/// What a position has to match to be found.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Query {
+    /// The position with the given Polyglot key.
+    Position(u64),
+    /// Exactly the given material for white and black.
+    Material { white: Material, black: Material },
+    /// White's material less black's, in pawns.
+    Balance(i32),
+    /// The piece on any of the squares.
+    PieceOn { piece: Piece, squares: Vec<String> },
+    /// Each side has a single bishop, and they move on squares of different colours.
+    OppositeBishops,
+    /// All of the queries.
+    All(Vec<Query>)
+}
+
+impl Query {
+    /// Match the current position of a game.
+    pub fn position(_game: &Game) -> Query {
+        Query::Position(_game.polyglot_key())
+    }
+
+    /// Match the piece anywhere on a rank, numbered 1 to 8.
+    pub fn on_rank(_piece: Piece, _rank: usize) -> Query {
+        Query::PieceOn { piece: _piece, squares: FILES.iter().map(|f| format!("{}{}", f, _rank)).collect() }
+    }
+}
+
+/// A position found in the database.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Hit {
+    /// The index of the game, in the order the games were added.
+    pub game: usize,
+    /// The number of moves played in the game before the position, 0 for the starting position.
+    pub ply: usize
+}
+
+// a position of a game, as kept in the database
+#[derive(Clone)]
+struct Entry {
+    key: u64,
+    board: [[Option<Piece>; 8]; 8]
+}
+
+fn matches(_query: &Query, _entry: &Entry) -> bool {
+    let board = &_entry.board;
+    match _query {
+        Query::Position(key) => _entry.key == *key,
+        Query::Material { white, black } => {
+            Material::from_board(board, Colour::White) == *white && Material::from_board(board, Colour::Black) == *black
+        },
+        Query::Balance(balance) => {
+            Material::from_board(board, Colour::White).value() - Material::from_board(board, Colour::Black).value() == *balance
+        },
+        Query::PieceOn { piece, squares } => squares.iter().any(|s| {
+            check_position(s) && {
+                let square = decode_position(s);
+                board[square[0]][square[1]] == Some(*piece)
+            }
+        }),
+        Query::OppositeBishops => {
+            // the colour of a square is given by whether its rank and file add up to an even number
+            let bishops = |colour: Colour| -> Vec<usize> {
+                let mut squares: Vec<usize> = Vec::default();
+                for (rank, row) in board.iter().enumerate() {
+                    for (file, square) in row.iter().enumerate() {
+                        if *square == Some(Piece::Bishop(colour)) {
+                            squares.push((rank + file) % 2);
+                        }
+                    }
+                }
+                squares
+            };
+            match (bishops(Colour::White).as_slice(), bishops(Colour::Black).as_slice()) {
+                ([white], [black]) => white != black,
+                _ => false
+            }
+        },
+        Query::All(queries) => queries.iter().all(|q| matches(q, _entry))
+    }
+}
+
+/// A collection of games that can be searched by position.
+#[derive(Clone, Default)]
+pub struct Database {
+    games: Vec<Pgn>,
+    positions: Vec<Vec<Entry>>,
+    keys: HashMap<u64, Vec<Hit>>
+}
+
+impl Database {
+    /// A database without any games.
+    pub fn new() -> Database {
+        Database::default()
+    }
+
+    /// Build a database from a collection of games in PGN.
+    pub fn from_pgn(_pgns: &str) -> Database {
+        let mut database = Database::new();
+        database.add_pgn(_pgns);
+        database
+    }
+
+    /// Get the number of games in the database.
+    pub fn len(&self) -> usize {
+        self.games.len()
+    }
+
+    /// Check whether the database has no games.
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+
+    /// Get a game of the database by its index.
+    pub fn get(&self, _game: usize) -> Option<&Pgn> {
+        self.games.get(_game)
+    }
+
+    // add a game that has been replayed, with the PGN it is referred to by
+    fn add(&mut self, _pgn: Pgn, _game: &Game) -> usize {
+        let index = self.games.len();
+        let mut entries: Vec<Entry> = _game.history.iter().map(|r| Entry { key: r.before.polyglot_key(), board: r.before.board }).collect();
+        entries.push(Entry { key: _game.polyglot_key(), board: _game.board });
+        for (ply, entry) in entries.iter().enumerate() {
+            let hits = self.keys.entry(entry.key).or_default();
+            // a position repeated in a game is found at the first time it was reached
+            if hits.last().map(|h| h.game) != Some(index) {
+                hits.push(Hit { game: index, ply });
+            }
+        }
+        self.games.push(_pgn);
+        self.positions.push(entries);
+        index
+    }
+
+    /// Add a game, and get its index.
+    pub fn add_game(&mut self, _game: &Game) -> usize {
+        self.add(Pgn::from_game(_game), _game)
+    }
+
+    /// Add every game in a collection of games in PGN, and get the number of games
+    /// added. Games that cannot be replayed are left out.
+    pub fn add_pgn(&mut self, _pgns: &str) -> usize {
+        let mut added = 0;
+        for pgn in Pgn::parse_all(_pgns) {
+            if let Some(game) = pgn.replay() {
+                self.add(pgn, &game);
+                added += 1;
+            }
+        }
+        added
+    }
+
+    /// Find the games with a position matching the query, each at the first position that matches.
+    pub fn search(&self, _query: &Query) -> Vec<Hit> {
+        if let Query::Position(key) = _query {
+            return self.keys.get(key).cloned().unwrap_or_default()
+        }
+        self.positions.iter().enumerate().filter_map(|(game, entries)| {
+            entries.iter().position(|e| matches(_query, e)).map(|ply| Hit { game, ply })
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Colour, Game, Piece};
+    use super::{Database, Hit, Material, Query};
+
+    static PGNS: &str = "[White \"A\"]\n\n1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Bxc6 dxc6 *\n\n\
+        [White \"B\"]\n\n1. Nf3 Nc6 2. e4 e5 *\n\n\
+        [White \"C\"]\n\n1. d4 d5 2. c4 dxc4 3. e4 e5 4. dxe5 Qxd1+ 5. Kxd1 *\n";
+
+    #[test]
+    fn games() {
+        let database = Database::from_pgn(PGNS);
+        assert_eq!(database.len(), 3);
+        assert_eq!(database.get(1).unwrap().get_tag("White"), Some("B"));
+    }
+
+    #[test]
+    fn positions() {
+        // the second game reaches the position of the first by another order of moves
+        let database = Database::from_pgn(PGNS);
+        let mut game = Game::new();
+        for san in ["e4", "e5", "Nf3", "Nc6"] {
+            game.make_san_move(san);
+        }
+        assert_eq!(database.search(&Query::position(&game)), vec![Hit { game: 0, ply: 4 }, Hit { game: 1, ply: 4 }]);
+        assert_eq!(database.search(&Query::position(&Game::new())).len(), 3);
+    }
+
+    #[test]
+    fn material() {
+        // material is checked exactly, or as the difference between the sides
+        let database = Database::from_pgn(PGNS);
+        let full = Material::of(&Game::new(), Colour::White);
+        let white = Material { bishops: 1, ..full };
+        let black = Material { knights: 1, ..full };
+        assert_eq!(database.search(&Query::Material { white, black }), vec![Hit { game: 0, ply: 8 }]);
+        assert_eq!(database.search(&Query::Balance(3)), vec![Hit { game: 0, ply: 7 }]);
+        assert_eq!(database.search(&Query::Balance(-1)), vec![Hit { game: 2, ply: 4 }]);
+    }
+
+    #[test]
+    fn piece_placement() {
+        // the king on d1 once the queens are traded
+        let database = Database::from_pgn(PGNS);
+        let query = Query::All(vec![
+            Query::PieceOn { piece: Piece::King(Colour::White), squares: vec!["d1".to_string()] },
+            Query::Balance(0)
+        ]);
+        assert_eq!(database.search(&query), vec![Hit { game: 2, ply: 9 }]);
+        assert_eq!(database.search(&Query::on_rank(Piece::Pawn(Colour::White), 5)), vec![Hit { game: 2, ply: 7 }]);
+    }
+
+    #[test]
+    fn opposite_bishops() {
+        let mut database = Database::from_pgn(PGNS);
+        database.add_game(&Game::from_fen("2b3k1/1R6/8/8/8/8/8/4B1K1 w - - 0 1").unwrap());
+        let query = Query::All(vec![Query::on_rank(Piece::Rook(Colour::White), 7), Query::OppositeBishops]);
+        assert_eq!(database.search(&query), vec![Hit { game: 3, ply: 0 }]);
+    }
+}
@@ -0,0 +1,656 @@
+//! Classifying openings by their code in the Encyclopaedia of Chess Openings (ECO).
+//!
+//! Each opening in the table is given by the moves that reach it. The positions
+//! they reach are looked up by their Polyglot key, so a game that gets there by
+//! another order of moves is classified the same way. Only positions up to the
+//! length of the longest line in the table are looked up.
+//!
+//! The table holds all 500 codes from A00 to E99, each with the line that opens
+//! it, and a few named openings that share a code with their neighbours. Deeper
+//! variations within a code are given the name of the line they branch off from.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use super::Game;
+
+/// An opening as named in the ECO table.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Opening {
+    /// The ECO code, e.g. "C65".
+    pub eco: &'static str,
+    pub name: &'static str
+}
+
+// the openings with the moves that reach them, in SAN
+static OPENINGS: &[(&str, &str, &str)] = &[
+    ("A00", "Polish Opening", "b4"),
+    ("A00", "Grob Opening", "g4"),
+    ("A00", "Van't Kruijs Opening", "e3"),
+    ("A00", "Mieses Opening", "d3"),
+    ("A00", "Hungarian Opening", "g3"),
+    ("A00", "Barnes Opening", "f3"),
+    ("A00", "Amar Opening", "Nh3"),
+    ("A00", "Sodium Attack", "Na3"),
+    ("A00", "Saragossa Opening", "c3"),
+    ("A00", "Anderssen's Opening", "a3"),
+    ("A01", "Nimzo-Larsen Attack", "b3"),
+    ("A02", "Bird Opening", "f4"),
+    ("A02", "Bird Opening: From's Gambit", "f4 e5"),
+    ("A03", "Bird Opening: Dutch Variation", "f4 d5"),
+    ("A04", "Zukertort Opening", "Nf3"),
+    ("A05", "Zukertort Opening", "Nf3 Nf6"),
+    ("A06", "Zukertort Opening", "Nf3 d5"),
+    ("A07", "King's Indian Attack", "Nf3 d5 g3"),
+    ("A08", "King's Indian Attack: Sicilian Variation", "Nf3 d5 g3 c5 Bg2"),
+    ("A09", "Réti Opening", "Nf3 d5 c4"),
+    ("A10", "English Opening", "c4"),
+    ("A11", "English Opening: Caro-Kann Defensive System", "c4 c6"),
+    ("A12", "English Opening: Caro-Kann Defensive System", "c4 c6 Nf3 d5 b3"),
+    ("A13", "English Opening: Agincourt Defense", "c4 e6"),
+    ("A14", "English Opening: Agincourt Defense, Neo-Catalan Declined", "c4 e6 Nf3 d5 g3 Nf6 Bg2 Be7 O-O"),
+    ("A15", "English Opening: Anglo-Indian Defense", "c4 Nf6"),
+    ("A16", "English Opening: Anglo-Indian Defense, Queen's Knight Variation", "c4 Nf6 Nc3"),
+    ("A17", "English Opening: Anglo-Indian Defense, Hedgehog System", "c4 Nf6 Nc3 e6"),
+    ("A18", "English Opening: Mikenas-Carls Variation", "c4 Nf6 Nc3 e6 e4"),
+    ("A19", "English Opening: Mikenas-Carls Variation, Sicilian", "c4 Nf6 Nc3 e6 e4 c5"),
+    ("A20", "English Opening: King's English Variation", "c4 e5"),
+    ("A21", "English Opening: King's English Variation, Reversed Sicilian", "c4 e5 Nc3"),
+    ("A22", "English Opening: King's English Variation, Two Knights Variation", "c4 e5 Nc3 Nf6"),
+    ("A23", "English Opening: King's English Variation, Bremen System, Keres Variation", "c4 e5 Nc3 Nf6 g3 c6"),
+    ("A24", "English Opening: King's English Variation, Bremen System with ...g6", "c4 e5 Nc3 Nf6 g3 g6"),
+    ("A25", "English Opening: King's English Variation, Sicilian Reversed", "c4 e5 Nc3 Nc6"),
+    ("A26", "English Opening: King's English Variation, Closed System", "c4 e5 Nc3 Nc6 g3 g6 Bg2 Bg7 d3 d6"),
+    ("A27", "English Opening: King's English Variation, Three Knights System", "c4 e5 Nc3 Nc6 Nf3"),
+    ("A28", "English Opening: King's English Variation, Four Knights Variation", "c4 e5 Nc3 Nc6 Nf3 Nf6"),
+    ("A29", "English Opening: King's English Variation, Four Knights, Fianchetto", "c4 e5 Nc3 Nc6 Nf3 Nf6 g3"),
+    ("A30", "English Opening: Symmetrical Variation", "c4 c5"),
+    ("A31", "English Opening: Symmetrical Variation, Anti-Benoni", "c4 c5 Nf3 Nf6 d4"),
+    ("A32", "English Opening: Symmetrical Variation, Anti-Benoni Variation", "c4 c5 Nf3 Nf6 d4 cxd4 Nxd4 e6"),
+    ("A33", "English Opening: Symmetrical Variation, Anti-Benoni, Spielmann", "c4 c5 Nf3 Nf6 d4 cxd4 Nxd4 e6 Nc3 Nc6"),
+    ("A34", "English Opening: Symmetrical Variation, Normal Variation", "c4 c5 Nc3"),
+    ("A35", "English Opening: Symmetrical Variation, Two Knights Variation", "c4 c5 Nc3 Nc6"),
+    ("A36", "English Opening: Symmetrical Variation, Fianchetto Variation", "c4 c5 Nc3 Nc6 g3"),
+    ("A37", "English Opening: Symmetrical Variation, Two Knights Line", "c4 c5 Nc3 Nc6 g3 g6 Bg2 Bg7 Nf3"),
+    ("A38", "English Opening: Symmetrical Variation, Full Symmetry Line", "c4 c5 Nc3 Nc6 g3 g6 Bg2 Bg7 Nf3 Nf6"),
+    ("A39", "English Opening: Symmetrical Variation, Mecking Variation", "c4 c5 Nc3 Nc6 g3 g6 Bg2 Bg7 Nf3 Nf6 O-O O-O d4"),
+    ("A40", "Queen's Pawn Game", "d4"),
+    ("A40", "Englund Gambit", "d4 e5"),
+    ("A41", "Queen's Pawn Game", "d4 d6"),
+    ("A42", "Modern Defense: Averbakh System", "d4 d6 c4 g6 Nc3 Bg7 e4"),
+    ("A43", "Benoni Defense: Old Benoni", "d4 c5"),
+    ("A44", "Benoni Defense: Old Benoni, Czech Benoni", "d4 c5 d5 e5"),
+    ("A45", "Indian Defense", "d4 Nf6"),
+    ("A45", "Trompowsky Attack", "d4 Nf6 Bg5"),
+    ("A46", "Indian Defense: Knights Variation", "d4 Nf6 Nf3"),
+    ("A47", "Queen's Indian Defense", "d4 Nf6 Nf3 b6"),
+    ("A48", "East Indian Defense", "d4 Nf6 Nf3 g6"),
+    ("A49", "King's Indian Defense: Fianchetto Variation, Immediate Fianchetto", "d4 Nf6 Nf3 g6 g3"),
+    ("A50", "Indian Defense: Normal Variation", "d4 Nf6 c4"),
+    ("A51", "Budapest Defense", "d4 Nf6 c4 e5"),
+    ("A52", "Budapest Defense: Adler Variation", "d4 Nf6 c4 e5 dxe5 Ng4"),
+    ("A53", "Old Indian Defense", "d4 Nf6 c4 d6"),
+    ("A54", "Old Indian Defense: Ukrainian Variation", "d4 Nf6 c4 d6 Nc3 e5"),
+    ("A55", "Old Indian Defense: Normal Variation", "d4 Nf6 c4 d6 Nc3 e5 Nf3 Nbd7 e4"),
+    ("A56", "Benoni Defense", "d4 Nf6 c4 c5"),
+    ("A57", "Benko Gambit", "d4 Nf6 c4 c5 d5 b5"),
+    ("A58", "Benko Gambit Accepted: Fully Accepted Variation", "d4 Nf6 c4 c5 d5 b5 cxb5 a6 bxa6"),
+    ("A59", "Benko Gambit Accepted: King Walk Variation", "d4 Nf6 c4 c5 d5 b5 cxb5 a6 bxa6 Bxa6 Nc3 d6 e4"),
+    ("A60", "Benoni Defense: Modern Variation", "d4 Nf6 c4 c5 d5 e6"),
+    ("A61", "Benoni Defense", "d4 Nf6 c4 c5 d5 e6 Nc3 exd5 cxd5 d6 Nf3 g6"),
+    ("A62", "Benoni Defense: Fianchetto Variation", "d4 Nf6 c4 c5 d5 e6 Nc3 exd5 cxd5 d6 Nf3 g6 g3 Bg7 Bg2 O-O"),
+    ("A63", "Benoni Defense: Fianchetto Variation, Hastings Defense", "d4 Nf6 c4 c5 d5 e6 Nc3 exd5 cxd5 d6 Nf3 g6 g3 Bg7 Bg2 O-O O-O Nbd7"),
+    ("A64", "Benoni Defense: Fianchetto Variation, Hastings Defense, Main Line", "d4 Nf6 c4 c5 d5 e6 Nc3 exd5 cxd5 d6 Nf3 g6 g3 Bg7 Bg2 O-O O-O Nbd7 Nd2 a6 a4 Re8"),
+    ("A65", "Benoni Defense: King's Pawn Line", "d4 Nf6 c4 c5 d5 e6 Nc3 exd5 cxd5 d6 e4"),
+    ("A66", "Benoni Defense: Pawn Storm Variation", "d4 Nf6 c4 c5 d5 e6 Nc3 exd5 cxd5 d6 e4 g6 f4"),
+    ("A67", "Benoni Defense: Taimanov Variation", "d4 Nf6 c4 c5 d5 e6 Nc3 exd5 cxd5 d6 e4 g6 f4 Bg7 Bb5+"),
+    ("A68", "Benoni Defense: Four Pawns Attack", "d4 Nf6 c4 c5 d5 e6 Nc3 exd5 cxd5 d6 e4 g6 f4 Bg7 Nf3 O-O"),
+    ("A69", "Benoni Defense: Four Pawns Attack, Main Line", "d4 Nf6 c4 c5 d5 e6 Nc3 exd5 cxd5 d6 e4 g6 f4 Bg7 Nf3 O-O Be2 Re8"),
+    ("A70", "Benoni Defense: Classical Variation", "d4 Nf6 c4 c5 d5 e6 Nc3 exd5 cxd5 d6 e4 g6 Nf3"),
+    ("A71", "Benoni Defense: Classical Variation, Averbakh-Grivas Attack", "d4 Nf6 c4 c5 d5 e6 Nc3 exd5 cxd5 d6 e4 g6 Nf3 Bg7 Bg5"),
+    ("A72", "Benoni Defense: Classical Variation, Traditional Variation", "d4 Nf6 c4 c5 d5 e6 Nc3 exd5 cxd5 d6 e4 g6 Nf3 Bg7 Be2 O-O"),
+    ("A73", "Benoni Defense: Classical Variation, Main Line", "d4 Nf6 c4 c5 d5 e6 Nc3 exd5 cxd5 d6 e4 g6 Nf3 Bg7 Be2 O-O O-O"),
+    ("A74", "Benoni Defense: Classical Variation, Full Line", "d4 Nf6 c4 c5 d5 e6 Nc3 exd5 cxd5 d6 e4 g6 Nf3 Bg7 Be2 O-O O-O a6 a4"),
+    ("A75", "Benoni Defense: Classical Variation, Argentine Counterattack", "d4 Nf6 c4 c5 d5 e6 Nc3 exd5 cxd5 d6 e4 g6 Nf3 Bg7 Be2 O-O O-O a6 a4 Bg4"),
+    ("A76", "Benoni Defense: Classical Variation, Czerniak Defense", "d4 Nf6 c4 c5 d5 e6 Nc3 exd5 cxd5 d6 e4 g6 Nf3 Bg7 Be2 O-O O-O Re8"),
+    ("A77", "Benoni Defense: Classical Variation, Czerniak Defense, Tal Line", "d4 Nf6 c4 c5 d5 e6 Nc3 exd5 cxd5 d6 e4 g6 Nf3 Bg7 Be2 O-O O-O Re8 Nd2"),
+    ("A78", "Benoni Defense: Classical Variation, Czerniak Defense, Na6", "d4 Nf6 c4 c5 d5 e6 Nc3 exd5 cxd5 d6 e4 g6 Nf3 Bg7 Be2 O-O O-O Re8 Nd2 Na6"),
+    ("A79", "Benoni Defense: Classical Variation, Czerniak Defense, 11.f3", "d4 Nf6 c4 c5 d5 e6 Nc3 exd5 cxd5 d6 e4 g6 Nf3 Bg7 Be2 O-O O-O Re8 Nd2 Na6 f3"),
+    ("A80", "Dutch Defense", "d4 f5"),
+    ("A81", "Dutch Defense: Fianchetto Attack", "d4 f5 g3"),
+    ("A82", "Dutch Defense: Staunton Gambit", "d4 f5 e4"),
+    ("A83", "Dutch Defense: Staunton Gambit, Lasker Variation", "d4 f5 e4 fxe4 Nc3 Nf6 Bg5"),
+    ("A84", "Dutch Defense: Normal Variation", "d4 f5 c4"),
+    ("A85", "Dutch Defense: Queen's Knight Variation", "d4 f5 c4 Nf6 Nc3"),
+    ("A86", "Dutch Defense: Fianchetto Variation", "d4 f5 c4 Nf6 g3"),
+    ("A87", "Dutch Defense: Leningrad Variation", "d4 f5 c4 Nf6 g3 g6 Bg2 Bg7 Nf3"),
+    ("A88", "Dutch Defense: Leningrad Variation, Warsaw Variation", "d4 f5 c4 Nf6 g3 g6 Bg2 Bg7 Nf3 O-O O-O d6 Nc3 c6"),
+    ("A89", "Dutch Defense: Leningrad Variation, Matulovic Variation", "d4 f5 c4 Nf6 g3 g6 Bg2 Bg7 Nf3 O-O O-O d6 Nc3 Nc6"),
+    ("A90", "Dutch Defense: Classical Variation", "d4 f5 c4 Nf6 g3 e6 Bg2"),
+    ("A91", "Dutch Defense: Classical Variation, Blackburne Variation", "d4 f5 c4 Nf6 g3 e6 Bg2 Be7"),
+    ("A92", "Dutch Defense: Classical Variation, Main Line", "d4 f5 c4 Nf6 g3 e6 Bg2 Be7 Nf3 O-O"),
+    ("A93", "Dutch Defense: Stonewall Variation, Botvinnik Variation", "d4 f5 c4 Nf6 g3 e6 Bg2 Be7 Nf3 O-O O-O d5 b3"),
+    ("A94", "Dutch Defense: Stonewall Variation, Modern Variation", "d4 f5 c4 Nf6 g3 e6 Bg2 Be7 Nf3 O-O O-O d5 b3 c6 Ba3"),
+    ("A95", "Dutch Defense: Stonewall Variation", "d4 f5 c4 Nf6 g3 e6 Bg2 Be7 Nf3 O-O O-O d5 Nc3 c6"),
+    ("A96", "Dutch Defense: Classical Variation, Modern Variation", "d4 f5 c4 Nf6 g3 e6 Bg2 Be7 Nf3 O-O O-O d6"),
+    ("A97", "Dutch Defense: Ilyin-Zhenevsky Variation", "d4 f5 c4 Nf6 g3 e6 Bg2 Be7 Nf3 O-O O-O d6 Nc3 Qe8"),
+    ("A98", "Dutch Defense: Ilyin-Zhenevsky Variation, Alatortsev-Lisitsyn Line", "d4 f5 c4 Nf6 g3 e6 Bg2 Be7 Nf3 O-O O-O d6 Nc3 Qe8 Qc2"),
+    ("A99", "Dutch Defense: Ilyin-Zhenevsky Variation, Modern Main Line", "d4 f5 c4 Nf6 g3 e6 Bg2 Be7 Nf3 O-O O-O d6 Nc3 Qe8 b3"),
+    ("B00", "King's Pawn Game", "e4"),
+    ("B00", "Nimzowitsch Defense", "e4 Nc6"),
+    ("B00", "Owen Defense", "e4 b6"),
+    ("B01", "Scandinavian Defense", "e4 d5"),
+    ("B01", "Scandinavian Defense: Mieses-Kotroc Variation", "e4 d5 exd5 Qxd5"),
+    ("B02", "Alekhine Defense", "e4 Nf6"),
+    ("B03", "Alekhine Defense", "e4 Nf6 e5 Nd5 d4"),
+    ("B04", "Alekhine Defense: Modern Variation", "e4 Nf6 e5 Nd5 d4 d6 Nf3"),
+    ("B05", "Alekhine Defense: Modern Variation, Main Line", "e4 Nf6 e5 Nd5 d4 d6 Nf3 Bg4"),
+    ("B06", "Modern Defense", "e4 g6"),
+    ("B07", "Pirc Defense", "e4 d6 d4 Nf6"),
+    ("B08", "Pirc Defense: Classical Variation", "e4 d6 d4 Nf6 Nc3 g6 Nf3"),
+    ("B09", "Pirc Defense: Austrian Attack", "e4 d6 d4 Nf6 Nc3 g6 f4"),
+    ("B10", "Caro-Kann Defense", "e4 c6"),
+    ("B11", "Caro-Kann Defense: Two Knights Attack, Mindeno Variation", "e4 c6 Nc3 d5 Nf3 Bg4"),
+    ("B12", "Caro-Kann Defense", "e4 c6 d4 d5"),
+    ("B12", "Caro-Kann Defense: Advance Variation", "e4 c6 d4 d5 e5"),
+    ("B13", "Caro-Kann Defense: Exchange Variation", "e4 c6 d4 d5 exd5 cxd5"),
+    ("B14", "Caro-Kann Defense: Panov Attack", "e4 c6 d4 d5 exd5 cxd5 c4 Nf6 Nc3 e6"),
+    ("B15", "Caro-Kann Defense", "e4 c6 d4 d5 Nc3"),
+    ("B16", "Caro-Kann Defense: Bronstein-Larsen Variation", "e4 c6 d4 d5 Nc3 dxe4 Nxe4 Nf6 Nxf6+ gxf6"),
+    ("B17", "Caro-Kann Defense: Karpov Variation", "e4 c6 d4 d5 Nc3 dxe4 Nxe4 Nd7"),
+    ("B18", "Caro-Kann Defense: Classical Variation", "e4 c6 d4 d5 Nc3 dxe4 Nxe4 Bf5"),
+    ("B19", "Caro-Kann Defense: Classical Variation, Spassky Variation", "e4 c6 d4 d5 Nc3 dxe4 Nxe4 Bf5 Ng3 Bg6 h4 h6 Nf3 Nd7"),
+    ("B20", "Sicilian Defense", "e4 c5"),
+    ("B21", "Sicilian Defense: McDonnell Attack", "e4 c5 f4"),
+    ("B21", "Sicilian Defense: Smith-Morra Gambit", "e4 c5 d4 cxd4 c3"),
+    ("B22", "Sicilian Defense: Alapin Variation", "e4 c5 c3"),
+    ("B23", "Sicilian Defense: Closed", "e4 c5 Nc3"),
+    ("B24", "Sicilian Defense: Closed", "e4 c5 Nc3 Nc6 g3"),
+    ("B25", "Sicilian Defense: Closed, Traditional", "e4 c5 Nc3 Nc6 g3 g6 Bg2 Bg7 d3 d6"),
+    ("B26", "Sicilian Defense: Closed, 6.Be3", "e4 c5 Nc3 Nc6 g3 g6 Bg2 Bg7 d3 d6 Be3"),
+    ("B27", "Sicilian Defense", "e4 c5 Nf3"),
+    ("B28", "Sicilian Defense: O'Kelly Variation", "e4 c5 Nf3 a6"),
+    ("B29", "Sicilian Defense: Nimzowitsch Variation", "e4 c5 Nf3 Nf6"),
+    ("B30", "Sicilian Defense: Old Sicilian", "e4 c5 Nf3 Nc6"),
+    ("B31", "Sicilian Defense: Nyezhmetdinov-Rossolimo Attack", "e4 c5 Nf3 Nc6 Bb5 g6"),
+    ("B32", "Sicilian Defense: Open", "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4"),
+    ("B33", "Sicilian Defense: Lasker-Pelikan Variation", "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4 Nf6 Nc3 e5"),
+    ("B34", "Sicilian Defense: Accelerated Dragon, Exchange Variation", "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4 g6 Nxc6"),
+    ("B35", "Sicilian Defense: Accelerated Dragon, Modern Bc4 Variation", "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4 g6 Nc3 Bg7 Be3 Nf6 Bc4"),
+    ("B36", "Sicilian Defense: Accelerated Dragon, Maróczy Bind", "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4 g6 c4"),
+    ("B37", "Sicilian Defense: Accelerated Dragon, Maróczy Bind", "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4 g6 c4 Bg7"),
+    ("B38", "Sicilian Defense: Accelerated Dragon, Maróczy Bind, 6.Be3", "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4 g6 c4 Bg7 Be3"),
+    ("B39", "Sicilian Defense: Accelerated Dragon, Maróczy Bind, Breyer Variation", "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4 g6 c4 Bg7 Be3 Nf6 Nc3 Ng4"),
+    ("B40", "Sicilian Defense: French Variation", "e4 c5 Nf3 e6"),
+    ("B41", "Sicilian Defense: Kan Variation", "e4 c5 Nf3 e6 d4 cxd4 Nxd4 a6"),
+    ("B42", "Sicilian Defense: Kan Variation, Modern Variation", "e4 c5 Nf3 e6 d4 cxd4 Nxd4 a6 Bd3"),
+    ("B43", "Sicilian Defense: Kan Variation, Wing Attack", "e4 c5 Nf3 e6 d4 cxd4 Nxd4 a6 Nc3"),
+    ("B44", "Sicilian Defense: Taimanov Variation", "e4 c5 Nf3 e6 d4 cxd4 Nxd4 Nc6"),
+    ("B45", "Sicilian Defense: Taimanov Variation, Normal Variation", "e4 c5 Nf3 e6 d4 cxd4 Nxd4 Nc6 Nc3"),
+    ("B46", "Sicilian Defense: Taimanov Variation", "e4 c5 Nf3 e6 d4 cxd4 Nxd4 Nc6 Nc3 a6"),
+    ("B47", "Sicilian Defense: Taimanov Variation, Bastrikov Variation", "e4 c5 Nf3 e6 d4 cxd4 Nxd4 Nc6 Nc3 Qc7"),
+    ("B48", "Sicilian Defense: Taimanov Variation, English Attack", "e4 c5 Nf3 e6 d4 cxd4 Nxd4 Nc6 Nc3 Qc7 Be3"),
+    ("B49", "Sicilian Defense: Taimanov Variation, Normal Line", "e4 c5 Nf3 e6 d4 cxd4 Nxd4 Nc6 Nc3 Qc7 Be3 a6 Be2"),
+    ("B50", "Sicilian Defense: Modern Variations", "e4 c5 Nf3 d6"),
+    ("B51", "Sicilian Defense: Moscow Variation", "e4 c5 Nf3 d6 Bb5+"),
+    ("B52", "Sicilian Defense: Moscow Variation, Main Line", "e4 c5 Nf3 d6 Bb5+ Bd7"),
+    ("B53", "Sicilian Defense: Chekhover Variation", "e4 c5 Nf3 d6 d4 cxd4 Qxd4"),
+    ("B54", "Sicilian Defense: Open", "e4 c5 Nf3 d6 d4 cxd4 Nxd4"),
+    ("B55", "Sicilian Defense: Prins Variation, Venice Attack", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 f3 e5 Bb5+"),
+    ("B56", "Sicilian Defense: Classical Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3"),
+    ("B57", "Sicilian Defense: Classical Variation, Sozin Attack", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 Nc6 Bc4"),
+    ("B58", "Sicilian Defense: Classical Variation, Boleslavsky Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 Nc6 Be2"),
+    ("B59", "Sicilian Defense: Boleslavsky Variation, 7.Nb3", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 Nc6 Be2 e5 Nb3"),
+    ("B60", "Sicilian Defense: Richter-Rauzer Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 Nc6 Bg5"),
+    ("B61", "Sicilian Defense: Richter-Rauzer Variation, Larsen Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 Nc6 Bg5 Bd7 Qd2"),
+    ("B62", "Sicilian Defense: Richter-Rauzer Variation, 6...e6", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 Nc6 Bg5 e6"),
+    ("B63", "Sicilian Defense: Richter-Rauzer Variation, Classical Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 Nc6 Bg5 e6 Qd2 Be7"),
+    ("B64", "Sicilian Defense: Richter-Rauzer Variation, Classical Variation, 9.f4", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 Nc6 Bg5 e6 Qd2 Be7 O-O-O O-O f4"),
+    ("B65", "Sicilian Defense: Richter-Rauzer Variation, Classical Variation, 9...Nxd4", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 Nc6 Bg5 e6 Qd2 Be7 O-O-O O-O f4 Nxd4 Qxd4"),
+    ("B66", "Sicilian Defense: Richter-Rauzer Variation, Modern Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 Nc6 Bg5 e6 Qd2 a6"),
+    ("B67", "Sicilian Defense: Richter-Rauzer Variation, Modern Variation, 8...Bd7", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 Nc6 Bg5 e6 Qd2 a6 O-O-O Bd7"),
+    ("B68", "Sicilian Defense: Richter-Rauzer Variation, Modern Variation, 9.f4 Be7", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 Nc6 Bg5 e6 Qd2 a6 O-O-O Bd7 f4 Be7"),
+    ("B69", "Sicilian Defense: Richter-Rauzer Variation, Modern Variation, 11.Bxf6", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 Nc6 Bg5 e6 Qd2 a6 O-O-O Bd7 f4 Be7 Nf3 b5 Bxf6"),
+    ("B70", "Sicilian Defense: Dragon Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 g6"),
+    ("B71", "Sicilian Defense: Dragon Variation, Levenfish Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 g6 f4"),
+    ("B72", "Sicilian Defense: Dragon Variation, 6.Be3", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 g6 Be3"),
+    ("B73", "Sicilian Defense: Dragon Variation, Classical Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 g6 Be3 Bg7 Be2 Nc6 O-O"),
+    ("B74", "Sicilian Defense: Dragon Variation, Classical Variation, Normal Line", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 g6 Be3 Bg7 Be2 Nc6 O-O O-O Nb3"),
+    ("B75", "Sicilian Defense: Dragon Variation, Yugoslav Attack", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 g6 Be3 Bg7 f3"),
+    ("B76", "Sicilian Defense: Dragon Variation, Yugoslav Attack, 7...O-O", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 g6 Be3 Bg7 f3 O-O"),
+    ("B77", "Sicilian Defense: Dragon Variation, Yugoslav Attack, 9.Bc4", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 g6 Be3 Bg7 f3 O-O Qd2 Nc6 Bc4"),
+    ("B78", "Sicilian Defense: Dragon Variation, Yugoslav Attack, 10.O-O-O", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 g6 Be3 Bg7 f3 O-O Qd2 Nc6 Bc4 Bd7 O-O-O"),
+    ("B79", "Sicilian Defense: Dragon Variation, Yugoslav Attack, 12.h4", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 g6 Be3 Bg7 f3 O-O Qd2 Nc6 Bc4 Bd7 O-O-O Qa5 Bb3 Rfc8 h4"),
+    ("B80", "Sicilian Defense: Scheveningen Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 e6"),
+    ("B81", "Sicilian Defense: Scheveningen Variation, Keres Attack", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 e6 g4"),
+    ("B82", "Sicilian Defense: Scheveningen Variation, 6.f4", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 e6 f4"),
+    ("B83", "Sicilian Defense: Scheveningen Variation, 6.Be2", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 e6 Be2"),
+    ("B84", "Sicilian Defense: Scheveningen Variation, Classical Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 e6 Be2 a6"),
+    ("B85", "Sicilian Defense: Scheveningen Variation, Classical Main Line", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 e6 Be2 a6 O-O Qc7 f4 Nc6"),
+    ("B86", "Sicilian Defense: Sozin Attack", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 e6 Bc4"),
+    ("B87", "Sicilian Defense: Sozin Attack, Flank Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 e6 Bc4 a6 Bb3 b5"),
+    ("B88", "Sicilian Defense: Sozin Attack, Leonhardt Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 e6 Bc4 Nc6"),
+    ("B89", "Sicilian Defense: Sozin Attack, 7.Be3", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 e6 Bc4 Nc6 Be3"),
+    ("B90", "Sicilian Defense: Najdorf Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6"),
+    ("B91", "Sicilian Defense: Najdorf Variation, Zagreb Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6 g3"),
+    ("B92", "Sicilian Defense: Najdorf Variation, Opocensky Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6 Be2"),
+    ("B93", "Sicilian Defense: Najdorf Variation, Amsterdam Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6 f4"),
+    ("B94", "Sicilian Defense: Najdorf Variation, 6.Bg5", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6 Bg5"),
+    ("B95", "Sicilian Defense: Najdorf Variation, 6...e6", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6 Bg5 e6"),
+    ("B96", "Sicilian Defense: Najdorf Variation, 7.f4", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6 Bg5 e6 f4"),
+    ("B97", "Sicilian Defense: Najdorf Variation, Poisoned Pawn Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6 Bg5 e6 f4 Qb6"),
+    ("B98", "Sicilian Defense: Najdorf Variation, 7...Be7", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6 Bg5 e6 f4 Be7"),
+    ("B99", "Sicilian Defense: Najdorf Variation, Main Line", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6 Bg5 e6 f4 Be7 Qf3 Qc7 O-O-O Nbd7"),
+    ("C00", "French Defense", "e4 e6"),
+    ("C01", "French Defense: Exchange Variation", "e4 e6 d4 d5 exd5 exd5"),
+    ("C02", "French Defense: Advance Variation", "e4 e6 d4 d5 e5"),
+    ("C03", "French Defense: Tarrasch Variation", "e4 e6 d4 d5 Nd2"),
+    ("C04", "French Defense: Tarrasch Variation, Guimard Main Line", "e4 e6 d4 d5 Nd2 Nc6 Ngf3 Nf6"),
+    ("C05", "French Defense: Tarrasch Variation, Closed Variation", "e4 e6 d4 d5 Nd2 Nf6"),
+    ("C06", "French Defense: Tarrasch Variation, Closed Variation, Main Line", "e4 e6 d4 d5 Nd2 Nf6 e5 Nfd7 Bd3 c5 c3 Nc6 Ne2"),
+    ("C07", "French Defense: Tarrasch Variation, Open System", "e4 e6 d4 d5 Nd2 c5"),
+    ("C08", "French Defense: Tarrasch Variation, Open System, Advance Line", "e4 e6 d4 d5 Nd2 c5 exd5 exd5"),
+    ("C09", "French Defense: Tarrasch Variation, Open System, Main Line", "e4 e6 d4 d5 Nd2 c5 exd5 exd5 Ngf3 Nc6"),
+    ("C10", "French Defense: Paulsen Variation", "e4 e6 d4 d5 Nc3"),
+    ("C11", "French Defense: Classical Variation", "e4 e6 d4 d5 Nc3 Nf6"),
+    ("C12", "French Defense: MacCutcheon Variation", "e4 e6 d4 d5 Nc3 Nf6 Bg5 Bb4"),
+    ("C13", "French Defense: Classical Variation, Normal Variation", "e4 e6 d4 d5 Nc3 Nf6 Bg5 Be7"),
+    ("C14", "French Defense: Classical Variation, Main Line", "e4 e6 d4 d5 Nc3 Nf6 Bg5 Be7 e5 Nfd7 Bxe7 Qxe7"),
+    ("C15", "French Defense: Winawer Variation", "e4 e6 d4 d5 Nc3 Bb4"),
+    ("C16", "French Defense: Winawer Variation, Advance Variation", "e4 e6 d4 d5 Nc3 Bb4 e5"),
+    ("C17", "French Defense: Winawer Variation, Advance Variation, 4...c5", "e4 e6 d4 d5 Nc3 Bb4 e5 c5"),
+    ("C18", "French Defense: Winawer Variation, Armenian Variation", "e4 e6 d4 d5 Nc3 Bb4 e5 c5 a3 Bxc3+ bxc3"),
+    ("C19", "French Defense: Winawer Variation, Advance Variation, Main Line", "e4 e6 d4 d5 Nc3 Bb4 e5 c5 a3 Bxc3+ bxc3 Ne7"),
+    ("C20", "King's Pawn Game", "e4 e5"),
+    ("C21", "Center Game", "e4 e5 d4 exd4"),
+    ("C21", "Danish Gambit", "e4 e5 d4 exd4 c3"),
+    ("C22", "Center Game: Normal Variation", "e4 e5 d4 exd4 Qxd4 Nc6"),
+    ("C23", "Bishop's Opening", "e4 e5 Bc4"),
+    ("C24", "Bishop's Opening: Berlin Defense", "e4 e5 Bc4 Nf6"),
+    ("C25", "Vienna Game", "e4 e5 Nc3"),
+    ("C26", "Vienna Game: Falkbeer Variation", "e4 e5 Nc3 Nf6"),
+    ("C27", "Vienna Game: Frankenstein-Dracula Variation", "e4 e5 Nc3 Nf6 Bc4 Nxe4"),
+    ("C28", "Vienna Game: Stanley Variation, Three Knights Variation", "e4 e5 Nc3 Nf6 Bc4 Nc6"),
+    ("C29", "Vienna Game: Vienna Gambit", "e4 e5 Nc3 Nf6 f4"),
+    ("C30", "King's Gambit", "e4 e5 f4"),
+    ("C31", "King's Gambit Declined: Falkbeer Countergambit", "e4 e5 f4 d5"),
+    ("C32", "King's Gambit Declined: Falkbeer Countergambit, Modern Variation", "e4 e5 f4 d5 exd5 e4 d3 Nf6 dxe4"),
+    ("C33", "King's Gambit Accepted", "e4 e5 f4 exf4"),
+    ("C34", "King's Gambit Accepted: King's Knight's Gambit", "e4 e5 f4 exf4 Nf3"),
+    ("C35", "King's Gambit Accepted: Cunningham Defense", "e4 e5 f4 exf4 Nf3 Be7"),
+    ("C36", "King's Gambit Accepted: Modern Defense", "e4 e5 f4 exf4 Nf3 d5"),
+    ("C37", "King's Gambit Accepted: Classical Variation", "e4 e5 f4 exf4 Nf3 g5"),
+    ("C38", "King's Gambit Accepted: Traditional Variation", "e4 e5 f4 exf4 Nf3 g5 Bc4 Bg7"),
+    ("C39", "King's Gambit Accepted: Kieseritzky Gambit", "e4 e5 f4 exf4 Nf3 g5 h4"),
+    ("C40", "King's Knight Opening", "e4 e5 Nf3"),
+    ("C40", "Latvian Gambit", "e4 e5 Nf3 f5"),
+    ("C41", "Philidor Defense", "e4 e5 Nf3 d6"),
+    ("C42", "Petrov's Defense", "e4 e5 Nf3 Nf6"),
+    ("C43", "Petrov's Defense: Modern Attack", "e4 e5 Nf3 Nf6 d4"),
+    ("C44", "King's Knight Opening: Normal Variation", "e4 e5 Nf3 Nc6"),
+    ("C44", "Ponziani Opening", "e4 e5 Nf3 Nc6 c3"),
+    ("C45", "Scotch Game", "e4 e5 Nf3 Nc6 d4"),
+    ("C46", "Three Knights Opening", "e4 e5 Nf3 Nc6 Nc3"),
+    ("C47", "Four Knights Game", "e4 e5 Nf3 Nc6 Nc3 Nf6"),
+    ("C48", "Four Knights Game: Spanish Variation", "e4 e5 Nf3 Nc6 Nc3 Nf6 Bb5"),
+    ("C49", "Four Knights Game: Double Spanish", "e4 e5 Nf3 Nc6 Nc3 Nf6 Bb5 Bb4"),
+    ("C50", "Italian Game", "e4 e5 Nf3 Nc6 Bc4"),
+    ("C50", "Italian Game: Hungarian Defense", "e4 e5 Nf3 Nc6 Bc4 Be7"),
+    ("C50", "Italian Game: Giuoco Piano", "e4 e5 Nf3 Nc6 Bc4 Bc5"),
+    ("C51", "Italian Game: Evans Gambit", "e4 e5 Nf3 Nc6 Bc4 Bc5 b4"),
+    ("C52", "Italian Game: Evans Gambit, Main Line", "e4 e5 Nf3 Nc6 Bc4 Bc5 b4 Bxb4 c3 Ba5"),
+    ("C53", "Italian Game: Classical Variation", "e4 e5 Nf3 Nc6 Bc4 Bc5 c3"),
+    ("C54", "Italian Game: Classical Variation, Giuoco Pianissimo", "e4 e5 Nf3 Nc6 Bc4 Bc5 c3 Nf6 d4 exd4 cxd4"),
+    ("C55", "Italian Game: Two Knights Defense", "e4 e5 Nf3 Nc6 Bc4 Nf6"),
+    ("C56", "Italian Game: Scotch Gambit, Nakhmanson Gambit", "e4 e5 Nf3 Nc6 Bc4 Nf6 d4 exd4 O-O Nxe4"),
+    ("C57", "Italian Game: Two Knights Defense, Fried Liver Attack", "e4 e5 Nf3 Nc6 Bc4 Nf6 Ng5 d5 exd5 Nxd5 Nxf7"),
+    ("C58", "Italian Game: Two Knights Defense, Polerio Defense", "e4 e5 Nf3 Nc6 Bc4 Nf6 Ng5 d5 exd5 Na5"),
+    ("C59", "Italian Game: Two Knights Defense, Knorre Variation", "e4 e5 Nf3 Nc6 Bc4 Nf6 Ng5 d5 exd5 Na5 Bb5+ c6 dxc6 bxc6 Be2 h6"),
+    ("C60", "Ruy Lopez", "e4 e5 Nf3 Nc6 Bb5"),
+    ("C61", "Ruy Lopez: Bird Variation", "e4 e5 Nf3 Nc6 Bb5 Nd4"),
+    ("C62", "Ruy Lopez: Steinitz Defense", "e4 e5 Nf3 Nc6 Bb5 d6"),
+    ("C63", "Ruy Lopez: Schliemann Defense", "e4 e5 Nf3 Nc6 Bb5 f5"),
+    ("C64", "Ruy Lopez: Classical Variation", "e4 e5 Nf3 Nc6 Bb5 Bc5"),
+    ("C65", "Ruy Lopez: Berlin Defense", "e4 e5 Nf3 Nc6 Bb5 Nf6"),
+    ("C66", "Ruy Lopez: Berlin Defense, Improved Steinitz Defense", "e4 e5 Nf3 Nc6 Bb5 Nf6 O-O d6"),
+    ("C67", "Ruy Lopez: Berlin Defense, Rio Gambit Accepted", "e4 e5 Nf3 Nc6 Bb5 Nf6 O-O Nxe4"),
+    ("C68", "Ruy Lopez: Exchange Variation", "e4 e5 Nf3 Nc6 Bb5 a6 Bxc6"),
+    ("C69", "Ruy Lopez: Exchange Variation, Gligoric Variation", "e4 e5 Nf3 Nc6 Bb5 a6 Bxc6 dxc6 O-O f6"),
+    ("C70", "Ruy Lopez: Morphy Defense", "e4 e5 Nf3 Nc6 Bb5 a6"),
+    ("C71", "Ruy Lopez: Morphy Defense, Modern Steinitz Defense", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 d6"),
+    ("C72", "Ruy Lopez: Morphy Defense, Modern Steinitz Defense, 5.O-O", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 d6 O-O"),
+    ("C73", "Ruy Lopez: Morphy Defense, Modern Steinitz Defense, Richter Variation", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 d6 Bxc6+ bxc6 d4"),
+    ("C74", "Ruy Lopez: Morphy Defense, Modern Steinitz Defense, 5.c3", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 d6 c3"),
+    ("C75", "Ruy Lopez: Morphy Defense, Modern Steinitz Defense, 5...Bd7", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 d6 c3 Bd7"),
+    ("C76", "Ruy Lopez: Morphy Defense, Modern Steinitz Defense, Fianchetto Variation", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 d6 c3 Bd7 d4 g6"),
+    ("C77", "Ruy Lopez: Morphy Defense, Normal Variation", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6"),
+    ("C78", "Ruy Lopez: Morphy Defense", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O"),
+    ("C79", "Ruy Lopez: Morphy Defense, Steinitz Deferred", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O d6"),
+    ("C80", "Ruy Lopez: Open", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Nxe4"),
+    ("C81", "Ruy Lopez: Open, Howell Attack", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Nxe4 d4 b5 Bb3 d5 dxe5 Be6 Qe2"),
+    ("C82", "Ruy Lopez: Open, 9.c3", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Nxe4 d4 b5 Bb3 d5 dxe5 Be6 c3"),
+    ("C83", "Ruy Lopez: Open, Classical Defense", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Nxe4 d4 b5 Bb3 d5 dxe5 Be6 c3 Be7"),
+    ("C84", "Ruy Lopez: Closed", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7"),
+    ("C85", "Ruy Lopez: Closed, Delayed Exchange", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Bxc6"),
+    ("C86", "Ruy Lopez: Worrall Attack", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Qe2"),
+    ("C87", "Ruy Lopez: Closed, Averbakh Variation", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 d6"),
+    ("C88", "Ruy Lopez: Closed", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3"),
+    ("C89", "Ruy Lopez: Marshall Attack", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3 O-O c3 d5"),
+    ("C90", "Ruy Lopez: Closed, 8...d6", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3 O-O c3 d6"),
+    ("C91", "Ruy Lopez: Closed, 9.d4", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3 O-O c3 d6 d4"),
+    ("C92", "Ruy Lopez: Closed, 9.h3", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3 O-O c3 d6 h3"),
+    ("C93", "Ruy Lopez: Closed, Smyslov Defense", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3 O-O c3 d6 h3 h6"),
+    ("C94", "Ruy Lopez: Closed, Breyer Defense", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3 O-O c3 d6 h3 Nb8"),
+    ("C95", "Ruy Lopez: Closed, Breyer Defense, 10.d4", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3 O-O c3 d6 h3 Nb8 d4"),
+    ("C96", "Ruy Lopez: Closed, Chigorin Defense", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3 O-O c3 d6 h3 Na5 Bc2"),
+    ("C97", "Ruy Lopez: Closed, Chigorin Defense, Main Line", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3 O-O c3 d6 h3 Na5 Bc2 c5 d4 Qc7"),
+    ("C98", "Ruy Lopez: Closed, Chigorin Defense, Rauzer Attack", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3 O-O c3 d6 h3 Na5 Bc2 c5 d4 Qc7 Nbd2 Nc6"),
+    ("C99", "Ruy Lopez: Closed, Chigorin Defense, Panov System", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3 O-O c3 d6 h3 Na5 Bc2 c5 d4 Qc7 Nbd2 cxd4 cxd4"),
+    ("D00", "Queen's Pawn Game", "d4 d5"),
+    ("D00", "Blackmar-Diemer Gambit", "d4 d5 e4"),
+    ("D01", "Richter-Veresov Attack", "d4 d5 Nc3 Nf6 Bg5"),
+    ("D02", "Queen's Pawn Game: Zukertort Variation", "d4 d5 Nf3"),
+    ("D02", "Queen's Pawn Game: London System", "d4 d5 Nf3 Nf6 Bf4"),
+    ("D03", "Queen's Pawn Game: Torre Attack", "d4 d5 Nf3 Nf6 Bg5"),
+    ("D04", "Queen's Pawn Game: Colle System", "d4 d5 Nf3 Nf6 e3"),
+    ("D05", "Queen's Pawn Game: Colle System, Main Line", "d4 d5 Nf3 Nf6 e3 e6"),
+    ("D06", "Queen's Gambit", "d4 d5 c4"),
+    ("D07", "Queen's Gambit Declined: Chigorin Defense", "d4 d5 c4 Nc6"),
+    ("D08", "Queen's Gambit Declined: Albin Countergambit", "d4 d5 c4 e5"),
+    ("D09", "Queen's Gambit Declined: Albin Countergambit, Fianchetto Variation", "d4 d5 c4 e5 dxe5 d4 Nf3 Nc6 g3"),
+    ("D10", "Slav Defense", "d4 d5 c4 c6"),
+    ("D11", "Slav Defense: Modern Line", "d4 d5 c4 c6 Nf3"),
+    ("D12", "Slav Defense: Quiet Variation", "d4 d5 c4 c6 Nf3 Nf6 e3 Bf5"),
+    ("D13", "Slav Defense: Exchange Variation", "d4 d5 c4 c6 Nf3 Nf6 cxd5 cxd5"),
+    ("D14", "Slav Defense: Exchange Variation, Symmetrical Line", "d4 d5 c4 c6 Nf3 Nf6 cxd5 cxd5 Nc3 Nc6 Bf4 Bf5"),
+    ("D15", "Slav Defense: Three Knights Variation", "d4 d5 c4 c6 Nf3 Nf6 Nc3"),
+    ("D16", "Slav Defense: Alapin Variation", "d4 d5 c4 c6 Nf3 Nf6 Nc3 dxc4 a4"),
+    ("D17", "Slav Defense: Czech Variation", "d4 d5 c4 c6 Nf3 Nf6 Nc3 dxc4 a4 Bf5"),
+    ("D18", "Slav Defense: Czech Variation, Classical System", "d4 d5 c4 c6 Nf3 Nf6 Nc3 dxc4 a4 Bf5 e3"),
+    ("D19", "Slav Defense: Czech Variation, Classical System, Main Line", "d4 d5 c4 c6 Nf3 Nf6 Nc3 dxc4 a4 Bf5 e3 e6 Bxc4 Bb4 O-O"),
+    ("D20", "Queen's Gambit Accepted", "d4 d5 c4 dxc4"),
+    ("D21", "Queen's Gambit Accepted: Normal Variation", "d4 d5 c4 dxc4 Nf3"),
+    ("D22", "Queen's Gambit Accepted: Alekhine Defense", "d4 d5 c4 dxc4 Nf3 a6"),
+    ("D23", "Queen's Gambit Accepted: Mannheim Variation", "d4 d5 c4 dxc4 Nf3 Nf6"),
+    ("D24", "Queen's Gambit Accepted: Gunsberg Defense", "d4 d5 c4 dxc4 Nf3 Nf6 Nc3"),
+    ("D25", "Queen's Gambit Accepted: Old Variation", "d4 d5 c4 dxc4 Nf3 Nf6 e3"),
+    ("D26", "Queen's Gambit Accepted: Classical Defense", "d4 d5 c4 dxc4 Nf3 Nf6 e3 e6"),
+    ("D27", "Queen's Gambit Accepted: Classical Defense, Main Line", "d4 d5 c4 dxc4 Nf3 Nf6 e3 e6 Bxc4 c5 O-O a6"),
+    ("D28", "Queen's Gambit Accepted: Classical Defense, 7.Qe2", "d4 d5 c4 dxc4 Nf3 Nf6 e3 e6 Bxc4 c5 O-O a6 Qe2"),
+    ("D29", "Queen's Gambit Accepted: Classical Defense, 8...Bb7", "d4 d5 c4 dxc4 Nf3 Nf6 e3 e6 Bxc4 c5 O-O a6 Qe2 b5 Bb3 Bb7"),
+    ("D30", "Queen's Gambit Declined", "d4 d5 c4 e6"),
+    ("D31", "Queen's Gambit Declined: Queen's Knight Variation", "d4 d5 c4 e6 Nc3"),
+    ("D32", "Tarrasch Defense", "d4 d5 c4 e6 Nc3 c5"),
+    ("D33", "Tarrasch Defense: Prague Variation", "d4 d5 c4 e6 Nc3 c5 cxd5 exd5 Nf3 Nc6 g3"),
+    ("D34", "Tarrasch Defense: Prague Variation, Main Line", "d4 d5 c4 e6 Nc3 c5 cxd5 exd5 Nf3 Nc6 g3 Nf6 Bg2 Be7"),
+    ("D35", "Queen's Gambit Declined: Exchange Variation", "d4 d5 c4 e6 Nc3 Nf6 cxd5"),
+    ("D36", "Queen's Gambit Declined: Exchange Variation, Reshevsky Variation", "d4 d5 c4 e6 Nc3 Nf6 cxd5 exd5 Bg5 c6 Qc2"),
+    ("D37", "Queen's Gambit Declined: Three Knights Variation", "d4 d5 c4 e6 Nc3 Nf6 Nf3"),
+    ("D38", "Queen's Gambit Declined: Ragozin Defense", "d4 d5 c4 e6 Nc3 Nf6 Nf3 Bb4"),
+    ("D39", "Queen's Gambit Declined: Ragozin Defense, Vienna Variation", "d4 d5 c4 e6 Nc3 Nf6 Nf3 Bb4 Bg5 dxc4"),
+    ("D40", "Queen's Gambit Declined: Semi-Tarrasch Defense", "d4 d5 c4 e6 Nc3 Nf6 Nf3 c5"),
+    ("D41", "Queen's Gambit Declined: Semi-Tarrasch Defense, Exchange Variation", "d4 d5 c4 e6 Nc3 Nf6 Nf3 c5 cxd5"),
+    ("D42", "Queen's Gambit Declined: Semi-Tarrasch Defense, Main Line", "d4 d5 c4 e6 Nc3 Nf6 Nf3 c5 cxd5 Nxd5 e3 Nc6 Bd3"),
+    ("D43", "Semi-Slav Defense", "d4 d5 c4 c6 Nf3 Nf6 Nc3 e6"),
+    ("D44", "Semi-Slav Defense: Botvinnik System", "d4 d5 c4 c6 Nf3 Nf6 Nc3 e6 Bg5 dxc4"),
+    ("D45", "Semi-Slav Defense: Normal Variation", "d4 d5 c4 c6 Nf3 Nf6 Nc3 e6 e3"),
+    ("D46", "Semi-Slav Defense: Main Line", "d4 d5 c4 c6 Nf3 Nf6 Nc3 e6 e3 Nbd7 Bd3"),
+    ("D47", "Semi-Slav Defense: Meran Variation", "d4 d5 c4 c6 Nf3 Nf6 Nc3 e6 e3 Nbd7 Bd3 dxc4 Bxc4"),
+    ("D48", "Semi-Slav Defense: Meran Variation, Old Main Line", "d4 d5 c4 c6 Nf3 Nf6 Nc3 e6 e3 Nbd7 Bd3 dxc4 Bxc4 b5 Bd3 a6"),
+    ("D49", "Semi-Slav Defense: Meran Variation, Blumenfeld Variation", "d4 d5 c4 c6 Nf3 Nf6 Nc3 e6 e3 Nbd7 Bd3 dxc4 Bxc4 b5 Bd3 a6 e4 c5 e5 cxd4 Nxb5"),
+    ("D50", "Queen's Gambit Declined: Modern Variation", "d4 d5 c4 e6 Nc3 Nf6 Bg5"),
+    ("D51", "Queen's Gambit Declined: Modern Variation, Knight Defense", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Nbd7"),
+    ("D52", "Queen's Gambit Declined: Cambridge Springs Defense", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Nbd7 e3 c6 Nf3"),
+    ("D53", "Queen's Gambit Declined: Modern Variation, Heral Variation", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Be7"),
+    ("D54", "Queen's Gambit Declined: Anti-Neo-Orthodox Variation", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Be7 e3 O-O Rc1"),
+    ("D55", "Queen's Gambit Declined: Neo-Orthodox Variation", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Be7 e3 O-O Nf3"),
+    ("D56", "Queen's Gambit Declined: Lasker Defense", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Be7 e3 O-O Nf3 h6 Bh4"),
+    ("D57", "Queen's Gambit Declined: Lasker Defense, Main Line", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Be7 e3 O-O Nf3 h6 Bh4 Ne4 Bxe7 Qxe7"),
+    ("D58", "Queen's Gambit Declined: Tartakower Defense", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Be7 e3 O-O Nf3 h6 Bh4 b6"),
+    ("D59", "Queen's Gambit Declined: Tartakower Defense, Makogonov Exchange Variation", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Be7 e3 O-O Nf3 h6 Bh4 b6 cxd5 Nxd5"),
+    ("D60", "Queen's Gambit Declined: Orthodox Defense", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Be7 e3 O-O Nf3 Nbd7"),
+    ("D61", "Queen's Gambit Declined: Orthodox Defense, Rubinstein Variation", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Be7 e3 O-O Nf3 Nbd7 Qc2"),
+    ("D62", "Queen's Gambit Declined: Orthodox Defense, Rubinstein Variation, Flohr Line", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Be7 e3 O-O Nf3 Nbd7 Qc2 c5 cxd5"),
+    ("D63", "Queen's Gambit Declined: Orthodox Defense, Main Line", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Be7 e3 O-O Nf3 Nbd7 Rc1"),
+    ("D64", "Queen's Gambit Declined: Orthodox Defense, Rubinstein Attack", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Be7 e3 O-O Nf3 Nbd7 Rc1 c6 Qc2"),
+    ("D65", "Queen's Gambit Declined: Orthodox Defense, Rubinstein Attack, Main Line", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Be7 e3 O-O Nf3 Nbd7 Rc1 c6 Qc2 a6 cxd5"),
+    ("D66", "Queen's Gambit Declined: Orthodox Defense, Fianchetto Variation", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Be7 e3 O-O Nf3 Nbd7 Rc1 c6 Bd3"),
+    ("D67", "Queen's Gambit Declined: Orthodox Defense, Main Line, Capablanca Freeing Maneuver", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Be7 e3 O-O Nf3 Nbd7 Rc1 c6 Bd3 dxc4 Bxc4 Nd5"),
+    ("D68", "Queen's Gambit Declined: Orthodox Defense, Classical Variation", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Be7 e3 O-O Nf3 Nbd7 Rc1 c6 Bd3 dxc4 Bxc4 Nd5 Bxe7 Qxe7 O-O Nxc3 Rxc3 e5"),
+    ("D69", "Queen's Gambit Declined: Orthodox Defense, Classical Variation, 13.dxe5", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Be7 e3 O-O Nf3 Nbd7 Rc1 c6 Bd3 dxc4 Bxc4 Nd5 Bxe7 Qxe7 O-O Nxc3 Rxc3 e5 dxe5 Nxe5 Nxe5 Qxe5"),
+    ("D70", "Neo-Grünfeld Defense", "d4 Nf6 c4 g6 f3 d5"),
+    ("D71", "Neo-Grünfeld Defense: Exchange Variation", "d4 Nf6 c4 g6 g3 d5 Bg2 Bg7 cxd5 Nxd5"),
+    ("D72", "Neo-Grünfeld Defense: Classical Variation, Original Defense", "d4 Nf6 c4 g6 g3 d5 Bg2 Bg7 cxd5 Nxd5 e4 Nb6 Ne2"),
+    ("D73", "Neo-Grünfeld Defense: Goglidze Attack", "d4 Nf6 c4 g6 g3 d5 Bg2 Bg7 Nf3"),
+    ("D74", "Neo-Grünfeld Defense: Delayed Exchange Variation", "d4 Nf6 c4 g6 g3 d5 Bg2 Bg7 Nf3 O-O cxd5 Nxd5 O-O"),
+    ("D75", "Neo-Grünfeld Defense: Delayed Exchange Variation, 7...c5", "d4 Nf6 c4 g6 g3 d5 Bg2 Bg7 Nf3 O-O cxd5 Nxd5 O-O c5 Nc3"),
+    ("D76", "Neo-Grünfeld Defense: Delayed Exchange Variation, 7...Nb6", "d4 Nf6 c4 g6 g3 d5 Bg2 Bg7 Nf3 O-O cxd5 Nxd5 O-O Nb6"),
+    ("D77", "Neo-Grünfeld Defense: Classical Variation", "d4 Nf6 c4 g6 g3 d5 Bg2 Bg7 Nf3 O-O O-O"),
+    ("D78", "Neo-Grünfeld Defense: Classical Variation, Original Defense", "d4 Nf6 c4 g6 g3 d5 Bg2 Bg7 Nf3 O-O O-O c6"),
+    ("D79", "Neo-Grünfeld Defense: Ultra-Delayed Exchange Variation", "d4 Nf6 c4 g6 g3 d5 Bg2 Bg7 Nf3 O-O O-O c6 cxd5 cxd5"),
+    ("D80", "Grünfeld Defense", "d4 Nf6 c4 g6 Nc3 d5"),
+    ("D81", "Grünfeld Defense: Russian Variation, Accelerated Variation", "d4 Nf6 c4 g6 Nc3 d5 Qb3"),
+    ("D82", "Grünfeld Defense: Brinckmann Attack", "d4 Nf6 c4 g6 Nc3 d5 Bf4"),
+    ("D83", "Grünfeld Defense: Brinckmann Attack, Grünfeld Gambit", "d4 Nf6 c4 g6 Nc3 d5 Bf4 Bg7 e3 O-O"),
+    ("D84", "Grünfeld Defense: Grünfeld Gambit Accepted", "d4 Nf6 c4 g6 Nc3 d5 Bf4 Bg7 e3 O-O cxd5 Nxd5 Nxd5 Qxd5 Bxc7"),
+    ("D85", "Grünfeld Defense: Exchange Variation", "d4 Nf6 c4 g6 Nc3 d5 cxd5 Nxd5 e4 Nxc3 bxc3"),
+    ("D86", "Grünfeld Defense: Exchange Variation, Classical Variation", "d4 Nf6 c4 g6 Nc3 d5 cxd5 Nxd5 e4 Nxc3 bxc3 Bg7 Bc4"),
+    ("D87", "Grünfeld Defense: Exchange Variation, Seville Variation", "d4 Nf6 c4 g6 Nc3 d5 cxd5 Nxd5 e4 Nxc3 bxc3 Bg7 Bc4 O-O Ne2 c5"),
+    ("D88", "Grünfeld Defense: Exchange Variation, Spassky Variation", "d4 Nf6 c4 g6 Nc3 d5 cxd5 Nxd5 e4 Nxc3 bxc3 Bg7 Bc4 O-O Ne2 c5 O-O Nc6 Be3 cxd4 cxd4"),
+    ("D89", "Grünfeld Defense: Exchange Variation, Sokolsky Variation", "d4 Nf6 c4 g6 Nc3 d5 cxd5 Nxd5 e4 Nxc3 bxc3 Bg7 Bc4 O-O Ne2 c5 O-O Nc6 Be3 cxd4 cxd4 Bg4 f3 Na5 Bd3 Be6"),
+    ("D90", "Grünfeld Defense: Three Knights Variation", "d4 Nf6 c4 g6 Nc3 d5 Nf3"),
+    ("D91", "Grünfeld Defense: Three Knights Variation, Petrosian System", "d4 Nf6 c4 g6 Nc3 d5 Nf3 Bg7 Bg5"),
+    ("D92", "Grünfeld Defense: Three Knights Variation, Hungarian Attack", "d4 Nf6 c4 g6 Nc3 d5 Nf3 Bg7 Bf4"),
+    ("D93", "Grünfeld Defense: Three Knights Variation, Hungarian Variation", "d4 Nf6 c4 g6 Nc3 d5 Nf3 Bg7 Bf4 O-O e3"),
+    ("D94", "Grünfeld Defense: Three Knights Variation, Burille Variation", "d4 Nf6 c4 g6 Nc3 d5 Nf3 Bg7 e3"),
+    ("D95", "Grünfeld Defense: Three Knights Variation, Vienna Variation", "d4 Nf6 c4 g6 Nc3 d5 Nf3 Bg7 e3 O-O Qb3"),
+    ("D96", "Grünfeld Defense: Russian Variation", "d4 Nf6 c4 g6 Nc3 d5 Nf3 Bg7 Qb3"),
+    ("D97", "Grünfeld Defense: Russian Variation, 7.e4", "d4 Nf6 c4 g6 Nc3 d5 Nf3 Bg7 Qb3 dxc4 Qxc4 O-O e4"),
+    ("D98", "Grünfeld Defense: Russian Variation, Smyslov Variation", "d4 Nf6 c4 g6 Nc3 d5 Nf3 Bg7 Qb3 dxc4 Qxc4 O-O e4 Bg4"),
+    ("D99", "Grünfeld Defense: Russian Variation, Yugoslav Variation", "d4 Nf6 c4 g6 Nc3 d5 Nf3 Bg7 Qb3 dxc4 Qxc4 O-O e4 Bg4 Be3 Nfd7 Qb3"),
+    ("E00", "Indian Defense", "d4 Nf6 c4 e6"),
+    ("E01", "Catalan Opening", "d4 Nf6 c4 e6 g3"),
+    ("E02", "Catalan Opening: Open Defense, 5.Qa4+", "d4 Nf6 c4 e6 g3 d5 Bg2 dxc4 Qa4+"),
+    ("E03", "Catalan Opening: Open Defense, Alekhine Variation", "d4 Nf6 c4 e6 g3 d5 Bg2 dxc4 Qa4+ Nbd7 Qxc4"),
+    ("E04", "Catalan Opening: Open Defense", "d4 Nf6 c4 e6 g3 d5 Bg2 dxc4 Nf3"),
+    ("E05", "Catalan Opening: Open Defense, Classical Line", "d4 Nf6 c4 e6 g3 d5 Bg2 dxc4 Nf3 Be7"),
+    ("E06", "Catalan Opening: Closed", "d4 Nf6 c4 e6 g3 d5 Bg2 Be7 Nf3"),
+    ("E07", "Catalan Opening: Closed Variation", "d4 Nf6 c4 e6 g3 d5 Bg2 Be7 Nf3 O-O O-O Nbd7"),
+    ("E08", "Catalan Opening: Closed, 7.Qc2", "d4 Nf6 c4 e6 g3 d5 Bg2 Be7 Nf3 O-O O-O Nbd7 Qc2"),
+    ("E09", "Catalan Opening: Closed, Main Line", "d4 Nf6 c4 e6 g3 d5 Bg2 Be7 Nf3 O-O O-O Nbd7 Qc2 c6 Nbd2"),
+    ("E10", "Indian Defense: Anti-Nimzo-Indian", "d4 Nf6 c4 e6 Nf3"),
+    ("E11", "Bogo-Indian Defense", "d4 Nf6 c4 e6 Nf3 Bb4+"),
+    ("E12", "Queen's Indian Defense", "d4 Nf6 c4 e6 Nf3 b6"),
+    ("E13", "Queen's Indian Defense: Kasparov Variation, 5.Bg5", "d4 Nf6 c4 e6 Nf3 b6 Nc3 Bb7 Bg5"),
+    ("E14", "Queen's Indian Defense: Spassky System", "d4 Nf6 c4 e6 Nf3 b6 e3"),
+    ("E15", "Queen's Indian Defense: Fianchetto Variation", "d4 Nf6 c4 e6 Nf3 b6 g3"),
+    ("E16", "Queen's Indian Defense: Capablanca Variation", "d4 Nf6 c4 e6 Nf3 b6 g3 Bb7 Bg2 Bb4+"),
+    ("E17", "Queen's Indian Defense: Fianchetto Variation, Traditional Variation", "d4 Nf6 c4 e6 Nf3 b6 g3 Bb7 Bg2 Be7"),
+    ("E18", "Queen's Indian Defense: Fianchetto Variation, Old Main Line", "d4 Nf6 c4 e6 Nf3 b6 g3 Bb7 Bg2 Be7 O-O O-O Nc3"),
+    ("E19", "Queen's Indian Defense: Fianchetto Variation, Old Main Line, 9.Qxc3", "d4 Nf6 c4 e6 Nf3 b6 g3 Bb7 Bg2 Be7 O-O O-O Nc3 Ne4 Qc2 Nxc3 Qxc3"),
+    ("E20", "Nimzo-Indian Defense", "d4 Nf6 c4 e6 Nc3 Bb4"),
+    ("E21", "Nimzo-Indian Defense: Three Knights Variation", "d4 Nf6 c4 e6 Nc3 Bb4 Nf3"),
+    ("E22", "Nimzo-Indian Defense: Spielmann Variation", "d4 Nf6 c4 e6 Nc3 Bb4 Qb3"),
+    ("E23", "Nimzo-Indian Defense: Spielmann Variation, Karlsbad Variation", "d4 Nf6 c4 e6 Nc3 Bb4 Qb3 c5 dxc5 Nc6"),
+    ("E24", "Nimzo-Indian Defense: Sämisch Variation, Accelerated", "d4 Nf6 c4 e6 Nc3 Bb4 a3 Bxc3+ bxc3"),
+    ("E25", "Nimzo-Indian Defense: Sämisch Variation, Keres Variation", "d4 Nf6 c4 e6 Nc3 Bb4 a3 Bxc3+ bxc3 c5 f3 d5 cxd5"),
+    ("E26", "Nimzo-Indian Defense: Sämisch Variation, 5...c5 6.e3", "d4 Nf6 c4 e6 Nc3 Bb4 a3 Bxc3+ bxc3 c5 e3"),
+    ("E27", "Nimzo-Indian Defense: Sämisch Variation, 5...O-O", "d4 Nf6 c4 e6 Nc3 Bb4 a3 Bxc3+ bxc3 O-O"),
+    ("E28", "Nimzo-Indian Defense: Sämisch Variation, 6.e3", "d4 Nf6 c4 e6 Nc3 Bb4 a3 Bxc3+ bxc3 O-O e3"),
+    ("E29", "Nimzo-Indian Defense: Sämisch Variation, Main Line", "d4 Nf6 c4 e6 Nc3 Bb4 a3 Bxc3+ bxc3 O-O e3 c5 Bd3 Nc6"),
+    ("E30", "Nimzo-Indian Defense: Leningrad Variation", "d4 Nf6 c4 e6 Nc3 Bb4 Bg5"),
+    ("E31", "Nimzo-Indian Defense: Leningrad Variation, Main Line", "d4 Nf6 c4 e6 Nc3 Bb4 Bg5 h6 Bh4 c5 d5 d6"),
+    ("E32", "Nimzo-Indian Defense: Classical Variation", "d4 Nf6 c4 e6 Nc3 Bb4 Qc2"),
+    ("E33", "Nimzo-Indian Defense: Classical Variation, Milner-Barry Variation", "d4 Nf6 c4 e6 Nc3 Bb4 Qc2 Nc6"),
+    ("E34", "Nimzo-Indian Defense: Classical Variation, Noa Variation", "d4 Nf6 c4 e6 Nc3 Bb4 Qc2 d5"),
+    ("E35", "Nimzo-Indian Defense: Classical Variation, Noa Variation, 5.cxd5 exd5", "d4 Nf6 c4 e6 Nc3 Bb4 Qc2 d5 cxd5 exd5"),
+    ("E36", "Nimzo-Indian Defense: Classical Variation, Noa Variation, 5.a3", "d4 Nf6 c4 e6 Nc3 Bb4 Qc2 d5 a3"),
+    ("E37", "Nimzo-Indian Defense: Classical Variation, Noa Variation, Main Line, 7.Qc2", "d4 Nf6 c4 e6 Nc3 Bb4 Qc2 d5 a3 Bxc3+ Qxc3 Ne4 Qc2"),
+    ("E38", "Nimzo-Indian Defense: Classical Variation, 4...c5", "d4 Nf6 c4 e6 Nc3 Bb4 Qc2 c5"),
+    ("E39", "Nimzo-Indian Defense: Classical Variation, Pirc Variation", "d4 Nf6 c4 e6 Nc3 Bb4 Qc2 c5 dxc5 O-O"),
+    ("E40", "Nimzo-Indian Defense: Normal Variation", "d4 Nf6 c4 e6 Nc3 Bb4 e3"),
+    ("E41", "Nimzo-Indian Defense: Hübner Variation", "d4 Nf6 c4 e6 Nc3 Bb4 e3 c5"),
+    ("E42", "Nimzo-Indian Defense: Hübner Variation, Rubinstein Variation", "d4 Nf6 c4 e6 Nc3 Bb4 e3 c5 Ne2"),
+    ("E43", "Nimzo-Indian Defense: St. Petersburg Variation", "d4 Nf6 c4 e6 Nc3 Bb4 e3 b6"),
+    ("E44", "Nimzo-Indian Defense: Fischer Variation, 5.Ne2", "d4 Nf6 c4 e6 Nc3 Bb4 e3 b6 Ne2"),
+    ("E45", "Nimzo-Indian Defense: Normal Variation, Bronstein (Byrne) Variation", "d4 Nf6 c4 e6 Nc3 Bb4 e3 b6 Ne2 Ba6"),
+    ("E46", "Nimzo-Indian Defense: Normal Variation, 4...O-O", "d4 Nf6 c4 e6 Nc3 Bb4 e3 O-O"),
+    ("E47", "Nimzo-Indian Defense: Normal Variation, Bishop Attack", "d4 Nf6 c4 e6 Nc3 Bb4 e3 O-O Bd3"),
+    ("E48", "Nimzo-Indian Defense: Normal Variation, Bishop Attack, Classical Defense", "d4 Nf6 c4 e6 Nc3 Bb4 e3 O-O Bd3 d5"),
+    ("E49", "Nimzo-Indian Defense: Normal Variation, Botvinnik System", "d4 Nf6 c4 e6 Nc3 Bb4 e3 O-O Bd3 d5 a3 Bxc3+ bxc3"),
+    ("E50", "Nimzo-Indian Defense: Normal Variation, 5.Nf3", "d4 Nf6 c4 e6 Nc3 Bb4 e3 O-O Nf3"),
+    ("E51", "Nimzo-Indian Defense: Normal Variation, Ragozin Variation", "d4 Nf6 c4 e6 Nc3 Bb4 e3 O-O Nf3 d5"),
+    ("E52", "Nimzo-Indian Defense: Normal Variation, Schlechter Defense", "d4 Nf6 c4 e6 Nc3 Bb4 e3 O-O Nf3 d5 Bd3 b6"),
+    ("E53", "Nimzo-Indian Defense: Normal Variation, Gligoric System", "d4 Nf6 c4 e6 Nc3 Bb4 e3 O-O Nf3 d5 Bd3 c5"),
+    ("E54", "Nimzo-Indian Defense: Normal Variation, Gligoric System, Exchange at c4", "d4 Nf6 c4 e6 Nc3 Bb4 e3 O-O Nf3 d5 Bd3 c5 O-O dxc4 Bxc4"),
+    ("E55", "Nimzo-Indian Defense: Normal Variation, Gligoric System, Bronstein Variation", "d4 Nf6 c4 e6 Nc3 Bb4 e3 O-O Nf3 d5 Bd3 c5 O-O dxc4 Bxc4 Nbd7"),
+    ("E56", "Nimzo-Indian Defense: Normal Variation, Gligoric System, 7...Nc6", "d4 Nf6 c4 e6 Nc3 Bb4 e3 O-O Nf3 d5 Bd3 c5 O-O Nc6"),
+    ("E57", "Nimzo-Indian Defense: Normal Variation, Gligoric System, 8...dxc4 and 9...cxd4", "d4 Nf6 c4 e6 Nc3 Bb4 e3 O-O Nf3 d5 Bd3 c5 O-O Nc6 a3 dxc4 Bxc4 cxd4"),
+    ("E58", "Nimzo-Indian Defense: Normal Variation, Gligoric System, 8...Bxc3", "d4 Nf6 c4 e6 Nc3 Bb4 e3 O-O Nf3 d5 Bd3 c5 O-O Nc6 a3 Bxc3 bxc3"),
+    ("E59", "Nimzo-Indian Defense: Normal Variation, Gligoric System, Main Line", "d4 Nf6 c4 e6 Nc3 Bb4 e3 O-O Nf3 d5 Bd3 c5 O-O Nc6 a3 Bxc3 bxc3 dxc4 Bxc4"),
+    ("E60", "King's Indian Defense", "d4 Nf6 c4 g6"),
+    ("E61", "King's Indian Defense", "d4 Nf6 c4 g6 Nc3 Bg7"),
+    ("E62", "King's Indian Defense: Fianchetto Variation", "d4 Nf6 c4 g6 Nc3 Bg7 Nf3 d6 g3"),
+    ("E63", "King's Indian Defense: Fianchetto Variation, Panno Variation", "d4 Nf6 c4 g6 Nc3 Bg7 Nf3 d6 g3 O-O Bg2 Nc6 O-O a6"),
+    ("E64", "King's Indian Defense: Fianchetto Variation, Yugoslav System", "d4 Nf6 c4 g6 Nc3 Bg7 Nf3 d6 g3 O-O Bg2 c5"),
+    ("E65", "King's Indian Defense: Fianchetto Variation, Yugoslav Variation, 7.O-O", "d4 Nf6 c4 g6 Nc3 Bg7 Nf3 d6 g3 O-O Bg2 c5 O-O"),
+    ("E66", "King's Indian Defense: Fianchetto Variation, Yugoslav Variation, Advance Line", "d4 Nf6 c4 g6 Nc3 Bg7 Nf3 d6 g3 O-O Bg2 c5 O-O Nc6 d5"),
+    ("E67", "King's Indian Defense: Fianchetto Variation, Classical Fianchetto", "d4 Nf6 c4 g6 Nc3 Bg7 Nf3 d6 g3 O-O Bg2 Nbd7"),
+    ("E68", "King's Indian Defense: Fianchetto Variation, Classical Variation, 8.e4", "d4 Nf6 c4 g6 Nc3 Bg7 Nf3 d6 g3 O-O Bg2 Nbd7 O-O e5 e4"),
+    ("E69", "King's Indian Defense: Fianchetto Variation, Classical Main Line", "d4 Nf6 c4 g6 Nc3 Bg7 Nf3 d6 g3 O-O Bg2 Nbd7 O-O e5 e4 c6 h3"),
+    ("E70", "King's Indian Defense: Normal Variation", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6"),
+    ("E71", "King's Indian Defense: Makogonov Variation", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 h3"),
+    ("E72", "King's Indian Defense: Normal Variation, Deferred Fianchetto", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 g3"),
+    ("E73", "King's Indian Defense: Normal Variation, 5.Be2", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Be2"),
+    ("E74", "King's Indian Defense: Averbakh Variation, Benoni Defense", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Be2 O-O Bg5 c5"),
+    ("E75", "King's Indian Defense: Averbakh Variation, Main Line", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Be2 O-O Bg5 c5 d5 e6"),
+    ("E76", "King's Indian Defense: Four Pawns Attack", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 f4"),
+    ("E77", "King's Indian Defense: Four Pawns Attack, 6.Be2", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 f4 O-O Be2"),
+    ("E78", "King's Indian Defense: Four Pawns Attack, Fluid Attack", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 f4 O-O Be2 c5 Nf3"),
+    ("E79", "King's Indian Defense: Four Pawns Attack, Main Line", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 f4 O-O Be2 c5 Nf3 cxd4 Nxd4 Nc6 Be3"),
+    ("E80", "King's Indian Defense: Sämisch Variation", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 f3"),
+    ("E81", "King's Indian Defense: Sämisch Variation, Normal Variation", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 f3 O-O"),
+    ("E82", "King's Indian Defense: Sämisch Variation, Double Fianchetto", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 f3 O-O Be3 b6"),
+    ("E83", "King's Indian Defense: Sämisch Variation, 6...Nc6", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 f3 O-O Be3 Nc6"),
+    ("E84", "King's Indian Defense: Sämisch Variation, Panno Main Line", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 f3 O-O Be3 Nc6 Nge2 a6 Qd2 Rb8"),
+    ("E85", "King's Indian Defense: Sämisch Variation, Orthodox Variation", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 f3 O-O Be3 e5"),
+    ("E86", "King's Indian Defense: Sämisch Variation, Orthodox Variation, 7.Nge2 c6", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 f3 O-O Be3 e5 Nge2 c6"),
+    ("E87", "King's Indian Defense: Sämisch Variation, Closed Variation", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 f3 O-O Be3 e5 d5"),
+    ("E88", "King's Indian Defense: Sämisch Variation, Closed Variation, 7...c6", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 f3 O-O Be3 e5 d5 c6"),
+    ("E89", "King's Indian Defense: Sämisch Variation, Closed Variation, Main Line", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 f3 O-O Be3 e5 d5 c6 Nge2 cxd5"),
+    ("E90", "King's Indian Defense: Normal Variation, 5.Nf3", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3"),
+    ("E91", "King's Indian Defense: Orthodox Variation, 6.Be2", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3 O-O Be2"),
+    ("E92", "King's Indian Defense: Orthodox Variation", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3 O-O Be2 e5"),
+    ("E93", "King's Indian Defense: Petrosian Variation, Keres Defense", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3 O-O Be2 e5 d5 Nbd7"),
+    ("E94", "King's Indian Defense: Orthodox Variation, 7.O-O", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3 O-O Be2 e5 O-O"),
+    ("E95", "King's Indian Defense: Orthodox Variation, 7...Nbd7, 8.Re1", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3 O-O Be2 e5 O-O Nbd7 Re1"),
+    ("E96", "King's Indian Defense: Orthodox Variation, 7...Nbd7, Main Line", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3 O-O Be2 e5 O-O Nbd7 Re1 c6 Bf1 a5"),
+    ("E97", "King's Indian Defense: Orthodox Variation, Aronin-Taimanov Defense", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3 O-O Be2 e5 O-O Nc6"),
+    ("E98", "King's Indian Defense: Orthodox Variation, Classical System, Main Line", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3 O-O Be2 e5 O-O Nc6 d5 Ne7 Ne1"),
+    ("E99", "King's Indian Defense: Orthodox Variation, Classical System, Main Line, 10.f3", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3 O-O Be2 e5 O-O Nc6 d5 Ne7 Ne1 Nd7 f3 f5")
+];
+
+// the openings by the key of the position they reach
+struct Table {
+    positions: HashMap<u64, Opening>,
+    // the number of moves of both sides in the longest line
+    plies: usize
+}
+
+// play a line of the table from the starting position
+fn reach(_moves: &str) -> Option<Game> {
+    let mut game = Game::new();
+    for san in _moves.split_whitespace() {
+        game.make_san_move(san)?;
+    }
+    Some(game)
+}
+
+fn table() -> &'static Table {
+    static TABLE: OnceLock<Table> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = Table { positions: HashMap::default(), plies: 0 };
+        // a line that cannot be played is left out rather than failing every lookup
+        for (eco, name, moves) in OPENINGS.iter() {
+            if let Some(game) = reach(moves) {
+                table.plies = table.plies.max(game.history.len());
+                table.positions.insert(game.polyglot_key(), Opening { eco, name });
+            }
+        }
+        table
+    })
+}
+
+impl Game {
+    /// Get the opening of the game, from the last position played that is in the ECO table.
+    pub fn opening(&self) -> Option<Opening> {
+        let table = table();
+        // positions after the longest line are not looked up, so long games cost no more than short ones
+        (0..=self.history.len().min(table.plies)).rev().find_map(|ply| {
+            let game = self.history.get(ply).map(|r| &r.before).unwrap_or(self);
+            table.positions.get(&game.polyglot_key()).copied()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Game;
+    use super::{reach, table, OPENINGS};
+
+    fn play(_moves: &str) -> Game {
+        reach(_moves).unwrap()
+    }
+
+    #[test]
+    fn legal_lines() {
+        for (eco, _, moves) in OPENINGS.iter() {
+            assert!(reach(moves).is_some(), "{} {}", eco, moves);
+        }
+    }
+
+    #[test]
+    fn distinct_positions() {
+        // every opening reaches a position of its own
+        assert_eq!(table().positions.len(), OPENINGS.len());
+        assert_eq!(table().plies, 28);
+    }
+
+    #[test]
+    fn every_code() {
+        for letter in ['A', 'B', 'C', 'D', 'E'] {
+            for number in 0..100 {
+                let eco = format!("{}{:02}", letter, number);
+                assert!(OPENINGS.iter().any(|(code, _, _)| *code == eco), "{}", eco);
+            }
+        }
+    }
+
+    #[test]
+    fn openings() {
+        assert_eq!(Game::new().opening(), None);
+        let opening = play("e4 e5 Nf3 Nc6 Bb5 Nf6 O-O Nxe4 d4").opening().unwrap();
+        assert_eq!((opening.eco, opening.name), ("C67", "Ruy Lopez: Berlin Defense, Rio Gambit Accepted"));
+    }
+
+    #[test]
+    fn transpositions() {
+        // the King's Indian reached through the English
+        let opening = play("c4 Nf6 Nc3 g6 d4 Bg7").opening().unwrap();
+        assert_eq!((opening.eco, opening.name), ("E61", "King's Indian Defense"));
+    }
+
+    #[test]
+    fn long_games() {
+        // the opening is found from the start of a game longer than any line in the table
+        let game = play("d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3 O-O Be2 e5 O-O Nc6 d5 Ne7 Ne1 Nd7 Be3 f5 f3 f4 Bf2 g5 Rc1 Ng6 c5 Nxc5 b4 Na6 Nd3 Rf7");
+        assert!(game.history.len() > table().plies);
+        assert_eq!(game.opening().unwrap().eco, "E98");
+    }
+
+    #[test]
+    fn pgn_tags() {
+        let game = play("e4 e5 Nf3 Nc6 Bc4 Bc5");
+        assert!(game.to_pgn().contains("[ECO \"C50\"]\n[Opening \"Italian Game: Giuoco Piano\"]\n"));
+    }
+}
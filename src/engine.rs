@@ -0,0 +1,456 @@
+//! A built-in engine, to play against and to judge positions with.
+//!
+//! Positions are scored from the material on the board and from where the
+//! pieces stand. The engine looks ahead with an alpha-beta search, deepened one
+//! move of either side at a time, and at the end of each line keeps playing
+//! captures until the position is quiet, so that an exchange is not judged
+//! halfway through. Its strength is the depth it searches to: at depth 1 it only
+//! looks at its own moves, and each step up looks one move further and takes
+//! several times as long. A search can be cut short by a time limit, in which
+//! case the move of the deepest search that was finished is played.
+
+use std::cmp::Reverse;
+use std::io;
+use std::time::{Duration, Instant};
+
+use super::clock::Bonus;
+use super::mate::play;
+use super::uci::{Player, Reply, Score};
+use super::{after_move, can_mate, legal_moves, opponent, Colour, Game, GameState, Move, Piece};
+
+// a move as the squares moved from and to, and the piece that ends up on the target square
+type Step = (Vec<usize>, Vec<usize>, Piece);
+
+// the score of being mated at once, in centipawns, for the side to move
+const MATE: i32 = 100000;
+// the longest line followed, in moves of either side
+const MAX_PLY: u32 = 64;
+
+static PAWN_ADVANCE: [i32; 8] = [0, 0, 5, 10, 20, 35, 60, 0];
+
+/// How the engine plays.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Engine {
+    /// The number of moves of either side to look ahead, from 1.
+    pub depth: u32,
+    /// The longest time to think about a move in games without a clock, or None
+    /// to always search to the full depth. In a game with a clock the time left
+    /// is shared out over the moves to come.
+    pub move_time: Option<Duration>
+}
+
+impl Default for Engine {
+    fn default() -> Engine {
+        Engine { depth: 3, move_time: None }
+    }
+}
+
+/// The result of a search.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Analysis {
+    /// The moves the engine expects to be played, from its choice on.
+    pub line: Vec<Move>,
+    /// The score of the first move, for the side that plays it.
+    pub score: Score,
+    /// The depth of the deepest search that was finished.
+    pub depth: u32,
+    /// The number of positions looked at.
+    pub nodes: u64
+}
+
+fn value(_piece: Piece) -> i32 {
+    match _piece {
+        Piece::Pawn(_) => 100,
+        Piece::Knight(_) => 320,
+        Piece::Bishop(_) => 330,
+        Piece::Rook(_) => 500,
+        Piece::Queen(_) => 900,
+        Piece::King(_) => 0
+    }
+}
+
+// get the bonus for a piece standing on a square, with the ranks counted from its own side
+fn placement(_piece: Piece, _rank: usize, _file: usize, _endgame: bool) -> i32 {
+    // from 0 in the corners to 12 in the centre
+    let centre = 14 - ((2 * _rank as i32 - 7).abs() + (2 * _file as i32 - 7).abs());
+    match _piece {
+        Piece::Pawn(_) if (3..=4).contains(&_file) && (2..=4).contains(&_rank) => PAWN_ADVANCE[_rank] + 10,
+        Piece::Pawn(_) => PAWN_ADVANCE[_rank],
+        Piece::Knight(_) => 4 * centre - 25,
+        Piece::Bishop(_) => 2 * centre - 10,
+        Piece::Rook(_) if _rank == 6 => 20,
+        Piece::Rook(_) => 0,
+        Piece::Queen(_) => centre,
+        // the king stays home until most pieces are gone, then comes out to help
+        Piece::King(_) if _endgame => 4 * centre - 20,
+        Piece::King(_) => -3 * centre - 20 * _rank as i32
+    }
+}
+
+// score a position in centipawns for the side to move
+fn evaluate(_game: &Game) -> i32 {
+    let pieces: i32 = _game.board.iter().flatten().flatten()
+        .filter(|p| !matches!(p, Piece::Pawn(_) | Piece::King(_)))
+        .map(|p| value(*p))
+        .sum();
+    // about a rook and two minor pieces each, or less
+    let endgame = pieces <= 2600;
+    let mut score = 0;
+    for (rank, row) in _game.board.iter().enumerate() {
+        for (file, square) in row.iter().enumerate() {
+            if let Some(piece) = square {
+                let own_rank = match piece.unwrap() {
+                    Colour::White => rank,
+                    Colour::Black => 7 - rank
+                };
+                let points = value(*piece) + placement(*piece, own_rank, file, endgame);
+                score += if piece.unwrap() == _game.active { points } else { -points };
+            }
+        }
+    }
+    score
+}
+
+// get the piece a move takes, if any, including a pawn taken en passant
+fn captured(_game: &Game, _step: &Step) -> Option<Piece> {
+    let (from, to, _) = _step;
+    match (_game.board[from[0]][from[1]], _game.board[to[0]][to[1]]) {
+        // in Chess960 the king castles by moving onto its own rook
+        (_, Some(p)) if p.unwrap() != _game.active => Some(p),
+        (_, Some(_)) => None,
+        (Some(Piece::Pawn(_)), None) if from[1] != to[1] => Some(Piece::Pawn(opponent(_game.active))),
+        _ => None
+    }
+}
+
+// check whether a move changes the material on the board, by a capture or a promotion to a queen
+fn is_noisy(_game: &Game, _step: &Step) -> bool {
+    captured(_game, _step).is_some() || matches!((_game.board[_step.0[0]][_step.0[1]], _step.2), (Some(Piece::Pawn(_)), Piece::Queen(_)))
+}
+
+// order moves with the one expected to be best first, then captures of the most valuable pieces
+// by the least valuable ones, then promotions
+fn order(_game: &Game, _moves: Vec<Step>, _expected: Option<&Step>) -> Vec<Step> {
+    let mut moves = _moves;
+    moves.sort_by_key(|m| {
+        if Some(m) == _expected { return Reverse(i32::MAX) }
+        let mover = _game.board[m.0[0]][m.0[1]].unwrap();
+        let priority = match captured(_game, m) {
+            Some(victim) => 100000 + 10 * value(victim) - value(mover),
+            None => 0
+        };
+        // the piece a pawn is promoted to is worth more than the pawn
+        Reverse(priority + value(m.2) - value(mover))
+    });
+    moves
+}
+
+// convert a score in centipawns to a score that tells mates apart
+fn to_score(_score: i32) -> Score {
+    if _score >= MATE - MAX_PLY as i32 {
+        Score::Mate((MATE - _score + 1) / 2)
+    } else if _score <= -(MATE - MAX_PLY as i32) {
+        Score::Mate(-(MATE + _score) / 2)
+    } else {
+        Score::Centipawns(_score)
+    }
+}
+
+// get the moves of a line as played moves
+fn to_moves(_game: &Game, _line: &[Step]) -> Vec<Move> {
+    let mut game = _game.scratch();
+    let mut moves: Vec<Move> = Vec::default();
+    for (from, to, promotion) in _line {
+        let (played, after) = play(&game, from, to, *promotion);
+        moves.push(played);
+        game = after;
+    }
+    moves
+}
+
+// the state of a search
+struct Search {
+    deadline: Option<Instant>,
+    nodes: u64,
+    stopped: bool,
+    // the keys of the positions played and searched on the way to the current one, to find repetitions
+    path: Vec<u64>
+}
+
+impl Search {
+    fn new(_game: &Game, _deadline: Option<Instant>) -> Search {
+        let mut path: Vec<u64> = _game.history.iter().map(|r| r.before.polyglot_key()).collect();
+        path.push(_game.polyglot_key());
+        Search { deadline: _deadline, nodes: 0, stopped: false, path }
+    }
+
+    fn out_of_time(&mut self) -> bool {
+        if let Some(deadline) = self.deadline {
+            // the clock is only read now and then, as reading it takes time too
+            if self.nodes.is_multiple_of(256) && Instant::now() >= deadline {
+                self.stopped = true;
+            }
+        }
+        self.stopped
+    }
+
+    // search the moves of the side to move to a depth, keeping the best line found with a score
+    // inside the window of scores still of interest; the expected line is tried first
+    fn negamax(&mut self, _game: &Game, _depth: u32, _ply: u32, _window: (i32, i32), _line: &mut Vec<Step>, _expected: &[Step]) -> i32 {
+        let (mut alpha, beta) = _window;
+        self.nodes += 1;
+        if self.out_of_time() { return 0 }
+        if _game.state == GameState::GameOver { return -(MATE - _ply as i32) }
+        if _ply > 0 {
+            // a repetition, the fifty-move rule and too little material to mate all draw
+            let key = _game.polyglot_key();
+            if self.path[..self.path.len() - 1].contains(&key) || _game.halfmove_clock >= 100 { return 0 }
+            if !can_mate(_game, Colour::White) && !can_mate(_game, Colour::Black) { return 0 }
+        }
+        if _depth == 0 || _ply >= MAX_PLY { return self.quiesce(_game, _ply, _window, _line) }
+
+        let moves = order(_game, legal_moves(_game), _expected.first());
+        // a side that is not mated and has no moves is stalemated
+        if moves.is_empty() { return 0 }
+        let mut best = -MATE;
+        for step in moves {
+            let after = after_move(_game, &step.0, &step.1, step.2);
+            let expected = match _expected.first() == Some(&step) {
+                true => &_expected[1..],
+                false => &[]
+            };
+            let mut line: Vec<Step> = Vec::default();
+            self.path.push(after.polyglot_key());
+            let score = -self.negamax(&after, _depth - 1, _ply + 1, (-beta, -alpha), &mut line, expected);
+            self.path.pop();
+            if self.stopped { return 0 }
+            if score > best {
+                best = score;
+                if score > alpha {
+                    alpha = score;
+                    line.insert(0, step);
+                    *_line = line;
+                }
+                if score >= beta { break }
+            }
+        }
+        best
+    }
+
+    // play captures until the position is quiet, and score it; a side in check has to answer it
+    fn quiesce(&mut self, _game: &Game, _ply: u32, _window: (i32, i32), _line: &mut Vec<Step>) -> i32 {
+        let (mut alpha, beta) = _window;
+        self.nodes += 1;
+        if self.out_of_time() { return 0 }
+        if _game.state == GameState::GameOver { return -(MATE - _ply as i32) }
+        let in_check = _game.state == GameState::Check;
+        let mut best = -MATE;
+        // a side that is not in check does not have to take, so it scores at least the position as it is
+        if !in_check || _ply >= MAX_PLY {
+            best = evaluate(_game);
+            if best >= beta || _ply >= MAX_PLY { return best }
+            alpha = alpha.max(best);
+        }
+
+        let moves: Vec<Step> = legal_moves(_game).into_iter().filter(|m| in_check || is_noisy(_game, m)).collect();
+        for step in order(_game, moves, None) {
+            let after = after_move(_game, &step.0, &step.1, step.2);
+            let mut line: Vec<Step> = Vec::default();
+            let score = -self.quiesce(&after, _ply + 1, (-beta, -alpha), &mut line);
+            if self.stopped { return 0 }
+            if score > best {
+                best = score;
+                if score > alpha {
+                    alpha = score;
+                    line.insert(0, step);
+                    *_line = line;
+                }
+                if score >= beta { break }
+            }
+        }
+        best
+    }
+}
+
+impl Engine {
+    /// An engine that searches to the given depth, without a time limit.
+    pub fn new(_depth: u32) -> Engine {
+        Engine { depth: _depth, move_time: None }
+    }
+
+    /// Search for the best move of the side to move, stopping at the depth of the
+    /// engine or at the deadline, whichever comes first. A search to depth 1 is
+    /// always finished. Returns None if the side to move has no moves.
+    pub fn search_until(&self, _game: &Game, _deadline: Option<Instant>) -> Option<Analysis> {
+        let game = _game.scratch();
+        if game.outcome.is_some() || legal_moves(&game).is_empty() { return None }
+        let mut search = Search::new(_game, None);
+        let mut best: Option<(Vec<Step>, i32, u32)> = None;
+        for depth in 1..=self.depth.max(1) {
+            let expected = best.as_ref().map(|b| b.0.clone()).unwrap_or_default();
+            let mut line: Vec<Step> = Vec::default();
+            let score = search.negamax(&game, depth, 0, (-MATE, MATE), &mut line, &expected);
+            if search.stopped { break }
+            best = Some((line, score, depth));
+            search.deadline = _deadline;
+            // there is no need to look further once a mate has been found
+            if score.abs() >= MATE - MAX_PLY as i32 { break }
+        }
+        let (line, score, depth) = best?;
+        Some(Analysis { line: to_moves(&game, &line), score: to_score(score), depth, nodes: search.nodes })
+    }
+
+    /// Search for the best move of the side to move, in the time the engine is given
+    /// for moves in games without a clock. Returns None if the side to move has no moves.
+    pub fn search(&self, _game: &Game) -> Option<Analysis> {
+        self.search_until(_game, self.move_time.map(|t| Instant::now() + t))
+    }
+
+    /// Search every move of the side to move to the depth of the engine, and get
+    /// them with their scores, best first. Takes longer than `search`, as the score
+    /// of every move is found exactly rather than only shown to be worse than the best.
+    pub fn analyse(&self, _game: &Game) -> Vec<Analysis> {
+        let game = _game.scratch();
+        if game.outcome.is_some() { return Vec::default() }
+        let mut search = Search::new(_game, None);
+        let mut analyses: Vec<Analysis> = Vec::default();
+        for step in order(&game, legal_moves(&game), None) {
+            let after = after_move(&game, &step.0, &step.1, step.2);
+            let mut line: Vec<Step> = Vec::default();
+            search.path.push(after.polyglot_key());
+            let score = -search.negamax(&after, self.depth.max(1) - 1, 1, (-MATE, MATE), &mut line, &[]);
+            search.path.pop();
+            line.insert(0, step);
+            analyses.push(Analysis { line: to_moves(&game, &line), score: to_score(score), depth: self.depth.max(1), nodes: search.nodes });
+        }
+        analyses.sort_by_key(|a| Reverse(match a.score {
+            Score::Mate(moves) if moves > 0 => MATE - moves,
+            Score::Mate(moves) => -MATE - moves,
+            Score::Centipawns(cp) => cp
+        }));
+        analyses
+    }
+
+    // get the time to think about a move, from the time left on the clock if the game has one
+    fn think_time(&self, _game: &Game) -> Option<Duration> {
+        let clock = match _game.get_clock() {
+            Some(c) => c,
+            None => return self.move_time
+        };
+        let remaining = clock.remaining(_game.active);
+        let moves = clock.moves_to_go(_game.active).unwrap_or(30).max(1);
+        let increment = match clock.get_bonus(_game.active) {
+            Bonus::Fischer(t) | Bonus::Bronstein(t) | Bonus::Delay(t) => t,
+            Bonus::None => Duration::ZERO
+        };
+        // never more than half of what is left, so that a move is always given in time
+        Some((remaining / moves + increment / 2).min(remaining / 2))
+    }
+}
+
+impl Player for Engine {
+    fn name(&self) -> String {
+        format!("osveijer-chess depth {}", self.depth)
+    }
+
+    fn new_game(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn think(&mut self, _game: &Game) -> io::Result<Reply> {
+        let deadline = self.think_time(_game).map(|t| Instant::now() + t);
+        let analysis = self.search_until(_game, deadline)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "there is no move to play"))?;
+        let best = &analysis.line[0];
+        Ok(Reply { from: best.from.clone(), to: best.to.clone(), promotion: best.promotion, score: Some(analysis.score) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::super::uci::{Player, Score};
+    use super::super::{Colour, Game, Piece};
+    use super::{evaluate, Engine};
+
+    // the first move the engine chooses, in SAN
+    fn choice(_fen: &str, _depth: u32) -> String {
+        let game = Game::from_fen(_fen).unwrap();
+        Engine::new(_depth).search(&game).unwrap().line[0].san.clone()
+    }
+
+    #[test]
+    fn evaluation() {
+        // the starting position is level, and a side a queen up is well ahead
+        assert_eq!(evaluate(&Game::new()), 0);
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap();
+        assert!(evaluate(&game) < -800);
+    }
+
+    #[test]
+    fn mates() {
+        let game = Game::from_fen("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        let analysis = Engine::new(3).search(&game).unwrap();
+        assert_eq!(analysis.line[0].san, "Ra8#");
+        assert_eq!(analysis.score, Score::Mate(1));
+        assert_eq!(analysis.depth, 1);
+        // the side getting mated sees it coming
+        let game = Game::from_fen("k7/8/1K6/8/8/8/8/7R b - - 0 1").unwrap();
+        assert_eq!(Engine::new(2).search(&game).unwrap().score, Score::Mate(-1));
+    }
+
+    #[test]
+    fn captures() {
+        // a queen left hanging is taken
+        assert_eq!(choice("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1", 1), "Rxd5");
+        // a defended pawn is not taken with the queen, once the engine sees the recapture
+        assert_ne!(choice("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1", 1), "Qxd5");
+    }
+
+    #[test]
+    fn promotions() {
+        let game = Game::from_fen("8/1P6/8/8/8/8/k7/4K3 w - - 0 1").unwrap();
+        let analysis = Engine::new(2).search(&game).unwrap();
+        assert_eq!(analysis.line[0].promotion, Some(Piece::Queen(Colour::White)));
+    }
+
+    #[test]
+    fn draws() {
+        // a stalemated side has no move to give, and stalemating the other side scores a draw
+        let game = Game::from_fen("k7/P7/1K6/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(Engine::new(2).search(&game), None);
+        let game = Game::from_fen("7k/8/6Q1/8/8/8/8/K7 w - - 0 1").unwrap();
+        let analysis = Engine::new(2).analyse(&game);
+        let stalemate = analysis.iter().find(|a| a.line[0].san == "Qf7").unwrap();
+        assert_eq!(stalemate.score, Score::Centipawns(0));
+        assert_ne!(analysis[0].line[0].san, "Qf7");
+    }
+
+    #[test]
+    fn every_move() {
+        let game = Game::from_fen("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1").unwrap();
+        let analysis = Engine::new(1).analyse(&game);
+        assert_eq!(analysis.len(), 10);
+        assert_eq!(analysis[0].line[0].san, "Rxd5");
+        assert!(matches!(analysis[1].score, Score::Centipawns(cp) if cp < 0));
+    }
+
+    #[test]
+    fn time_limits() {
+        // a deep search is cut short, but a move is still given
+        let engine = Engine { depth: 20, move_time: Some(Duration::from_millis(100)) };
+        let started = Instant::now();
+        let analysis = engine.search(&Game::new()).unwrap();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(analysis.depth < 20);
+    }
+
+    #[test]
+    fn plays_games() {
+        let mut engine = Engine::new(1);
+        let reply = engine.think(&Game::from_fen("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1").unwrap()).unwrap();
+        assert_eq!((reply.from.as_str(), reply.to.as_str()), ("d1", "d5"));
+        assert_eq!(engine.name(), "osveijer-chess depth 1");
+    }
+}
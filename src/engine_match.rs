@@ -0,0 +1,462 @@
+//! Matches between two players, usually engines, to tell which is stronger.
+//!
+//! Each opening of the suite is played twice, with the players changing colours,
+//! so that neither is favoured by the openings. Games can be cut short when both
+//! players agree on the score, and a match can be stopped as soon as a sequential
+//! probability ratio test (SPRT) decides between two hypotheses about the
+//! difference in Elo between the players.
+//!
+//! Any `Player` can take part: an engine run over UCI, or the built-in `Engine`,
+//! so that two settings of it can be played against each other. Games are not
+//! adjudicated by endgame tablebases, as the crate cannot probe them.
+
+use std::io;
+use std::time::Duration;
+
+use super::clock::TimeControl;
+use super::pgn::Pgn;
+use super::uci::{Player, Score};
+use super::{opponent, play_promotion, Colour, Game, Termination};
+
+// the score given to a mate, in centipawns
+const MATE_SCORE: i32 = 100000;
+
+/// When to end games early, from the scores the players give their moves.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Adjudication {
+    /// A game is won when both players have scored it at least this many
+    /// centipawns in favour of the same side for `resign_moves` moves in a row.
+    pub resign_score: Option<i32>,
+    pub resign_moves: u32,
+    /// A game is drawn when both players have scored it within this many
+    /// centipawns of equal for `draw_moves` moves in a row, from move `draw_after`.
+    pub draw_score: Option<i32>,
+    pub draw_moves: u32,
+    pub draw_after: u32,
+    /// A game is drawn once it has gone on for this many moves.
+    pub max_moves: Option<u32>
+}
+
+impl Default for Adjudication {
+    fn default() -> Adjudication {
+        Adjudication {
+            resign_score: Some(1000),
+            resign_moves: 3,
+            draw_score: Some(10),
+            draw_moves: 8,
+            draw_after: 40,
+            max_moves: None
+        }
+    }
+}
+
+/// The hypotheses of a sequential probability ratio test, that the first player
+/// is `elo0` stronger than the second (H0) or `elo1` stronger (H1), and the
+/// chances of accepting H1 when H0 is true (`alpha`) and H0 when H1 is true (`beta`).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64
+}
+
+impl Default for Sprt {
+    fn default() -> Sprt {
+        Sprt { elo0: 0.0, elo1: 5.0, alpha: 0.05, beta: 0.05 }
+    }
+}
+
+impl Sprt {
+    /// Get the log-likelihood ratio below which H0 is accepted.
+    pub fn lower_bound(&self) -> f64 {
+        (self.beta / (1.0 - self.alpha)).ln()
+    }
+
+    /// Get the log-likelihood ratio above which H1 is accepted.
+    pub fn upper_bound(&self) -> f64 {
+        ((1.0 - self.beta) / self.alpha).ln()
+    }
+}
+
+/// The decision of a sequential probability ratio test.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Verdict {
+    /// H0 is accepted: the first player is not stronger by `elo1`.
+    AcceptH0,
+    /// H1 is accepted: the first player is stronger by more than `elo0`.
+    AcceptH1,
+    /// More games are needed.
+    Continue
+}
+
+/// How a match is played.
+#[derive(Clone)]
+pub struct MatchOptions {
+    /// The games to start from, played in turn. The starting position if empty.
+    pub openings: Vec<Game>,
+    pub time_control: TimeControl,
+    /// The number of rounds, in each of which an opening is played once with each colour.
+    pub rounds: usize,
+    pub adjudication: Adjudication,
+    /// A test to stop the match on once it is decided.
+    pub sprt: Option<Sprt>
+}
+
+impl Default for MatchOptions {
+    fn default() -> MatchOptions {
+        MatchOptions {
+            openings: Vec::default(),
+            time_control: TimeControl::fischer(Duration::from_secs(10), Duration::from_millis(100)),
+            rounds: 100,
+            adjudication: Adjudication::default(),
+            sprt: None
+        }
+    }
+}
+
+/// The results of a match, from the point of view of the first player.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MatchResult {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    /// The games played, in order.
+    pub games: Vec<Pgn>
+}
+
+// get the expected score of a player who is the given number of Elo stronger
+fn expected_score(_elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-_elo / 400.0))
+}
+
+// get the difference in Elo that gives an expected score
+fn elo_difference(_score: f64) -> f64 {
+    -400.0 * (1.0 / _score - 1.0).log10()
+}
+
+impl MatchResult {
+    /// Get the number of games finished.
+    pub fn total(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Get the share of the points the first player has scored, between 0 and 1.
+    pub fn score(&self) -> f64 {
+        match self.total() {
+            0 => 0.5,
+            n => (self.wins as f64 + self.draws as f64 / 2.0) / n as f64
+        }
+    }
+
+    // get the variance of the points scored in a game, 0 before any are played
+    fn variance(&self) -> f64 {
+        if self.total() == 0 { return 0.0 }
+        let n = self.total() as f64;
+        let s = self.score();
+        (self.wins as f64 * (1.0 - s).powi(2) + self.draws as f64 * (0.5 - s).powi(2) + self.losses as f64 * s.powi(2)) / n
+    }
+
+    /// Get the difference in Elo between the players, positive if the first is stronger.
+    /// Infinite if one player has scored every point.
+    pub fn elo(&self) -> f64 {
+        elo_difference(self.score())
+    }
+
+    /// Get the margin of error of the difference in Elo, at 95% confidence.
+    pub fn elo_error(&self) -> f64 {
+        if self.total() == 0 { return f64::INFINITY }
+        let deviation = (self.variance() / self.total() as f64).sqrt();
+        let (low, high) = (self.score() - 1.959964 * deviation, self.score() + 1.959964 * deviation);
+        (elo_difference(high) - elo_difference(low)) / 2.0
+    }
+
+    /// Get the log-likelihood ratio of the hypotheses of a test, using a normal
+    /// approximation of the results. It is 0 while every game has had the same result.
+    pub fn llr(&self, _sprt: &Sprt) -> f64 {
+        if self.variance() == 0.0 { return 0.0 }
+        let (s0, s1) = (expected_score(_sprt.elo0), expected_score(_sprt.elo1));
+        (s1 - s0) * (2.0 * self.score() - s0 - s1) / (2.0 * self.variance() / self.total() as f64)
+    }
+
+    /// Get the decision of a test on the results so far.
+    pub fn verdict(&self, _sprt: &Sprt) -> Verdict {
+        let llr = self.llr(_sprt);
+        if llr >= _sprt.upper_bound() {
+            Verdict::AcceptH1
+        } else if llr <= _sprt.lower_bound() {
+            Verdict::AcceptH0
+        } else {
+            Verdict::Continue
+        }
+    }
+}
+
+// get the score of a move in centipawns for white
+fn white_score(_score: Score, _mover: Colour) -> i32 {
+    let score = match _score {
+        Score::Centipawns(cp) => cp,
+        Score::Mate(moves) if moves > 0 => MATE_SCORE,
+        Score::Mate(_) => -MATE_SCORE
+    };
+    match _mover {
+        Colour::White => score,
+        Colour::Black => -score
+    }
+}
+
+// get the result to adjudicate, as the winner or none for a draw, from the scores of the moves played so far
+fn adjudicate(_scores: &[Option<i32>], _fullmove: u32, _adjudication: &Adjudication) -> Option<Option<Colour>> {
+    // the scores of the last moves of both players, or none if a move was not scored
+    let last = |moves: u32| -> Option<Vec<i32>> {
+        let plies = 2 * moves as usize;
+        if moves == 0 || _scores.len() < plies { return None }
+        _scores[_scores.len() - plies..].iter().copied().collect()
+    };
+    if let (Some(limit), Some(scores)) = (_adjudication.resign_score, last(_adjudication.resign_moves)) {
+        if scores.iter().all(|&s| s >= limit) { return Some(Some(Colour::White)) }
+        if scores.iter().all(|&s| s <= -limit) { return Some(Some(Colour::Black)) }
+    }
+    if let (Some(limit), Some(scores)) = (_adjudication.draw_score, last(_adjudication.draw_moves)) {
+        if _fullmove >= _adjudication.draw_after && scores.iter().all(|&s| s.abs() <= limit) { return Some(None) }
+    }
+    match _adjudication.max_moves {
+        Some(moves) if _fullmove > moves => Some(None),
+        _ => None
+    }
+}
+
+/// Play a game between two players from an opening, with the time control and
+/// adjudication of the options. A player who does not answer in time loses on
+/// time, and one who makes an illegal move, names a promotion the move cannot make
+/// or fails otherwise loses by abandonment.
+pub fn play_game(_white: &mut dyn Player, _black: &mut dyn Player, _opening: &Game, _options: &MatchOptions) -> io::Result<Game> {
+    _white.new_game()?;
+    _black.new_game()?;
+    let mut game = _opening.clone();
+    game.set_clock(_options.time_control.clone());
+    let mut scores: Vec<Option<i32>> = Vec::default();
+    while game.get_outcome().is_none() {
+        let mover = game.active;
+        let reply = match mover {
+            Colour::White => _white.think(&game),
+            Colour::Black => _black.think(&game)
+        };
+        let reply = match reply {
+            Ok(r) => r,
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                game.end(Some(opponent(mover)), Termination::Timeout);
+                break
+            },
+            Err(_) => {
+                game.abandon(mover);
+                break
+            }
+        };
+        // a flag fall ends the game as it is checked before the move
+        if play_promotion(&mut game, reply.from, reply.to, reply.promotion).is_none() {
+            game.abandon(mover);
+            break
+        }
+        scores.push(reply.score.map(|s| white_score(s, mover)));
+        if game.get_outcome().is_some() { break }
+        let fullmove = game.fullmove_number;
+        match adjudicate(&scores, fullmove, &_options.adjudication) {
+            Some(winner) => game.end(winner, Termination::Adjudication),
+            None => None
+        };
+    }
+    Ok(game)
+}
+
+/// Play a match between two players. The match stops early once the test of the
+/// options, if any, is decided. Fails if a player cannot start a new game.
+pub fn run_match(_first: &mut dyn Player, _second: &mut dyn Player, _options: &MatchOptions) -> io::Result<MatchResult> {
+    let openings = match _options.openings.is_empty() {
+        true => vec![Game::new()],
+        false => _options.openings.clone()
+    };
+    let mut result = MatchResult::default();
+    for round in 0.._options.rounds {
+        let opening = &openings[round % openings.len()];
+        for first_colour in [Colour::White, Colour::Black] {
+            let game = match first_colour {
+                Colour::White => play_game(_first, _second, opening, _options)?,
+                Colour::Black => play_game(_second, _first, opening, _options)?
+            };
+            match game.get_outcome().map(|o| o.winner) {
+                Some(Some(winner)) if winner == first_colour => result.wins += 1,
+                Some(Some(_)) => result.losses += 1,
+                _ => result.draws += 1
+            }
+
+            let mut pgn = Pgn::from_game(&game);
+            let (white, black) = match first_colour {
+                Colour::White => (_first.name(), _second.name()),
+                Colour::Black => (_second.name(), _first.name())
+            };
+            pgn.set_tag("White", &white);
+            pgn.set_tag("Black", &black);
+            pgn.set_tag("Round", &(round + 1).to_string());
+            pgn.set_tag("TimeControl", &_options.time_control.to_string());
+            result.games.push(pgn);
+
+            if let Some(sprt) = &_options.sprt {
+                if result.verdict(sprt) != Verdict::Continue { return Ok(result) }
+            }
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::super::engine::Engine;
+    use super::super::uci::{Player, Reply, Score};
+    use super::super::{code_moves, legal_moves, Colour, Game, Outcome, Piece, Termination};
+    use super::{play_game, run_match, MatchOptions, MatchResult, Sprt, Verdict};
+
+    // a player that plays the first legal move and always gives the same score, for the side to move
+    struct Scripted {
+        name: &'static str,
+        score: i32
+    }
+
+    impl Player for Scripted {
+        fn name(&self) -> String {
+            self.name.to_owned()
+        }
+
+        fn new_game(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn think(&mut self, _game: &Game) -> io::Result<Reply> {
+            let (from, to, _) = legal_moves(_game)[0].clone();
+            let squares = code_moves(&vec![from, to]);
+            Ok(Reply { from: squares[0].clone(), to: squares[1].clone(), promotion: None, score: Some(Score::Centipawns(self.score)) })
+        }
+    }
+
+    // a player that never answers in time
+    struct Silent;
+
+    impl Player for Silent {
+        fn name(&self) -> String {
+            "Silent".to_owned()
+        }
+
+        fn new_game(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn think(&mut self, _game: &Game) -> io::Result<Reply> {
+            Err(io::Error::new(io::ErrorKind::TimedOut, "no answer"))
+        }
+    }
+
+    // a player that always plays the same move, in UCI notation
+    struct Fixed(&'static str);
+
+    impl Player for Fixed {
+        fn name(&self) -> String {
+            self.0.to_owned()
+        }
+
+        fn new_game(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn think(&mut self, _game: &Game) -> io::Result<Reply> {
+            Ok(Reply::from_uci(self.0, _game.active).unwrap())
+        }
+    }
+
+    #[test]
+    fn resign_adjudication() {
+        // A always scores itself as winning and B itself as losing, so both agree A is winning
+        let mut options = MatchOptions { rounds: 2, ..MatchOptions::default() };
+        options.adjudication.resign_score = Some(500);
+        let mut a = Scripted { name: "A", score: 600 };
+        let mut b = Scripted { name: "B", score: -600 };
+        let result = run_match(&mut a, &mut b, &options).unwrap();
+        assert_eq!((result.wins, result.draws, result.losses), (4, 0, 0));
+        assert_eq!(result.games[1].get_tag("White"), Some("B"));
+        assert_eq!(result.games[1].get_tag("Result"), Some("0-1"));
+        assert_eq!(result.games[3].get_tag("Round"), Some("2"));
+        assert_eq!(result.elo(), f64::INFINITY);
+    }
+
+    #[test]
+    fn draw_adjudication() {
+        // each player scores itself as winning, so they never agree and the games are
+        // drawn by the limit on their length
+        let mut options = MatchOptions { rounds: 1, ..MatchOptions::default() };
+        options.adjudication.max_moves = Some(3);
+        let mut a = Scripted { name: "A", score: 600 };
+        let mut b = Scripted { name: "B", score: 600 };
+        let result = run_match(&mut a, &mut b, &options).unwrap();
+        assert_eq!((result.wins, result.draws, result.losses), (0, 2, 0));
+        assert_eq!(result.games[0].get_tag("Termination"), Some("adjudication"));
+        let game = result.games[0].replay().unwrap();
+        assert_eq!(game.get_outcome().unwrap().termination, Termination::Adjudication);
+        assert_eq!(game.get_history().len(), 6);
+    }
+
+    #[test]
+    fn timeouts() {
+        let mut a = Scripted { name: "A", score: 0 };
+        let game = play_game(&mut a, &mut Silent, &Game::new(), &MatchOptions::default()).unwrap();
+        assert_eq!(game.get_outcome(), Some(Outcome { winner: Some(Colour::White), termination: Termination::Timeout }));
+        assert_eq!(game.get_history().len(), 1);
+    }
+
+    #[test]
+    fn promotions() {
+        // the promotion is played as named, and naming one on a move that does not promote is illegal
+        let opening = Game::from_fen("8/1P5p/8/8/8/8/k7/4K3 w - - 0 1").unwrap();
+        let game = play_game(&mut Fixed("b7b8n"), &mut Fixed("a2a3"), &opening, &MatchOptions::default()).unwrap();
+        assert_eq!(game.get_history()[0].promotion, Some(Piece::Knight(Colour::White)));
+        assert_eq!(game.get_outcome(), Some(Outcome { winner: Some(Colour::Black), termination: Termination::Abandonment }));
+        let game = play_game(&mut Fixed("e2e4q"), &mut Fixed("e7e5"), &Game::new(), &MatchOptions::default()).unwrap();
+        assert!(game.get_history().is_empty());
+        assert_eq!(game.get_outcome().unwrap().termination, Termination::Abandonment);
+    }
+
+    #[test]
+    fn engine_settings() {
+        // the built-in engine at two depths
+        let mut options = MatchOptions { rounds: 1, ..MatchOptions::default() };
+        options.adjudication.max_moves = Some(6);
+        let result = run_match(&mut Engine::new(1), &mut Engine::new(2), &options).unwrap();
+        assert_eq!(result.total(), 2);
+        assert_eq!(result.games[0].get_tag("White"), Some("osveijer-chess depth 1"));
+        assert_eq!(result.games[1].get_tag("White"), Some("osveijer-chess depth 2"));
+    }
+
+    #[test]
+    fn match_statistics() {
+        let result = MatchResult { wins: 60, draws: 80, losses: 40, games: Vec::default() };
+        assert!((result.elo() - 38.76).abs() < 0.01);
+        assert!((result.elo_error() - 38.03).abs() < 0.01);
+        assert_eq!(MatchResult { wins: 3, draws: 0, losses: 1, games: Vec::default() }.score(), 0.75);
+    }
+
+    #[test]
+    fn sprt() {
+        let sprt = Sprt::default();
+        let result = MatchResult { wins: 60, draws: 80, losses: 40, games: Vec::default() };
+        assert!((result.llr(&sprt) - 0.4955).abs() < 0.001);
+        assert_eq!(result.verdict(&sprt), Verdict::Continue);
+        let result = MatchResult { wins: 600, draws: 800, losses: 400, games: Vec::default() };
+        assert_eq!(result.verdict(&sprt), Verdict::AcceptH1);
+        let result = MatchResult { wins: 400, draws: 800, losses: 500, games: Vec::default() };
+        assert_eq!(result.verdict(&sprt), Verdict::AcceptH0);
+        // a ratio is found without draws, but not while every game has been won
+        assert!(MatchResult { wins: 10, draws: 0, losses: 5, games: Vec::default() }.llr(&sprt) > 0.0);
+        assert_eq!(MatchResult { wins: 10, draws: 0, losses: 0, games: Vec::default() }.llr(&sprt), 0.0);
+        assert_eq!(MatchResult::default().llr(&sprt), 0.0);
+    }
+}
@@ -0,0 +1,218 @@
+//! An opening explorer over a collection of games.
+//!
+//! Every position of every game added is indexed by its Polyglot key, with the
+//! moves played from it and the results of the games they were played in. As
+//! the moves lead to other positions in the index, the index is a tree of the
+//! games, in which transpositions meet.
+
+use std::collections::{HashMap, HashSet};
+
+use super::pgn::Pgn;
+use super::{Colour, Game, Move};
+
+/// The number of games and how they ended.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// The number of games, including those without a result.
+    pub games: u32,
+    pub white_wins: u32,
+    pub draws: u32,
+    pub black_wins: u32
+}
+
+impl Stats {
+    // count a game with the given winner, or none for a draw or a game without a result
+    fn add(&mut self, _result: Option<Option<Colour>>) {
+        self.games += 1;
+        match _result {
+            Some(Some(Colour::White)) => self.white_wins += 1,
+            Some(Some(Colour::Black)) => self.black_wins += 1,
+            Some(None) => self.draws += 1,
+            None => ()
+        }
+    }
+
+    fn percentage(&self, _count: u32) -> f64 {
+        match self.games {
+            0 => 0.0,
+            n => 100.0 * _count as f64 / n as f64
+        }
+    }
+
+    /// Get the percentage of the games that white won.
+    pub fn white_percentage(&self) -> f64 {
+        self.percentage(self.white_wins)
+    }
+
+    /// Get the percentage of the games that were drawn.
+    pub fn draw_percentage(&self) -> f64 {
+        self.percentage(self.draws)
+    }
+
+    /// Get the percentage of the games that black won.
+    pub fn black_percentage(&self) -> f64 {
+        self.percentage(self.black_wins)
+    }
+}
+
+/// A move played from a position, with the games it was played in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MoveStats {
+    pub played: Move,
+    pub stats: Stats
+}
+
+// a position in the index, with the games that reached it and the moves played from it
+#[derive(Clone, Debug, Default)]
+struct Position {
+    stats: Stats,
+    moves: Vec<MoveStats>
+}
+
+/// An index of the positions in a collection of games.
+#[derive(Clone, Debug, Default)]
+pub struct Explorer {
+    positions: HashMap<u64, Position>,
+    games: usize
+}
+
+impl Explorer {
+    /// An explorer without any games.
+    pub fn new() -> Explorer {
+        Explorer::default()
+    }
+
+    /// Index every game in a collection of games in PGN.
+    pub fn from_pgn(_pgns: &str) -> Explorer {
+        let mut explorer = Explorer::new();
+        explorer.add_pgn(_pgns);
+        explorer
+    }
+
+    /// Get the number of games indexed.
+    pub fn len(&self) -> usize {
+        self.games
+    }
+
+    /// Check whether no games have been indexed.
+    pub fn is_empty(&self) -> bool {
+        self.games == 0
+    }
+
+    /// Add a game to the index, with its result if it has ended.
+    pub fn add_game(&mut self, _game: &Game) {
+        let result = _game.get_outcome().map(|o| o.winner);
+        // a position that is repeated in a game, or a move played again from it, is counted once
+        let mut seen: HashSet<(u64, Option<&str>)> = HashSet::default();
+        for record in &_game.history {
+            let key = record.before.polyglot_key();
+            let position = self.positions.entry(key).or_default();
+            if seen.insert((key, None)) {
+                position.stats.add(result);
+            }
+            if !seen.insert((key, Some(&record.played.san))) { continue }
+            match position.moves.iter_mut().find(|m| m.played.san == record.played.san) {
+                Some(m) => m.stats.add(result),
+                None => {
+                    let mut stats = Stats::default();
+                    stats.add(result);
+                    position.moves.push(MoveStats { played: record.played.clone(), stats });
+                }
+            }
+        }
+        let key = _game.polyglot_key();
+        if seen.insert((key, None)) {
+            self.positions.entry(key).or_default().stats.add(result);
+        }
+        self.games += 1;
+    }
+
+    /// Add every game in a collection of games in PGN to the index, and get the
+    /// number of games added. Games that cannot be replayed are left out.
+    pub fn add_pgn(&mut self, _pgns: &str) -> usize {
+        let mut added = 0;
+        for game in Pgn::parse_all(_pgns).iter().filter_map(Pgn::replay) {
+            self.add_game(&game);
+            added += 1;
+        }
+        added
+    }
+
+    /// Get the games that reached the current position of a game.
+    pub fn position_stats(&self, _game: &Game) -> Stats {
+        self.positions.get(&_game.polyglot_key()).map(|p| p.stats).unwrap_or_default()
+    }
+
+    /// Get the moves played from the current position of a game, the most played first.
+    pub fn moves(&self, _game: &Game) -> Vec<MoveStats> {
+        let mut moves = match self.positions.get(&_game.polyglot_key()) {
+            Some(p) => p.moves.clone(),
+            None => Vec::default()
+        };
+        moves.sort_by_key(|m| std::cmp::Reverse(m.stats.games));
+        moves
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Game;
+    use super::{Explorer, Stats};
+
+    static PGNS: &str = "[Event \"1\"]\n[Result \"1-0\"]\n\n1. e4 e5 2. Nf3 Nc6 1-0\n\n\
+        [Event \"2\"]\n[Result \"1/2-1/2\"]\n\n1. e4 c5 2. Nf3 1/2-1/2\n\n\
+        [Event \"3\"]\n[Result \"0-1\"]\n\n1. Nf3 Nc6 2. e4 e5 0-1\n\n\
+        [Event \"4\"]\n\n1. e4 e5 2. Ke3 *\n";
+
+    // the position after 1. e4 e5 2. Nf3 Nc6
+    fn two_knights() -> Game {
+        let mut game = Game::new();
+        for san in ["e4", "e5", "Nf3", "Nc6"] {
+            game.make_san_move(san);
+        }
+        game
+    }
+
+    #[test]
+    fn illegal_games_left_out() {
+        assert_eq!(Explorer::from_pgn(PGNS).len(), 3);
+    }
+
+    #[test]
+    fn move_stats() {
+        let explorer = Explorer::from_pgn(PGNS);
+        let start = Game::new();
+        assert_eq!(explorer.position_stats(&start), Stats { games: 3, white_wins: 1, draws: 1, black_wins: 1 });
+        let moves = explorer.moves(&start);
+        assert_eq!(moves.iter().map(|m| m.played.san.as_str()).collect::<Vec<&str>>(), vec!["e4", "Nf3"]);
+        assert_eq!(moves[0].stats, Stats { games: 2, white_wins: 1, draws: 1, black_wins: 0 });
+        assert_eq!(moves[0].stats.white_percentage(), 50.0);
+    }
+
+    #[test]
+    fn transpositions() {
+        // the third game transposes into the first
+        let explorer = Explorer::from_pgn(PGNS);
+        let game = two_knights();
+        assert_eq!(explorer.position_stats(&game).games, 2);
+        assert_eq!(explorer.position_stats(&game).black_percentage(), 50.0);
+        assert!(explorer.moves(&game).is_empty());
+    }
+
+    #[test]
+    fn add_games() {
+        let mut explorer = Explorer::from_pgn(PGNS);
+        assert_eq!(explorer.add_pgn("1. e4 e5 2. Nf3 Nc6 3. Bb5 *"), 1);
+        assert_eq!(explorer.len(), 4);
+        let moves = explorer.moves(&two_knights());
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].stats, Stats { games: 1, white_wins: 0, draws: 0, black_wins: 0 });
+    }
+
+    #[test]
+    fn repeated_moves() {
+        // a move repeated within a game counts the game once
+        let explorer = Explorer::from_pgn("1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 *");
+        assert_eq!(explorer.moves(&Game::new())[0].stats.games, 1);
+    }
+}
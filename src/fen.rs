@@ -0,0 +1,248 @@
+//! Forsyth–Edwards Notation (FEN) for loading and saving positions.
+//!
+//! Castling rights are written as "KQkq" when the rook is the outermost one on its
+//! side, and as the file of the rook otherwise (X-FEN), so Chess960 positions can be
+//! written too. Shredder-FEN, which always uses the files, is read as well.
+
+use super::{check_check, colour_index, decode_position, game_state_change, get_king_pos, opponent, Colour, Game, Piece, FILES};
+
+/// FEN of the standard starting position.
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+// get the rank pieces of a colour start on
+fn back_rank(_colour: Colour) -> usize {
+    match _colour {
+        Colour::White => 0,
+        Colour::Black => 7
+    }
+}
+
+impl Game {
+    /// Initialises a game from a position in FEN. Returns None if the FEN is malformed
+    /// or the position is not playable, e.g. a king is missing, a pawn is on the first or last rank,
+    /// or the side not to move is in check.
+    pub fn from_fen(_fen: &str) -> Option<Game> {
+        let fields: Vec<&str> = _fen.split_whitespace().collect();
+        if fields.len() < 2 || fields.len() > 6 { return None }
+
+        let mut game = Game::new();
+        game.board = [[None; 8]; 8];
+
+        // the placement is given from rank 8 down to rank 1
+        let ranks: Vec<&str> = fields[0].split('/').collect();
+        if ranks.len() != 8 { return None }
+        for (i, rank) in ranks.iter().enumerate() {
+            let row = 7 - i;
+            let mut file = 0;
+            for c in rank.chars() {
+                match c.to_digit(10) {
+                    Some(n) if (1..=8).contains(&n) => file += n as usize,
+                    Some(_) => return None,
+                    None => {
+                        if file > 7 { return None }
+                        let colour = match c.is_ascii_uppercase() {
+                            true => Colour::White,
+                            false => Colour::Black
+                        };
+                        let piece = Piece::from_letter(c, colour)?;
+                        // pawns never stand on the first or last rank
+                        if matches!(piece, Piece::Pawn(_)) && (row == 0 || row == 7) { return None }
+                        game.board[row][file] = Some(piece);
+                        file += 1;
+                    }
+                }
+            }
+            if file != 8 { return None }
+        }
+
+        game.active = match fields[1] {
+            "w" => Colour::White,
+            "b" => Colour::Black,
+            _ => return None
+        };
+        game.castling = [[None, None], [None, None]];
+        if fields.len() > 2 && fields[2] != "-" {
+            for c in fields[2].chars() {
+                let colour = match c.is_ascii_uppercase() {
+                    true => Colour::White,
+                    false => Colour::Black
+                };
+                let rank = back_rank(colour);
+                let king = game.board[rank].iter().position(|p| *p == Some(Piece::King(colour)));
+                let mut rooks = (0..8).filter(|&f| game.board[rank][f] == Some(Piece::Rook(colour)));
+                // the king side is towards the h-file, the queen side towards the a-file
+                let (side, file) = match c.to_ascii_lowercase() {
+                    'k' => (0, rooks.rfind(|&f| Some(f) > king)),
+                    'q' => (1, rooks.find(|&f| Some(f) < king)),
+                    'a'..='h' => {
+                        let file = FILES.iter().position(|&f| f == c.to_ascii_lowercase())?;
+                        (if Some(file) > king { 0 } else { 1 }, Some(file))
+                    },
+                    _ => return None
+                };
+                // rights that do not match the position are left out
+                if let (Some(k), Some(f)) = (king, file) {
+                    if k != 0 && k != 7 && game.board[rank][f] == Some(Piece::Rook(colour)) {
+                        game.castling[colour_index(colour)][side] = Some(f);
+                        if k != 4 || (f != 0 && f != 7) {
+                            game.chess960 = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        // the en passant square is kept if a pawn has just moved past it
+        if fields.len() > 3 && fields[3] != "-" {
+            if !super::check_position(fields[3]) { return None }
+            let square = decode_position(fields[3]);
+            let (rank, pawn) = match game.active {
+                Colour::White => (5, Piece::Pawn(Colour::Black)),
+                Colour::Black => (2, Piece::Pawn(Colour::White))
+            };
+            let pushed = match game.active {
+                Colour::White => 4,
+                Colour::Black => 3
+            };
+            if square[0] == rank && game.board[rank][square[1]].is_none() && game.board[pushed][square[1]] == Some(pawn) {
+                game.en_passant = Some(square);
+            }
+        }
+
+        if fields.len() > 4 {
+            game.halfmove_clock = fields[4].parse().ok()?;
+        }
+        if fields.len() > 5 {
+            game.fullmove_number = fields[5].parse().ok()?;
+        }
+
+        // each side needs exactly one king, and the side that just moved cannot be left in check
+        for colour in [Colour::White, Colour::Black] {
+            let kings = game.board.iter().flatten().filter(|p| **p == Some(Piece::King(colour))).count();
+            if kings != 1 { return None }
+        }
+        let waiting = opponent(game.active);
+        if check_check(&get_king_pos(&game, waiting), &game, waiting) { return None }
+
+        // work out whether the side to move is in check or mated
+        game.active = waiting;
+        game_state_change(&mut game);
+        game.active = opponent(waiting);
+        game.update_outcome();
+        Some(game)
+    }
+
+    /// Get the current position in FEN.
+    pub fn to_fen(&self) -> String {
+        self.fen(false)
+    }
+
+    /// Get the current position in Shredder-FEN, which gives castling rights by the files of the rooks.
+    pub fn to_shredder_fen(&self) -> String {
+        self.fen(true)
+    }
+
+    fn fen(&self, _shredder: bool) -> String {
+        let mut ranks: Vec<String> = Vec::default();
+        for row in self.board.iter().rev() {
+            let mut rank = String::default();
+            let mut empty = 0;
+            for square in row {
+                match square {
+                    Some(p) => {
+                        if empty > 0 {
+                            rank += &empty.to_string();
+                            empty = 0;
+                        }
+                        rank.push(match p.unwrap() {
+                            Colour::White => p.letter(),
+                            Colour::Black => p.letter().to_ascii_lowercase()
+                        });
+                    },
+                    None => empty += 1
+                }
+            }
+            if empty > 0 {
+                rank += &empty.to_string();
+            }
+            ranks.push(rank);
+        }
+
+        let active = match self.active {
+            Colour::White => "w",
+            Colour::Black => "b"
+        };
+
+        let mut castling = String::default();
+        for colour in [Colour::White, Colour::Black] {
+            let rank = back_rank(colour);
+            for side in 0..2 {
+                if let Some(file) = self.castling[colour_index(colour)][side] {
+                    // other rooks further out on the same side make the letter ambiguous
+                    let outer = match side {
+                        0 => file + 1..8,
+                        _ => 0..file
+                    };
+                    let outermost = outer.into_iter().all(|f| self.board[rank][f] != Some(Piece::Rook(colour)));
+                    let letter = match (_shredder || !outermost, side) {
+                        (true, _) => FILES[file].to_ascii_uppercase(),
+                        (false, 0) => 'K',
+                        (false, _) => 'Q'
+                    };
+                    castling.push(match colour {
+                        Colour::White => letter,
+                        Colour::Black => letter.to_ascii_lowercase()
+                    });
+                }
+            }
+        }
+        if castling.is_empty() {
+            castling.push('-');
+        }
+
+        let en_passant = match &self.en_passant {
+            Some(square) => format!("{}{}", FILES[square[1]], square[0] + 1),
+            None => "-".to_owned()
+        };
+        format!("{} {} {} {} {} {}", ranks.join("/"), active, castling, en_passant, self.halfmove_clock, self.fullmove_number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Game, GameState};
+    use super::START_FEN;
+
+    #[test]
+    fn fen_round_trip() {
+        let mut game = Game::new();
+        assert_eq!(game.to_fen(), START_FEN);
+
+        game.make_move("e2".to_string(), "e4".to_string());
+        game.make_move("g8".to_string(), "f6".to_string());
+        game.make_move("g1".to_string(), "f3".to_string());
+        let fen = "rnbqkb1r/pppppppp/5n2/8/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 2 2";
+        assert_eq!(game.to_fen(), fen);
+        assert_eq!(Game::from_fen(fen).unwrap().to_fen(), fen);
+
+        // fool's mate
+        let mated = Game::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3").unwrap();
+        assert_eq!(mated.get_game_state(), GameState::GameOver);
+
+        assert!(Game::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w - - 0 1").is_none());
+        assert!(Game::from_fen("rnbq1bnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1").is_none());
+        assert!(Game::from_fen("4k2R/8/8/8/8/8/8/4K3 w - - 0 1").is_none());
+        // pawns on the first or last rank
+        assert!(Game::from_fen("P3k3/8/8/8/8/8/8/4K3 w - - 0 1").is_none());
+        assert!(Game::from_fen("4k3/8/8/8/8/8/8/p3K3 b - - 0 1").is_none());
+
+        // en passant squares, and castling rights that are lost or written by file
+        let fen = "rnbqkbnr/ppp1pppp/8/8/3pP3/5N2/PPPP1PPP/RNBQKB1R b Kq e3 0 3";
+        assert_eq!(Game::from_fen(fen).unwrap().to_fen(), fen);
+        assert_eq!(Game::from_fen("r3k3/8/8/8/8/8/8/4K2R w KQkq - 0 1").unwrap().to_fen(), "r3k3/8/8/8/8/8/8/4K2R w Kq - 0 1");
+        let chess960 = Game::from_fen("rk2r3/8/8/8/8/8/8/RK2R3 w EAea - 0 1").unwrap();
+        assert_eq!(chess960.to_fen(), "rk2r3/8/8/8/8/8/8/RK2R3 w KQkq - 0 1");
+        assert_eq!(chess960.to_shredder_fen(), "rk2r3/8/8/8/8/8/8/RK2R3 w EAea - 0 1");
+        assert_eq!(Game::from_fen("4k1rr/8/8/8/8/8/8/4K1RR w Gg - 0 1").unwrap().to_fen(), "4k1rr/8/8/8/8/8/8/4K1RR w Gg - 0 1");
+    }
+}
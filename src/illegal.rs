@@ -0,0 +1,248 @@
+//! Explaining why a move is not legal.
+
+use std::fmt;
+
+use super::{after_move, castling_squares, check_check, check_position, code_moves, colour_index, decode_position, get_king_pos, opponent, Colour, Game, Piece};
+
+/// The rule a move breaks.
+#[derive(Clone, Debug, PartialEq)]
+pub enum IllegalMove {
+    /// A square is not in the format "<file><rank>".
+    InvalidSquare,
+    /// The game has already ended.
+    GameOver,
+    /// There is no piece on the square moved from.
+    NoPiece,
+    /// The piece belongs to the side that is not to move.
+    WrongSide,
+    /// The target square holds a piece of the same side.
+    OwnPiece,
+    /// The piece does not move that way, e.g. a bishop moving along a file.
+    CannotMoveThatWay,
+    /// A pawn can only move diagonally to take something.
+    NothingToTake,
+    /// A piece stands in the way.
+    PathBlocked { blocker: String },
+    /// The piece is pinned to its king and would leave the line of the pin.
+    Pinned { pinner: String },
+    /// The king is in check and the move does not get it out of check.
+    DoesNotResolveCheck { checkers: Vec<String> },
+    /// The king would move onto a square that is attacked.
+    KingIntoCheck { attackers: Vec<String> },
+    /// The move would leave the king attacked in some other way, e.g. an en passant capture opening a line.
+    ExposesKing,
+    /// The king or the rook has already moved, or the rook has been taken.
+    NoCastlingRight,
+    /// The king cannot castle while in check.
+    CastlingOutOfCheck,
+    /// The king cannot pass over or land on an attacked square when castling.
+    CastlingThroughCheck { square: String }
+}
+
+impl fmt::Display for IllegalMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IllegalMove::InvalidSquare => write!(f, "not a square"),
+            IllegalMove::GameOver => write!(f, "the game is over"),
+            IllegalMove::NoPiece => write!(f, "there is no piece there"),
+            IllegalMove::WrongSide => write!(f, "it is the other side's move"),
+            IllegalMove::OwnPiece => write!(f, "a piece of your own is on that square"),
+            IllegalMove::CannotMoveThatWay => write!(f, "the piece does not move that way"),
+            IllegalMove::NothingToTake => write!(f, "a pawn only moves diagonally to take"),
+            IllegalMove::PathBlocked { blocker } => write!(f, "the way is blocked on {}", blocker),
+            IllegalMove::Pinned { pinner } => write!(f, "the piece is pinned by {}", pinner),
+            IllegalMove::DoesNotResolveCheck { checkers } => write!(f, "the king is in check from {}", checkers.join(" and ")),
+            IllegalMove::KingIntoCheck { attackers } => write!(f, "the king would be in check from {}", attackers.join(" and ")),
+            IllegalMove::ExposesKing => write!(f, "the move would leave the king in check"),
+            IllegalMove::NoCastlingRight => write!(f, "castling is no longer allowed on that side"),
+            IllegalMove::CastlingOutOfCheck => write!(f, "the king cannot castle out of check"),
+            IllegalMove::CastlingThroughCheck { square } => write!(f, "the king cannot castle through check on {}", square)
+        }
+    }
+}
+
+// get the squares strictly between two squares on a line
+fn between(_from: &[usize], _to: &[usize]) -> Vec<Vec<usize>> {
+    let dr = (_to[0] as i32 - _from[0] as i32).signum();
+    let df = (_to[1] as i32 - _from[1] as i32).signum();
+    let mut squares: Vec<Vec<usize>> = Vec::default();
+    let mut rank = _from[0] as i32 + dr;
+    let mut file = _from[1] as i32 + df;
+    while (rank, file) != (_to[0] as i32, _to[1] as i32) {
+        squares.push(vec![rank as usize, file as usize]);
+        rank += dr;
+        file += df;
+    }
+    squares
+}
+
+// explain why a king move to the back rank is not a legal castling move, if it is an attempt to castle
+fn explain_castling(_game: &Game, _from: &[usize], _to: &[usize], _colour: Colour) -> Option<IllegalMove> {
+    let rank = match _colour {
+        Colour::White => 0,
+        Colour::Black => 7
+    };
+    if _from[0] != rank || _to[0] != rank { return None }
+    let rights = _game.castling[colour_index(_colour)];
+
+    // in Chess960 the king castles by moving onto its rook, otherwise by moving two squares
+    let side = match _game.chess960 {
+        true => {
+            if _game.board[_to[0]][_to[1]] != Some(Piece::Rook(_colour)) { return None }
+            match (0..2).find(|&s| rights[s] == Some(_to[1])) {
+                Some(s) => s,
+                None => return Some(IllegalMove::NoCastlingRight)
+            }
+        },
+        false => {
+            if _from[1] != 4 || _from[1].abs_diff(_to[1]) != 2 { return None }
+            let side = if _to[1] > _from[1] { 0 } else { 1 };
+            match rights[side] {
+                Some(f) if _game.board[rank][f] == Some(Piece::Rook(_colour)) => side,
+                _ => return Some(IllegalMove::NoCastlingRight)
+            }
+        }
+    };
+    if _game.board[rank][rights[side]?] != Some(Piece::Rook(_colour)) { return Some(IllegalMove::NoCastlingRight) }
+    if _game.is_in_check(_colour) { return Some(IllegalMove::CastlingOutOfCheck) }
+
+    let rook = rights[side]?;
+    let king = _from[1];
+    let (king_to, rook_to) = castling_squares(side);
+    let low = king.min(king_to).min(rook).min(rook_to);
+    let high = king.max(king_to).max(rook).max(rook_to);
+    if let Some(f) = (low..=high).find(|&f| f != king && f != rook && _game.board[rank][f].is_some()) {
+        return Some(IllegalMove::PathBlocked { blocker: code_moves(&vec![vec![rank, f]])[0].clone() })
+    }
+    let mut game = _game.scratch();
+    game.board[rank][king] = None;
+    game.board[rank][rook] = None;
+    (king.min(king_to)..=king.max(king_to))
+        .find(|&f| check_check(&vec![rank, f], &game, _colour))
+        .map(|f| IllegalMove::CastlingThroughCheck { square: code_moves(&vec![vec![rank, f]])[0].clone() })
+}
+
+impl Game {
+    // check whether the king of a colour is attacked
+    fn is_in_check(&self, _colour: Colour) -> bool {
+        check_check(&get_king_pos(self, _colour), self, _colour)
+    }
+
+    /// Explain why a move is not legal, or get None if it is.
+    pub fn explain_illegal(&self, _from: String, _to: String) -> Option<IllegalMove> {
+        if !check_position(&_from) || !check_position(&_to) { return Some(IllegalMove::InvalidSquare) }
+        if self.get_outcome().is_some() { return Some(IllegalMove::GameOver) }
+        let from = decode_position(&_from);
+        let to = decode_position(&_to);
+        let piece = match self.board[from[0]][from[1]] {
+            Some(p) => p,
+            None => return Some(IllegalMove::NoPiece)
+        };
+        let colour = piece.unwrap();
+        if colour != self.active { return Some(IllegalMove::WrongSide) }
+        if self.get_possible_moves(_from.clone())?.contains(&_to) { return None }
+
+        if let Piece::King(_) = piece {
+            if let Some(reason) = explain_castling(self, &from, &to, colour) {
+                return Some(reason)
+            }
+        }
+        let target = self.board[to[0]][to[1]];
+        if target.map(|p| p.unwrap()) == Some(colour) { return Some(IllegalMove::OwnPiece) }
+
+        // check the shape of the move, and what stands in the way
+        let ranks = to[0] as i32 - from[0] as i32;
+        let files = to[1] as i32 - from[1] as i32;
+        let straight = ranks == 0 || files == 0;
+        let diagonal = ranks.abs() == files.abs();
+        let forward = match colour {
+            Colour::White => 1,
+            Colour::Black => -1
+        };
+        let shaped = match piece {
+            Piece::King(_) => ranks.abs() <= 1 && files.abs() <= 1,
+            Piece::Knight(_) => (ranks.abs(), files.abs()) == (1, 2) || (ranks.abs(), files.abs()) == (2, 1),
+            Piece::Bishop(_) => diagonal,
+            Piece::Rook(_) => straight,
+            Piece::Queen(_) => straight || diagonal,
+            Piece::Pawn(_) => {
+                let start = match colour {
+                    Colour::White => 1,
+                    Colour::Black => 6
+                };
+                (files == 0 && (ranks == forward || (ranks == 2 * forward && from[0] == start)))
+                    || (files.abs() == 1 && ranks == forward)
+            }
+        };
+        if !shaped { return Some(IllegalMove::CannotMoveThatWay) }
+
+        let mut path = match piece {
+            Piece::Bishop(_) | Piece::Rook(_) | Piece::Queen(_) | Piece::Pawn(_) => between(&from, &to),
+            _ => Vec::default()
+        };
+        if let Piece::Pawn(_) = piece {
+            if files == 0 {
+                // pawns cannot take straight ahead
+                path.push(to.clone());
+            } else if target.is_none() && self.en_passant.as_ref() != Some(&to) {
+                return Some(IllegalMove::NothingToTake)
+            }
+        }
+        if let Some(square) = path.iter().find(|s| self.board[s[0]][s[1]].is_some()) {
+            return Some(IllegalMove::PathBlocked { blocker: code_moves(&vec![square.clone()])[0].clone() })
+        }
+
+        // the move follows the rules for the piece, so it must leave the king attacked
+        let after = after_move(self, &from, &to, piece);
+        if let Piece::King(_) = piece {
+            let attackers = after.attackers_of(_to, opponent(colour)).unwrap_or_default();
+            return Some(IllegalMove::KingIntoCheck { attackers })
+        }
+        if let Some(pin) = self.pins(colour).into_iter().find(|p| p.pinned == _from) {
+            if !pin.ray.contains(&_to) {
+                return Some(IllegalMove::Pinned { pinner: pin.pinner })
+            }
+        }
+        if self.is_in_check(colour) {
+            return Some(IllegalMove::DoesNotResolveCheck { checkers: self.checkers() })
+        }
+        Some(IllegalMove::ExposesKing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Game;
+    use super::IllegalMove;
+
+    #[test]
+    fn explain_illegal() {
+        let explain = |game: &Game, from: &str, to: &str| game.explain_illegal(from.to_string(), to.to_string());
+        let game = Game::new();
+        assert_eq!(explain(&game, "e2", "e4"), None);
+        assert_eq!(explain(&game, "e2", "e9"), Some(IllegalMove::InvalidSquare));
+        assert_eq!(explain(&game, "e4", "e5"), Some(IllegalMove::NoPiece));
+        assert_eq!(explain(&game, "e7", "e5"), Some(IllegalMove::WrongSide));
+        assert_eq!(explain(&game, "d1", "d2"), Some(IllegalMove::OwnPiece));
+        assert_eq!(explain(&game, "c1", "c3"), Some(IllegalMove::CannotMoveThatWay));
+        assert_eq!(explain(&game, "e2", "d3"), Some(IllegalMove::NothingToTake));
+        assert_eq!(explain(&game, "d1", "d4"), Some(IllegalMove::PathBlocked { blocker: "d2".to_string() }));
+        assert_eq!(explain(&game, "e1", "g1"), Some(IllegalMove::PathBlocked { blocker: "f1".to_string() }));
+
+        let game = Game::from_fen("4k3/8/8/q7/8/8/3N4/4K2R w K - 0 1").unwrap();
+        assert_eq!(explain(&game, "d2", "f3"), Some(IllegalMove::Pinned { pinner: "a5".to_string() }));
+        assert_eq!(explain(&game, "e1", "d1"), None);
+        let game = Game::from_fen("4k3/8/8/8/8/8/3N4/r3K2R w K - 0 1").unwrap();
+        assert_eq!(explain(&game, "d2", "f3"), Some(IllegalMove::DoesNotResolveCheck { checkers: vec!["a1".to_string()] }));
+        assert_eq!(explain(&game, "e1", "g1"), Some(IllegalMove::CastlingOutOfCheck));
+        assert_eq!(explain(&game, "e1", "f1"), Some(IllegalMove::KingIntoCheck { attackers: vec!["a1".to_string()] }));
+        let game = Game::from_fen("4k3/8/8/8/8/8/5r2/4K2R w K - 0 1").unwrap();
+        assert_eq!(explain(&game, "e1", "g1"), Some(IllegalMove::CastlingThroughCheck { square: "f1".to_string() }));
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/4K2R w - - 0 1").unwrap();
+        assert_eq!(explain(&game, "e1", "g1"), Some(IllegalMove::NoCastlingRight));
+
+        // taking en passant would open the rank to the king
+        let game = Game::from_fen("8/8/8/K2pP2r/8/8/8/4k3 w - d6 0 1").unwrap();
+        assert_eq!(explain(&game, "e5", "d6"), Some(IllegalMove::ExposesKing));
+    }
+}
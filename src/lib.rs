@@ -1,12 +1,94 @@
 use std::fmt;
+use std::time::Instant;
+
+pub mod attacks;
+pub mod binary;
+pub mod cecp;
+pub mod chess960;
+pub mod clock;
+pub mod database;
+pub mod eco;
+pub mod engine;
+pub mod engine_match;
+pub mod explorer;
+pub mod fen;
+pub mod illegal;
+pub mod mate;
+pub mod pgn;
+pub mod polyglot;
+pub mod puzzles;
+pub mod ratings;
+pub mod render;
+pub mod san;
+#[cfg(feature = "serde")]
+pub mod serialise;
+pub mod svg;
+pub mod tactics;
+pub mod tournament;
+pub mod tree;
+pub mod uci;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum GameState {
     InProgress,
     Check,
     GameOver
 }
 
+/// The way a game has ended.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Termination {
+    Checkmate,
+    /// The side to move has no legal moves but is not in check.
+    Stalemate,
+    Resignation,
+    /// A player ran out of time.
+    Timeout,
+    /// The players agreed to a draw.
+    Agreement,
+    /// The same position occurred three times and a draw was claimed, or five times.
+    Repetition,
+    /// Fifty moves were played without a capture or pawn move and a draw was claimed, or seventy-five.
+    FiftyMoves,
+    /// Neither side has the material to mate.
+    InsufficientMaterial,
+    /// A player left the game.
+    Abandonment,
+    /// The game was decided off the board, e.g. by a match runner from the scores engines gave.
+    Adjudication
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self {
+            Termination::Checkmate => "checkmate",
+            Termination::Stalemate => "stalemate",
+            Termination::Resignation => "resignation",
+            Termination::Timeout => "timeout",
+            Termination::Agreement => "agreement",
+            Termination::Repetition => "repetition",
+            Termination::FiftyMoves => "fifty-move rule",
+            Termination::InsufficientMaterial => "insufficient material",
+            Termination::Abandonment => "abandonment",
+            Termination::Adjudication => "adjudication"
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+/// The result of a game that has ended.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outcome {
+    /// The side that won, or None for a draw.
+    pub winner: Option<Colour>,
+    pub termination: Termination
+}
+
 /* IMPORTANT:
  * - Document well!
  * - Write well structured and clean code!
@@ -16,6 +98,8 @@ pub enum GameState {
 static FILES: [char; 8] = ['a','b','c','d','e','f','g','h'];
 
 #[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum Colour {
     White,
     Black
@@ -58,25 +142,89 @@ impl Piece {
             Colour::White => out
         }
     }
+
+    /// Get the letter of the piece used in algebraic notation and FEN, e.g. 'N' for a knight.
+    pub fn letter(&self) -> char {
+        match self {
+            Piece::King(_) => 'K',
+            Piece::Queen(_) => 'Q',
+            Piece::Bishop(_) => 'B',
+            Piece::Knight(_) => 'N',
+            Piece::Rook(_) => 'R',
+            Piece::Pawn(_) => 'P'
+        }
+    }
+
+    // get the piece of the given type and colour from its letter, ignoring case
+    fn from_letter(_letter: char, _colour: Colour) -> Option<Piece> {
+        match _letter.to_ascii_uppercase() {
+            'K' => Some(Piece::King(_colour)),
+            'Q' => Some(Piece::Queen(_colour)),
+            'B' => Some(Piece::Bishop(_colour)),
+            'N' => Some(Piece::Knight(_colour)),
+            'R' => Some(Piece::Rook(_colour)),
+            'P' => Some(Piece::Pawn(_colour)),
+            _ => None
+        }
+    }
+}
+
+/// A move that has been played in a game.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Move {
+    pub from: String,
+    pub to: String,
+    /// The piece a pawn was promoted to, if the move was a promotion.
+    pub promotion: Option<Piece>,
+    /// The move in standard algebraic notation, e.g. "Nxe5+".
+    pub san: String
+}
+
+// a played move together with the game as it was before the move, so that it can be undone
+#[derive(Clone)]
+struct Record {
+    played: Move,
+    before: Game
 }
 
-#[derive(Copy,Clone)]
+#[derive(Clone)]
 pub struct Game {
     /* save board, active colour, ... */
     state: GameState,
     pub active: Colour,
     pub board: [[Option<Piece>;8];8],
     white_promotion: Piece,
-    black_promotion: Piece
+    black_promotion: Piece,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    // files of the rooks that can still castle, for white and black, on the king side and the queen side
+    castling: [[Option<usize>; 2]; 2],
+    // the square a pawn can be taken on en passant, after a pawn has moved two squares
+    en_passant: Option<Vec<usize>>,
+    // in Chess960 the king castles by moving onto the rook, as the king's move alone can be ambiguous
+    chess960: bool,
+    clock: Option<clock::Clock>,
+    // how the game ended, when it did not end on the board
+    outcome: Option<Outcome>,
+    // the player who has offered a draw that has not been answered yet
+    draw_offer: Option<Colour>,
+    history: Vec<Record>
 }
 
 
+// check that a &str is a square in the format "<file><rank>"
+fn check_position(_position: &str) -> bool {
+    let chars: Vec<char> = _position.chars().collect();
+    chars.len() == 2 && FILES.contains(&chars[0]) && ('1'..='8').contains(&chars[1])
+}
+
 // take &str in format "<file><rank>" and convert to vector of i8 with format [<column>,<row>]
 fn decode_position(_position: &str) -> Vec<usize> {
-    let mut vec: Vec<usize> = Vec::default();
-    vec.push(_position.chars().nth(1).unwrap().to_string().parse::<usize>().unwrap() - 1); // - 1 because index starts at 0
-    vec.push(FILES.iter().position(|&c| c == _position.chars().nth(0).unwrap()).unwrap());
-    vec
+    vec![
+        _position.chars().nth(1).unwrap().to_string().parse::<usize>().unwrap() - 1, // - 1 because index starts at 0
+        FILES.iter().position(|&c| c == _position.chars().next().unwrap()).unwrap()
+    ]
 }
 
 fn code_moves(_moves: &Vec<Vec<usize>>) -> Vec<String> {
@@ -90,16 +238,16 @@ fn code_moves(_moves: &Vec<Vec<usize>>) -> Vec<String> {
 fn get_availble_moves(_piece: Piece, _position: &Vec<usize>, _game: &Game, _checkable: bool) -> Vec<Vec<usize>> {
     // get all moves
     let mut moves: Vec<Vec<usize>> = match _piece {
-        Piece::King(c) => get_king_movement(&_position, _game, _checkable, c),
+        Piece::King(c) => get_king_movement(_position, _game, _checkable, c),
         Piece::Queen(c) => {
-            let mut moves = get_straight_moves(&_position, _game, c);
-            moves.append(&mut get_diagonal_moves(&_position,_game, c));
+            let mut moves = get_straight_moves(_position, _game, c);
+            moves.append(&mut get_diagonal_moves(_position,_game, c));
             moves
         },
-        Piece::Bishop(c) => get_diagonal_moves(&_position, _game, c),
-        Piece::Knight(c) => get_knight_moves(&_position,_game, c),
-        Piece::Rook(c) => get_straight_moves(&_position, _game, c),
-        Piece::Pawn(_) => get_pawn_moves(&_position,_game)
+        Piece::Bishop(c) => get_diagonal_moves(_position, _game, c),
+        Piece::Knight(c) => get_knight_moves(_position,_game, c),
+        Piece::Rook(c) => get_straight_moves(_position, _game, c),
+        Piece::Pawn(_) => get_pawn_moves(_position,_game)
     };
 
     // remove illegal moves
@@ -107,21 +255,124 @@ fn get_availble_moves(_piece: Piece, _position: &Vec<usize>, _game: &Game, _chec
         // if pinned, remove moves that reveal king
         moves = match _piece {
             Piece::King(_) => moves,
-            _ => check_pinned(&_position, _game, &moves, _game.active)
+            _ => check_pinned(_position, _game, &moves, _game.active)
         };
 
         // if in check, remove moves that do not resolve check
         if _game.state == GameState::Check {
             moves = match _piece {
                 Piece::King(_) => moves,
-                _ => resolve_check(&_position, _game, &moves, _game.active)
+                _ => resolve_check(_position, _game, &moves, _game.active)
             };
         }
+
+        // special moves are only tried out when the legal moves are asked for
+        match _piece {
+            Piece::King(c) => moves.append(&mut get_castling(_position, _game, c)),
+            Piece::Pawn(_) => moves.append(&mut get_en_passant(_position, _game)),
+            _ => ()
+        }
     }
 
     moves
 }
 
+// index of a colour in arrays kept for both sides
+fn colour_index(_colour: Colour) -> usize {
+    match _colour {
+        Colour::White => 0,
+        Colour::Black => 1
+    }
+}
+
+// get the squares the king and the rook end up on when castling on the king side (0) or the queen side (1)
+fn castling_squares(_side: usize) -> (usize, usize) {
+    match _side {
+        0 => (6, 5),
+        _ => (2, 3)
+    }
+}
+
+// get the castling moves of a king, which moves two squares, or onto its rook in Chess960
+fn get_castling(_position: &Vec<usize>, _game: &Game, _colour: Colour) -> Vec<Vec<usize>> {
+    let mut moves: Vec<Vec<usize>> = Vec::default();
+    let rank = match _colour {
+        Colour::White => 0,
+        Colour::Black => 7
+    };
+    if _position[0] != rank || _game.state == GameState::Check { return moves }
+
+    for side in 0..2 {
+        let rook = match _game.castling[colour_index(_colour)][side] {
+            Some(f) if _game.board[rank][f] == Some(Piece::Rook(_colour)) => f,
+            _ => continue
+        };
+        let king = _position[1];
+        let (king_to, rook_to) = castling_squares(side);
+
+        // the squares both pieces pass over must be empty, apart from the king and the rook themselves
+        let low = king.min(king_to).min(rook).min(rook_to);
+        let high = king.max(king_to).max(rook).max(rook_to);
+        if (low..=high).any(|f| f != king && f != rook && _game.board[rank][f].is_some()) { continue }
+
+        // the king cannot castle out of, through or into check
+        let mut game = _game.scratch();
+        game.board[rank][king] = None;
+        game.board[rank][rook] = None;
+        if (king.min(king_to)..=king.max(king_to)).any(|f| check_check(&vec![rank, f], &game, _colour)) { continue }
+
+        moves.push(match _game.chess960 {
+            true => vec![rank, rook],
+            false => vec![rank, king_to]
+        });
+    }
+    moves
+}
+
+// get the side a king move castles to, if it is a castling move
+fn castling_side(_game: &Game, _from: &[usize], _to: &[usize]) -> Option<usize> {
+    let colour = match _game.board[_from[0]][_from[1]] {
+        Some(Piece::King(c)) => c,
+        _ => return None
+    };
+    if _from[0] != _to[0] { return None }
+    (0..2).find(|&side| match _game.castling[colour_index(colour)][side] {
+        Some(rook) => match _game.chess960 {
+            true => _to[1] == rook && _game.board[_to[0]][_to[1]] == Some(Piece::Rook(colour)),
+            false => _to[1] == castling_squares(side).0 && _from[1].abs_diff(_to[1]) == 2
+        },
+        None => false
+    })
+}
+
+// get the en passant capture of a pawn, if it has one that does not leave its own king in check
+fn get_en_passant(_position: &Vec<usize>, _game: &Game) -> Vec<Vec<usize>> {
+    let mut moves: Vec<Vec<usize>> = Vec::default();
+    let target = match &_game.en_passant {
+        Some(t) => t,
+        None => return moves
+    };
+    let colour = _game.board[_position[0]][_position[1]].unwrap().unwrap();
+    let (rank, target_rank) = match colour {
+        Colour::White => (4, 5),
+        Colour::Black => (3, 2)
+    };
+    if _position[0] != rank || target[0] != target_rank || _position[1].abs_diff(target[1]) != 1
+        || _game.board[rank][target[1]] != Some(Piece::Pawn(opponent(colour))) {
+        return moves
+    }
+
+    // taking the pawn removes two pieces from the rank, so try the capture on a copy of the board
+    let mut game = _game.scratch();
+    game.board[target[0]][target[1]] = game.board[rank][_position[1]];
+    game.board[rank][_position[1]] = None;
+    game.board[rank][target[1]] = None;
+    if !check_check(&get_king_pos(&game, colour), &game, colour) {
+        moves.push(target.clone());
+    }
+    moves
+}
+
 
 fn get_king_movement(_position: &Vec<usize>, _game: &Game, _checkable: bool, _colour: Colour) -> Vec<Vec<usize>> {
     let mut moves: Vec<Vec<isize>> = Vec::default();
@@ -329,7 +580,7 @@ fn get_king_takes(_position: &Vec<usize>) -> Vec<Vec<usize>> {
     ];
 
     for i in offsets {
-        let mv = vec![_position[0] as isize + i.0, _position[1] as isize + i.1];
+        let mv = [_position[0] as isize + i.0, _position[1] as isize + i.1];
         if mv[0] >= 0 && mv[0] <= 7 && mv[1] >= 0 && mv[1] <= 7 {
             takes.push(vec![mv[0] as usize, mv[1] as usize]);
         }
@@ -352,7 +603,7 @@ fn get_knight_takes(_position: &Vec<usize>) -> Vec<Vec<usize>> {
     ];
     
     for i in offsets {
-        let mv = vec![_position[0] as isize + i.0, _position[1] as isize + i.1];
+        let mv = [_position[0] as isize + i.0, _position[1] as isize + i.1];
         if mv[0] >= 0 && mv[0] <= 7 && mv[1] >= 0 && mv[1] <= 7 {
             takes.push(vec![mv[0] as usize, mv[1] as usize]);
         }
@@ -380,7 +631,7 @@ fn get_diagonal_takes(_position: &Vec<usize>, _colour: Colour, _game: &Game) ->
 fn end_takes(_line: Vec<Vec<usize>>, _colour: Colour, _game: &Game) -> Vec<Vec<usize>> {
     let mut out: Vec<Vec<usize>> = Vec::default();
     for i in _line[1..].iter() {
-        if _game.board[i[0]][i[1]] == None || _game.board[i[0]][i[1]] == Some(Piece::King(_colour)) {
+        if _game.board[i[0]][i[1]].is_none() || _game.board[i[0]][i[1]] == Some(Piece::King(_colour)) {
             out.push(i.to_owned());
         } else {
             out.push(i.to_owned());
@@ -458,60 +709,60 @@ fn get_diagonal_moves(_position: &Vec<usize>, _game: &Game, _colour: Colour) ->
     // down right
     let line = get_line(_position, 1, 1);
     for i in line[1..].iter() {
-        match _game.board[i[0] as usize][i[1] as usize] {
+        match _game.board[i[0]][i[1]] {
             Some(x) => {match x.unwrap() == _colour {
                 true => break,
                 false => {
-                    moves.push(vec![i[0] as usize, i[1] as usize]);
+                    moves.push(vec![i[0], i[1]]);
                     break
                 }
             }},
-            None => moves.push(vec![i[0] as usize, i[1] as usize])
+            None => moves.push(vec![i[0], i[1]])
         }
     }
 
     // down left
     let line = get_line(_position, 1, -1);
     for i in line[1..].iter() {
-        match _game.board[i[0] as usize][i[1] as usize] {
+        match _game.board[i[0]][i[1]] {
             Some(x) => {match x.unwrap() == _colour {
                 true => break,
                 false => {
-                    moves.push(vec![i[0] as usize, i[1] as usize]);
+                    moves.push(vec![i[0], i[1]]);
                     break
                 }
             }},
-            None => moves.push(vec![i[0] as usize, i[1] as usize])
+            None => moves.push(vec![i[0], i[1]])
         }
     }
 
     // up right
     let line = get_line(_position, -1, 1);
     for i in line[1..].iter() {
-        match _game.board[i[0] as usize][i[1] as usize] {
+        match _game.board[i[0]][i[1]] {
             Some(x) => {match x.unwrap() == _colour {
                 true => break,
                 false => {
-                    moves.push(vec![i[0] as usize, i[1] as usize]);
+                    moves.push(vec![i[0], i[1]]);
                     break
                 }
             }},
-            None => moves.push(vec![i[0] as usize, i[1] as usize])
+            None => moves.push(vec![i[0], i[1]])
         }
     }
 
     // up left
     let line = get_line(_position, -1, -1);
     for i in line[1..].iter() {
-        match _game.board[i[0] as usize][i[1] as usize] {
+        match _game.board[i[0]][i[1]] {
             Some(x) => {match x.unwrap() == _colour {
                 true => break,
                 false => {
-                    moves.push(vec![i[0] as usize, i[1] as usize]);
+                    moves.push(vec![i[0], i[1]]);
                     break
                 }
             }},
-            None => moves.push(vec![i[0] as usize, i[1] as usize])
+            None => moves.push(vec![i[0], i[1]])
         }
     }
 
@@ -527,16 +778,14 @@ fn check_occupied(_position: &Vec<isize>, _game: &Game, _colour: Colour) -> bool
 
 fn check_check(_postion: &Vec<usize>, _game: &Game, _colour: Colour) -> bool {
     let mut in_check = false;
-    for i in _game.board.iter() {
-        for j in i {
+    for (file, i) in _game.board.iter().enumerate() {
+        for (rank, j) in i.iter().enumerate() {
             match j {
                 Some(p) => {match p.unwrap() != _colour {
                         true => {
-                            let file = _game.board.iter().position(|x| x == i).unwrap();
-                            let rank = _game.board[file].iter().position(|x| x == j).unwrap();
                             let pos = vec![file,rank]; 
                             let takes = get_takes(p, &pos, _colour, _game);
-                            in_check = takes.iter().any(|x| x == &vec![_postion[0] as usize, _postion[1] as usize]);
+                            in_check = takes.iter().any(|x| x == &vec![_postion[0], _postion[1]]);
                             if in_check { break }
                         },
                         _ => continue
@@ -558,50 +807,36 @@ fn check_pinned(_position: &Vec<usize>, _game: &Game, _moves: &Vec<Vec<usize>>,
     // worth noting is that the only pieces that can pin is the Queen, Bishops and Rooks
     let mut pin_line: Vec<Vec<usize>> = Vec::default();
     let mut pinned: bool = false;
-    for i in _game.board.iter() {
-        for j in i {
+    for (file, i) in _game.board.iter().enumerate() {
+        for (rank, j) in i.iter().enumerate() {
             match j {
                 Some(p) => {
-                    let file = _game.board.iter().position(|x| x == i).unwrap();
-                    let rank = i.iter().position(|x| x == j).unwrap();
                     let positon = vec![file,rank];
                     match p {
                         Piece::Queen(c) => {if *c != _colour {
-                            match get_diagonal_pin(&positon,_position,&king_position,_game,0) {
-                                Some(x) => {
-                                    pin_line = x;
-                                    pinned = true;
-                                    break
-                                },
-                                None => ()
+                            if let Some(x) = get_diagonal_pin(&positon,_position,&king_position,_game,0) {
+                                pin_line = x;
+                                pinned = true;
+                                break
                             }
-                            match get_straight_pin(&positon,_position,&king_position,_game,0) {
-                                Some(x) => {
-                                    pin_line = x;
-                                    pinned = true;
-                                    break
-                                },
-                                None => ()
+                            if let Some(x) = get_straight_pin(&positon,_position,&king_position,_game,0) {
+                                pin_line = x;
+                                pinned = true;
+                                break
                             }
                         }},
                         Piece::Bishop(c) => {if *c != _colour {
-                            match get_diagonal_pin(&positon,_position,&king_position,_game,0) {
-                                Some(x) => {
-                                    pin_line = x;
-                                    pinned = true;
-                                    break
-                                },
-                                None => ()
+                            if let Some(x) = get_diagonal_pin(&positon,_position,&king_position,_game,0) {
+                                pin_line = x;
+                                pinned = true;
+                                break
                             }
                         }},
                         Piece::Rook(c) => {if *c != _colour {
-                            match get_straight_pin(&positon,_position,&king_position,_game,0) {
-                                Some(x) => {
-                                    pin_line = x;
-                                    pinned = true;
-                                    break
-                                },
-                                None => ()
+                            if let Some(x) = get_straight_pin(&positon,_position,&king_position,_game,0) {
+                                pin_line = x;
+                                pinned = true;
+                                break
                             }
                         }},
                         _ => continue
@@ -641,7 +876,7 @@ fn get_diagonal_pin(_position: &Vec<usize>, _pinned: &Vec<usize>, _king: &Vec<us
                     line.remove(x);
                     if line.len() == 1 {
                         Some(line)
-                    } else if line.len() != 1 && line[1..].iter().any(|p| _game.board[p[0]][p[1]] != None) {
+                    } else if line.len() != 1 && line[1..].iter().any(|p| _game.board[p[0]][p[1]].is_some()) {
                         None
                     } else {
                         Some(line)
@@ -662,7 +897,7 @@ fn get_diagonal_pin(_position: &Vec<usize>, _pinned: &Vec<usize>, _king: &Vec<us
 fn get_line(_position: &Vec<usize>, _dirx: isize, _diry: isize) -> Vec<Vec<usize>> {
     let mut line: Vec<Vec<usize>> = Vec::default();
 
-    let mut pos = vec![_position[0] as isize,_position[1] as isize];
+    let mut pos = [_position[0] as isize,_position[1] as isize];
     
     while pos[0] >= 0 && pos[0] <= 7 && pos[1] >= 0 && pos[1] <= 7 {
         let upos = vec![pos[0] as usize, pos[1] as usize];
@@ -688,7 +923,7 @@ fn get_straight_pin(_position: &Vec<usize>, _pinned: &Vec<usize>, _king: &Vec<us
                     line.remove(x);
                     if line.len() == 1 {
                         Some(line)
-                    } else if line[1..].iter().any(|p| _game.board[p[0]][p[1]] != None) {
+                    } else if line[1..].iter().any(|p| _game.board[p[0]][p[1]].is_some()) {
                         None
                     } else {
                         Some(line)
@@ -711,16 +946,14 @@ fn resolve_check(_position: &Vec<usize>, _game: &Game, _moves: &Vec<Vec<usize>>,
     let king_position: Vec<usize> = get_king_pos(_game, _colour);
 
     let mut checking: Vec<Vec<usize>> = Vec::default();
-    for i in _game.board.iter() {
-        for j in i {
+    for (file, i) in _game.board.iter().enumerate() {
+        for (rank, j) in i.iter().enumerate() {
             match j {
                 Some(p) => {
                     if p.unwrap() != _colour {
-                        let file = _game.board.iter().position(|x| x == i).unwrap();
-                        let rank = i.iter().position(|x| x == j).unwrap();
                         let positon = vec![file,rank];
 
-                        if get_availble_moves(*p, &positon, _game, false).iter().any(|x| *x == king_position) {
+                        if get_availble_moves(*p, &positon, _game, false).contains(&king_position) {
                             checking.push(positon);
                         }
                     }
@@ -732,7 +965,7 @@ fn resolve_check(_position: &Vec<usize>, _game: &Game, _moves: &Vec<Vec<usize>>,
 
     // when there are multiple pices checking there is no way to resolve all checks in a single move exept moving the king
     if checking.len() > 1 {
-        vec![vec![]]
+        Vec::default()
     } else {
 
         let check_resolve = match _game.board[checking[0][0]][checking[0][1]].unwrap() {
@@ -768,7 +1001,7 @@ fn get_diagonal_check(_position: &Vec<usize>, _king: &Vec<usize>, _game: &Game,
             while line.len() > x {
                 line.remove(x);
             }
-            if line[1..].iter().any(|p| _game.board[p[0]][p[1]] != None) {
+            if line[1..].iter().any(|p| _game.board[p[0]][p[1]].is_some()) {
                 None
             } else {
                 Some(line)
@@ -792,7 +1025,7 @@ fn get_straight_check(_position: &Vec<usize>, _king: &Vec<usize>, _game: &Game,
             while line.len() > x {
                 line.remove(x);
             }
-            if line[1..].iter().any(|p| _game.board[p[0]][p[1]] != None) {
+            if line[1..].iter().any(|p| _game.board[p[0]][p[1]].is_some()) {
                 None
             } else {
                 Some(line)
@@ -807,6 +1040,114 @@ fn get_straight_check(_position: &Vec<usize>, _king: &Vec<usize>, _game: &Game,
     }
 }
 
+// check whether a side has the material to mate by any series of legal moves
+fn can_mate(_game: &Game, _colour: Colour) -> bool {
+    let mut own: Vec<(Piece, usize)> = Vec::default();
+    let mut other: Vec<(Piece, usize)> = Vec::default();
+    for (rank, row) in _game.board.iter().enumerate() {
+        for (file, square) in row.iter().enumerate() {
+            match square {
+                Some(Piece::King(_)) | None => (),
+                Some(p) if p.unwrap() == _colour => own.push((*p, (rank + file) % 2)),
+                Some(p) => other.push((*p, (rank + file) % 2))
+            }
+        }
+    }
+    match own.as_slice() {
+        [] => false,
+        // a lone knight needs something of the opponent's to block the king in
+        [(Piece::Knight(_), _)] => !other.is_empty(),
+        // bishops on squares of one colour cannot mate unless the opponent has a piece to block with
+        // that is not a bishop on the same colour
+        _ => {
+            let colour = own[0].1;
+            let bishops = |pieces: &[(Piece, usize)]| pieces.iter().all(|(p, c)| matches!(p, Piece::Bishop(_)) && *c == colour);
+            !(bishops(&own) && bishops(&other))
+        }
+    }
+}
+
+// get all legal moves of the side to move as the squares moved from and to, and the piece
+// that ends up on the target square, which differs from the moving piece for promotions
+fn legal_moves(_game: &Game) -> Vec<(Vec<usize>, Vec<usize>, Piece)> {
+    let mut moves: Vec<(Vec<usize>, Vec<usize>, Piece)> = Vec::default();
+    if _game.state == GameState::GameOver { return moves }
+    for (rank, row) in _game.board.iter().enumerate() {
+        for (file, square) in row.iter().enumerate() {
+            let piece = match square {
+                Some(p) if p.unwrap() == _game.active => *p,
+                _ => continue
+            };
+            let position = vec![rank, file];
+            for mv in get_availble_moves(piece, &position, _game, true) {
+                match piece {
+                    Piece::Pawn(c) if mv[0] == 0 || mv[0] == 7 => {
+                        for promotion in [Piece::Queen(c), Piece::Rook(c), Piece::Bishop(c), Piece::Knight(c)] {
+                            moves.push((position.clone(), mv.clone(), promotion));
+                        }
+                    },
+                    _ => moves.push((position.clone(), mv, piece))
+                }
+            }
+        }
+    }
+    moves
+}
+
+// play a move with the piece a pawn is promoted to, if any, and return the resulting state of the game,
+// or None if the move is not legal or the piece is not one the pawn can become,
+// in which case the promotion setting is left as it was
+fn play_promotion(_game: &mut Game, _from: String, _to: String, _promotion: Option<Piece>) -> Option<GameState> {
+    let promotion = match _promotion {
+        Some(p) => p,
+        None => return _game.make_move(_from, _to)
+    };
+    if !check_position(&_from) || !check_position(&_to) { return None }
+    let (from, to) = (decode_position(&_from), decode_position(&_to));
+    if !matches!(_game.board[from[0]][from[1]], Some(Piece::Pawn(_))) { return None }
+    if !legal_moves(_game).iter().any(|(f, t, p)| f == &from && t == &to && *p == promotion) { return None }
+
+    let before = match _game.active {
+        Colour::White => std::mem::replace(&mut _game.white_promotion, promotion),
+        Colour::Black => std::mem::replace(&mut _game.black_promotion, promotion)
+    };
+    let state = _game.make_move(_from, _to);
+    if state.is_none() {
+        match _game.active {
+            Colour::White => _game.white_promotion = before,
+            Colour::Black => _game.black_promotion = before
+        }
+    }
+    state
+}
+
+// get the game after a legal move, without keeping history
+fn after_move(_game: &Game, _from: &[usize], _to: &[usize], _promotion: Piece) -> Game {
+    let mut game = _game.scratch();
+    if let Piece::Pawn(_) = _game.board[_from[0]][_from[1]].unwrap() {
+        match _game.active {
+            Colour::White => game.white_promotion = _promotion,
+            Colour::Black => game.black_promotion = _promotion
+        }
+    }
+    game.play(_from, _to);
+    game
+}
+
+fn perft(_game: &Game, _depth: u32) -> u64 {
+    if _depth == 0 { return 1 }
+    let moves = legal_moves(_game);
+    if _depth == 1 { return moves.len() as u64 }
+    moves.iter().map(|(from, to, promotion)| perft(&after_move(_game, from, to, *promotion), _depth - 1)).sum()
+}
+
+fn opponent(_colour: Colour) -> Colour {
+    match _colour {
+        Colour::Black => Colour::White,
+        Colour::White => Colour::Black
+    }
+}
+
 fn game_state_change(_game: &mut Game) {
     _game.state = GameState::InProgress;
     let colour = match _game.active {
@@ -829,32 +1170,27 @@ fn get_king_pos(_game: &Game, _colour: Colour) -> Vec<usize> {
     for i in _game.board.iter() {
         for j in i {
             match j {
-                Some(p) => {
-                    match p {
-                        Piece::King(c) => if *c == _colour { 
-                            king_position = vec![_game.board.iter().position(|x| x == i).unwrap(), i.iter().position(|x| x == j).unwrap()];
-                            break 
-                        },
-                        _ => continue
-                    }
+                Some(Piece::King(c)) => if *c == _colour { 
+                    king_position = vec![_game.board.iter().position(|x| x == i).unwrap(), i.iter().position(|x| x == j).unwrap()];
+                    break 
                 },
-                None => continue
+                _ => continue
             }
         }
-        if king_position != Vec::default() { break }
+        if !king_position.is_empty() { break }
     }
     king_position
 }
 
 fn check_mate(_game: &Game, _colour: Colour) -> bool {
     let mut mate = true;
-    for i in _game.board.iter() {
-        for j in i {
+    for (file, i) in _game.board.iter().enumerate() {
+        for (rank, j) in i.iter().enumerate() {
             match j {
                 Some(p) => {
                     match p.unwrap() == _colour {
                         true => {
-                            let pos = code_moves(&vec![vec![_game.board.iter().position(|f| f == i).unwrap(), i.iter().position(|r| r == j).unwrap()]])[0].clone();
+                            let pos = code_moves(&vec![vec![file, rank]])[0].clone();
                             let moves = _game.get_possible_moves(pos.clone());
                             match moves {
                                 Some(x) => {
@@ -897,13 +1233,121 @@ impl Game {
                     [Some(Piece::Pawn(Colour::Black)),Some(Piece::Pawn(Colour::Black)),Some(Piece::Pawn(Colour::Black)),Some(Piece::Pawn(Colour::Black)),Some(Piece::Pawn(Colour::Black)),Some(Piece::Pawn(Colour::Black)),Some(Piece::Pawn(Colour::Black)),Some(Piece::Pawn(Colour::Black))],
                     [Some(Piece::Rook(Colour::Black)),Some(Piece::Knight(Colour::Black)),Some(Piece::Bishop(Colour::Black)),Some(Piece::Queen(Colour::Black)),Some(Piece::King(Colour::Black)),Some(Piece::Bishop(Colour::Black)),Some(Piece::Knight(Colour::Black)),Some(Piece::Rook(Colour::Black))]],
             white_promotion: Piece::Queen(Colour::White),
-            black_promotion: Piece::Queen(Colour::Black)
+            black_promotion: Piece::Queen(Colour::Black),
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            castling: [[Some(7), Some(0)], [Some(7), Some(0)]],
+            en_passant: None,
+            chess960: false,
+            clock: None,
+            outcome: None,
+            draw_offer: None,
+            history: Vec::default()
+        }
+    }
+
+    // copy the game without its history, for trying out moves
+    fn scratch(&self) -> Game {
+        Game {
+            state: self.state,
+            active: self.active,
+            board: self.board,
+            white_promotion: self.white_promotion,
+            black_promotion: self.black_promotion,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            castling: self.castling,
+            en_passant: self.en_passant.clone(),
+            chess960: self.chess960,
+            clock: None,
+            outcome: self.outcome,
+            draw_offer: self.draw_offer,
+            history: Vec::default()
+        }
+    }
+
+    // move a piece without checking that the move is legal, and update the rest of the game
+    fn play(&mut self, _from: &[usize], _to: &[usize]) {
+        let piece = self.board[_from[0]][_from[1]].unwrap();
+        let colour = piece.unwrap();
+        let mut captured = self.board[_to[0]][_to[1]];
+
+        match castling_side(self, _from, _to) {
+            Some(side) => {
+                // the rook may stand where the king ends up and the other way around, so lift both first
+                let rook = self.castling[colour_index(colour)][side].unwrap();
+                let (king_to, rook_to) = castling_squares(side);
+                self.board[_from[0]][_from[1]] = None;
+                self.board[_from[0]][rook] = None;
+                self.board[_from[0]][king_to] = Some(piece);
+                self.board[_from[0]][rook_to] = Some(Piece::Rook(colour));
+                captured = None;
+            },
+            None => {
+                // a pawn moving diagonally onto an empty square takes en passant
+                if let (Piece::Pawn(_), None) = (piece, captured) {
+                    if _from[1] != _to[1] {
+                        captured = self.board[_from[0]][_to[1]];
+                        self.board[_from[0]][_to[1]] = None;
+                    }
+                }
+                self.board[_to[0]][_to[1]] = Some(piece);
+                self.board[_from[0]][_from[1]] = None;
+                if let Piece::Pawn(c) = piece {
+                    match c {
+                        Colour::Black => {
+                            if _to[0] == 0 {
+                                self.board[_to[0]][_to[1]] = Some(self.black_promotion);
+                            }
+                        },
+                        Colour::White => {
+                            if _to[0] == 7 {
+                                self.board[_to[0]][_to[1]] = Some(self.white_promotion);
+                            }
+                        }
+                    }
+                };
+            }
+        }
+
+        // castling rights are lost when the king moves, and when a rook leaves or is taken on its square
+        if let Piece::King(_) = piece {
+            self.castling[colour_index(colour)] = [None, None];
+        }
+        for (index, rank) in [(0, 0), (1, 7)] {
+            for side in 0..2 {
+                if let Some(file) = self.castling[index][side] {
+                    if (_from[0] == rank && _from[1] == file) || (_to[0] == rank && _to[1] == file) {
+                        self.castling[index][side] = None;
+                    }
+                }
+            }
         }
+
+        self.en_passant = match piece {
+            Piece::Pawn(_) if _from[0].abs_diff(_to[0]) == 2 => Some(vec![(_from[0] + _to[0]) / 2, _from[1]]),
+            _ => None
+        };
+
+        game_state_change(self);
+
+        // the halfmove clock is reset by pawn moves and captures
+        match (piece, captured) {
+            (Piece::Pawn(_), _) | (_, Some(_)) => self.halfmove_clock = 0,
+            _ => self.halfmove_clock += 1
+        }
+        if self.active == Colour::Black {
+            self.fullmove_number += 1;
+        }
+        self.active = opponent(self.active);
     }
 
     /// If the current game state is InProgress and the move is legal, 
     /// move a piece and return the resulting state of the game.
     pub fn make_move(&mut self, _from: String, _to: String) -> Option<GameState> {
+        if !check_position(&_from) || !check_position(&_to) { return None }
+        let now = Instant::now();
+        if self.outcome.is_some() || self.flag_at(now) { return None }
         match self.state {
             GameState::GameOver => None,
             _ => {
@@ -914,32 +1358,41 @@ impl Game {
                     Some(p) => {
                         match p.unwrap() == self.active {
                             true => {
-                                match self.get_possible_moves(_from).unwrap().iter().any(|m| m == &_to) {
+                                match self.get_possible_moves(_from.clone()).unwrap().iter().any(|m| m == &_to) {
                                     true => {
-                                        self.board[to_position[0]][to_position[1]] = self.board[from_position[0]][from_position[1]];
-                                        self.board[from_position[0]][from_position[1]] = None;
-                                        match p {
-                                            Piece::Pawn(c) => {
-                                                match c {
-                                                    Colour::Black => {
-                                                        if to_position[0] == 0 {
-                                                            self.board[to_position[0]][to_position[1]] = Some(self.black_promotion);
-                                                        }
-                                                    },
-                                                    Colour::White => {
-                                                        if to_position[0] == 7 {
-                                                            self.board[to_position[0]][to_position[1]] = Some(self.white_promotion);
-                                                        }
-                                                    }
-                                                }
-                                            },
-                                            _ => ()
-                                        };
-                                        game_state_change(self);
-                                        self.active = match self.active {
-                                            Colour::Black => Colour::White,
-                                            Colour::White => Colour::Black
+                                        let mut san = san::move_body(self, &from_position, &to_position);
+
+                                        // keep the game as it was before the move so that it can be undone
+                                        let before = self.scratch();
+
+                                        self.play(&from_position, &to_position);
+                                        // moving instead of answering a draw offer declines it
+                                        if self.draw_offer == Some(self.active) {
+                                            self.draw_offer = None;
+                                        }
+                                        match self.state {
+                                            GameState::Check => san.push('+'),
+                                            GameState::GameOver => san.push('#'),
+                                            GameState::InProgress => ()
+                                        }
+
+                                        let promotion = match (p, self.board[to_position[0]][to_position[1]]) {
+                                            (Piece::Pawn(_), Some(x)) if x != p => Some(x),
+                                            _ => None
                                         };
+                                        self.history.push(Record {
+                                            played: Move { from: _from, to: _to, promotion, san },
+                                            before
+                                        });
+
+                                        self.update_outcome();
+                                        let ended = self.get_outcome().is_some();
+                                        if let Some(clock) = &mut self.clock {
+                                            match ended {
+                                                true => clock.stop_at(now),
+                                                false => { clock.press_at(now); }
+                                            }
+                                        }
                                         Some(self.state)
                                     },
                                     false => None
@@ -955,8 +1408,35 @@ impl Game {
         }
     }
 
+    /// Like `make_move`, but a pawn reaching the last rank is promoted to the given piece
+    /// rather than to the promotion setting. A piece given for a move that is not a
+    /// promotion makes the move illegal, and an illegal move leaves the setting as it was.
+    pub fn make_move_promoting(&mut self, _from: String, _to: String, _promotion: Option<Piece>) -> Option<GameState> {
+        play_promotion(self, _from, _to, _promotion)
+    }
+
+    /// Take back the last move played and return it, or None if no move has been played.
+    pub fn undo_move(&mut self) -> Option<Move> {
+        let record = self.history.pop()?;
+        let history = std::mem::take(&mut self.history);
+        // time spent is not given back, the clock only switches to the side to move again
+        let mut clock = self.clock.take();
+        *self = record.before;
+        self.history = history;
+        if let Some(c) = &mut clock {
+            c.start(self.active);
+        }
+        self.clock = clock;
+        Some(record.played)
+    }
+
+    /// Get all moves played so far, in order.
+    pub fn get_history(&self) -> Vec<Move> {
+        self.history.iter().map(|r| r.played.clone()).collect()
+    }
+
     /// Set the piece type that a peasant becames following a promotion.
-    pub fn set_promotion(&mut self, _piece: String) -> () {
+    pub fn set_promotion(&mut self, _piece: String) {
         match self.active {
             Colour::Black => {
                 self.black_promotion = match &_piece[..] {
@@ -983,12 +1463,187 @@ impl Game {
     pub fn get_game_state(&self) -> GameState {
         self.state
     }
+
+    /// Get the outcome of the game, if it has ended.
+    pub fn get_outcome(&self) -> Option<Outcome> {
+        match self.state {
+            // the side to move has been mated
+            GameState::GameOver => Some(Outcome { winner: Some(opponent(self.active)), termination: Termination::Checkmate }),
+            _ => self.outcome
+        }
+    }
+
+    /// Play the game with a clock for the given time control. The clock of the
+    /// side to move starts running, and is switched by every move.
+    pub fn set_clock(&mut self, _control: clock::TimeControl) {
+        let mut clock = clock::Clock::new(_control);
+        clock.start(self.active);
+        self.clock = Some(clock);
+    }
+
+    /// Get the clock of the game, if it is timed.
+    pub fn get_clock(&self) -> Option<&clock::Clock> {
+        self.clock.as_ref()
+    }
+
+    /// Check whether the side to move has run out of time, which ends the game.
+    /// Returns the outcome of the game if it has ended.
+    pub fn check_time(&mut self) -> Option<Outcome> {
+        self.flag_at(Instant::now());
+        self.get_outcome()
+    }
+
+    /// Let a player leave the game, which their opponent wins. Returns None if the game has already ended.
+    pub fn abandon(&mut self, _colour: Colour) -> Option<Outcome> {
+        if self.get_outcome().is_some() { return None }
+        self.end(Some(opponent(_colour)), Termination::Abandonment)
+    }
+
+    /// Resign the game for a player. Returns None if the game has already ended.
+    pub fn resign(&mut self, _colour: Colour) -> Option<Outcome> {
+        if self.get_outcome().is_some() { return None }
+        self.end(Some(opponent(_colour)), Termination::Resignation)
+    }
+
+    /// Offer a draw for a player. The offer stands until the opponent accepts or
+    /// declines it, or moves instead. Returns false if the game has ended or a draw
+    /// has already been offered.
+    pub fn offer_draw(&mut self, _colour: Colour) -> bool {
+        if self.get_outcome().is_some() || self.draw_offer.is_some() { return false }
+        self.draw_offer = Some(_colour);
+        true
+    }
+
+    /// Get the player whose draw offer is waiting for an answer, if any.
+    pub fn get_draw_offer(&self) -> Option<Colour> {
+        self.draw_offer
+    }
+
+    /// Accept the draw the opponent of a player has offered. Returns None if
+    /// the game has ended or the opponent has not offered a draw.
+    pub fn accept_draw(&mut self, _colour: Colour) -> Option<Outcome> {
+        if self.get_outcome().is_some() || self.draw_offer != Some(opponent(_colour)) { return None }
+        self.draw_offer = None;
+        self.end(None, Termination::Agreement)
+    }
+
+    /// Decline the draw the opponent of a player has offered. Returns false if
+    /// the game has ended or the opponent has not offered a draw.
+    pub fn decline_draw(&mut self, _colour: Colour) -> bool {
+        if self.get_outcome().is_some() || self.draw_offer != Some(opponent(_colour)) { return false }
+        self.draw_offer = None;
+        true
+    }
+
+    /// Get the reason a draw can be claimed in the current position, if any:
+    /// the position has occurred three times, or fifty moves have been played
+    /// without a capture or a pawn move.
+    pub fn get_draw_claim(&self) -> Option<Termination> {
+        if self.get_outcome().is_some() {
+            None
+        } else if self.repetitions() >= 3 {
+            Some(Termination::Repetition)
+        } else if self.halfmove_clock >= 100 {
+            Some(Termination::FiftyMoves)
+        } else {
+            None
+        }
+    }
+
+    /// Claim a draw by threefold repetition or the fifty-move rule. Returns None
+    /// if the game has ended or there is no draw to claim.
+    pub fn claim_draw(&mut self) -> Option<Outcome> {
+        let termination = self.get_draw_claim()?;
+        self.end(None, termination)
+    }
+
+    // end the game, stopping the clock
+    fn end(&mut self, _winner: Option<Colour>, _termination: Termination) -> Option<Outcome> {
+        if let Some(clock) = &mut self.clock {
+            clock.stop();
+        }
+        self.outcome = Some(Outcome { winner: _winner, termination: _termination });
+        self.outcome
+    }
+
+    // end the game if the position is drawn by the rules without a claim
+    fn update_outcome(&mut self) {
+        if self.get_outcome().is_some() { return }
+        if self.state == GameState::InProgress && check_mate(self, self.active) {
+            self.end(None, Termination::Stalemate);
+        } else if !can_mate(self, Colour::White) && !can_mate(self, Colour::Black) {
+            self.end(None, Termination::InsufficientMaterial);
+        } else if self.repetitions() >= 5 {
+            self.end(None, Termination::Repetition);
+        } else if self.halfmove_clock >= 150 {
+            self.end(None, Termination::FiftyMoves);
+        }
+    }
+
+    // check that two games are in the same position, with the same moves available
+    fn same_position(&self, _other: &Game) -> bool {
+        // an en passant square only matters when the pawn can be taken
+        let en_passant = |game: &Game| -> Vec<Vec<usize>> {
+            let mut moves: Vec<Vec<usize>> = Vec::default();
+            if let Some(square) = &game.en_passant {
+                let rank = match game.active {
+                    Colour::White => 4,
+                    Colour::Black => 3
+                };
+                for file in [square[1].wrapping_sub(1), square[1] + 1] {
+                    if file < 8 && game.board[rank][file] == Some(Piece::Pawn(game.active)) {
+                        moves.append(&mut get_en_passant(&vec![rank, file], game));
+                    }
+                }
+            }
+            moves
+        };
+        self.board == _other.board && self.active == _other.active && self.castling == _other.castling
+            && en_passant(self) == en_passant(_other)
+    }
+
+    // get the number of times the current position has occurred in the game
+    fn repetitions(&self) -> usize {
+        // positions cannot repeat across pawn moves and captures
+        let moves = (self.halfmove_clock as usize).min(self.history.len());
+        1 + self.history[self.history.len() - moves..].iter().filter(|r| self.same_position(&r.before)).count()
+    }
+
+    // end the game if a flag has fallen at the given time
+    fn flag_at(&mut self, _now: Instant) -> bool {
+        if self.outcome.is_some() || self.state == GameState::GameOver { return false }
+        let flagged = match &mut self.clock {
+            Some(clock) => match clock.flagged_at(_now) {
+                Some(colour) => {
+                    clock.stop_at(_now);
+                    colour
+                },
+                None => return false
+            },
+            None => return false
+        };
+        // running out of time only loses if the opponent could still mate
+        let winner = opponent(flagged);
+        self.outcome = Some(Outcome {
+            winner: if can_mate(self, winner) { Some(winner) } else { None },
+            termination: Termination::Timeout
+        });
+        true
+    }
+
+    /// Count the positions reached by all sequences of legal moves of the given length,
+    /// counting each piece a pawn can be promoted to separately. The counts can be
+    /// compared with published results to check the move generation.
+    pub fn perft(&self, _depth: u32) -> u64 {
+        perft(&self.scratch(), _depth)
+    }
     
     /// If a piece is standing on the given tile, return all possible 
     /// new positions of that piece. Don't forget to the rules for check. 
     /// 
     /// (optional) Don't forget to include en passent and castling.
     pub fn get_possible_moves(&self, _position: String) -> Option<Vec<String>> {
+        if !check_position(&_position) { return None }
         let position = decode_position(&_position);
         if self.state == GameState::GameOver { return None }
         match self.board[position[0]][position[1]] {
@@ -996,7 +1651,7 @@ impl Game {
                 if piece.unwrap() != self.active {
                     return None
                 }
-                let moves = get_availble_moves(piece, &position, &self, true);
+                let moves = get_availble_moves(piece, &position, self, true);
         
                 let c_moves = code_moves(&moves);
                 Some(c_moves)
@@ -1006,6 +1661,12 @@ impl Game {
     }
 }
 
+impl Default for Game {
+    fn default() -> Game {
+        Game::new()
+    }
+}
+
 /// Implement print routine for Game.
 /// 
 /// Output example:
@@ -1050,6 +1711,8 @@ mod tests {
     use super::GameState;
     use super::Piece;
     use super::Colour;
+    use super::Outcome;
+    use super::Termination;
 
     // check test framework
     #[test]
@@ -1243,4 +1906,95 @@ mod tests {
 
         println!("{:?}", game);
     }
+
+    #[test]
+    fn undo() {
+        let mut game = Game::new();
+        assert_eq!(game.undo_move(), None);
+
+        game.make_move("e2".to_string(), "e4".to_string());
+        game.make_move("f7".to_string(), "f6".to_string());
+        game.make_move("d2".to_string(), "d4".to_string());
+        game.make_move("g7".to_string(), "g5".to_string());
+        game.make_move("d1".to_string(), "h5".to_string());
+        assert_eq!(game.get_game_state(), GameState::GameOver);
+        assert_eq!(game.get_history().len(), 5);
+
+        let undone = game.undo_move().unwrap();
+        assert_eq!((undone.from.as_str(), undone.to.as_str(), undone.san.as_str()), ("d1", "h5", "Qh5#"));
+        assert_eq!(game.get_game_state(), GameState::InProgress);
+        assert_eq!(game.active, Colour::White);
+        assert_eq!(game.board[0][3], Some(Piece::Queen(Colour::White)));
+        assert_eq!(game.get_history().len(), 4);
+    }
+
+    #[test]
+    fn outcome() {
+        let mut game = Game::new();
+        for (from, to) in [("f2", "f3"), ("e7", "e5"), ("g2", "g4"), ("d8", "h4")] {
+            game.make_move(from.to_string(), to.to_string());
+        }
+        assert_eq!(game.get_outcome(), Some(Outcome { winner: Some(Colour::Black), termination: Termination::Checkmate }));
+
+        // draws without a claim
+        let mut game = Game::from_fen("7k/8/6Q1/8/8/8/8/K7 w - - 0 1").unwrap();
+        game.make_move("g6".to_string(), "f7".to_string());
+        assert_eq!(game.get_outcome(), Some(Outcome { winner: None, termination: Termination::Stalemate }));
+        assert_eq!(game.make_move("h8".to_string(), "g8".to_string()), None);
+        let mut game = Game::from_fen("7k/8/8/8/8/8/6q1/K3N3 w - - 0 1").unwrap();
+        game.make_move("e1".to_string(), "g2".to_string());
+        assert_eq!(game.get_outcome().unwrap().termination, Termination::InsufficientMaterial);
+        let mut game = Game::new();
+        for _ in 0..4 {
+            assert_eq!(game.get_outcome(), None);
+            for (from, to) in [("g1", "f3"), ("g8", "f6"), ("f3", "g1"), ("f6", "g8")] {
+                game.make_move(from.to_string(), to.to_string());
+            }
+        }
+        assert_eq!(game.get_outcome().unwrap().termination, Termination::Repetition);
+
+        // actions of the players
+        let mut game = Game::new();
+        assert!(game.offer_draw(Colour::White));
+        assert!(!game.offer_draw(Colour::Black));
+        game.make_move("e2".to_string(), "e4".to_string());
+        assert_eq!(game.get_draw_offer(), Some(Colour::White));
+        game.make_move("e7".to_string(), "e5".to_string());
+        assert_eq!(game.get_draw_offer(), None);
+        assert_eq!(game.accept_draw(Colour::Black), None);
+        assert!(game.offer_draw(Colour::White));
+        assert!(game.decline_draw(Colour::Black));
+        assert!(game.offer_draw(Colour::White));
+        assert_eq!(game.accept_draw(Colour::Black), Some(Outcome { winner: None, termination: Termination::Agreement }));
+        assert_eq!(game.resign(Colour::White), None);
+
+        let mut game = Game::new();
+        assert_eq!(game.claim_draw(), None);
+        for (from, to) in [("g1", "f3"), ("g8", "f6"), ("f3", "g1"), ("f6", "g8")].repeat(2) {
+            game.make_move(from.to_string(), to.to_string());
+        }
+        assert_eq!(game.claim_draw(), Some(Outcome { winner: None, termination: Termination::Repetition }));
+        assert_eq!(Game::new().resign(Colour::Black), Some(Outcome { winner: Some(Colour::White), termination: Termination::Resignation }));
+
+        let mut game = Game::new();
+        assert_eq!(game.abandon(Colour::White), Some(Outcome { winner: Some(Colour::Black), termination: Termination::Abandonment }));
+        assert_eq!(game.abandon(Colour::Black), None);
+    }
+
+    #[test]
+    fn perft() {
+        let game = Game::new();
+        assert_eq!(game.perft(1), 20);
+        assert_eq!(game.perft(2), 400);
+        assert_eq!(game.perft(3), 8902);
+
+        // castling, en passant and promotions
+        let game = Game::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(game.perft(1), 48);
+        assert_eq!(game.perft(2), 2039);
+        let game = Game::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
+        assert_eq!(game.perft(3), 2812);
+        let game = Game::from_fen("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8").unwrap();
+        assert_eq!(game.perft(2), 1486);
+    }
 }
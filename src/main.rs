@@ -0,0 +1,304 @@
+//! Interactive terminal play.
+//!
+//! Reads commands from standard input, one per line. Moves can be entered in
+//! coordinate notation ("e2e4", "e7e8q") or in standard algebraic notation ("Nf3").
+//! The built-in engine can be asked for a move, or left to play one side.
+
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use osveijer_chess::cecp::Cecp;
+use osveijer_chess::engine::Engine;
+use osveijer_chess::render::Renderer;
+use osveijer_chess::uci::Reply;
+use osveijer_chess::{Colour, Game, GameState, Piece};
+
+static HELP: &str = "Commands:
+  <move>          play a move, e.g. e2e4, e7e8q or Nf3
+  moves <square>  list the legal moves of the piece on a square
+  undo            take back the last move, and the engine's answer to it
+  go              let the engine play the side to move
+  engine <side>   let the engine answer as white or black, or turn it off
+  strength <n>    set how many moves ahead the engine looks, from 1 to 5
+  resign          resign for the side to move
+  draw            offer a draw, or accept the opponent's offer
+  decline         decline the opponent's draw offer
+  claim           claim a draw by repetition or the fifty-move rule
+  flip            turn the board around
+  fen [<fen>]     show the position in FEN, or set up a position
+  save <file>     save the game as PGN
+  load <file>     load a game from a PGN file
+  new [<number>]  start a new game, or Chess960 game by its position number
+  xboard          hand over to a chess interface speaking CECP
+  help            show this help
+  quit            leave";
+
+// print whose turn it is and whether the game has ended
+fn print_status(_game: &Game) {
+    let colour = match _game.active {
+        Colour::White => "White",
+        Colour::Black => "Black"
+    };
+    if let Some(outcome) = _game.get_outcome() {
+        match outcome.winner {
+            Some(Colour::White) => println!("White wins by {}", outcome.termination),
+            Some(Colour::Black) => println!("Black wins by {}", outcome.termination),
+            None => println!("Draw by {}", outcome.termination)
+        }
+        return
+    }
+    match _game.get_game_state() {
+        GameState::Check => println!("{} to move, in check", colour),
+        _ => println!("{} to move", colour)
+    }
+}
+
+// normalise a move in coordinate notation, e.g. "e7-e8=N" becomes "e7e8n"
+fn coordinate_input(_input: &str) -> Option<String> {
+    let input: String = _input.chars().filter(|c| !"- =".contains(*c)).collect::<String>().to_lowercase();
+    if !(input.len() == 4 || input.len() == 5) || !input.is_ascii() { return None }
+    let is_square = |s: &str| {
+        let b = s.as_bytes();
+        (b'a'..=b'h').contains(&b[0]) && (b'1'..=b'8').contains(&b[1])
+    };
+    if !is_square(&input[0..2]) || !is_square(&input[2..4]) { return None }
+    Some(input)
+}
+
+// play a move in coordinate notation, or None if the input is not in coordinate notation
+fn coordinate_move(_game: &mut Game, _input: &str) -> Option<Option<GameState>> {
+    let input = coordinate_input(_input)?;
+    let reply = Reply::from_uci(&input, _game.active)?;
+    // without a promotion piece, pawns are promoted to queens
+    let last_rank = match _game.active {
+        Colour::White => "8",
+        Colour::Black => "1"
+    };
+    let from = reply.from.as_bytes();
+    let pawn = matches!(_game.board[(from[1] - b'1') as usize][(from[0] - b'a') as usize], Some(Piece::Pawn(_)));
+    let promotion = match reply.promotion {
+        None if pawn && reply.to.ends_with(last_rank) => Some(Piece::Queen(_game.active)),
+        p => p
+    };
+    Some(_game.make_move_promoting(reply.from, reply.to, promotion))
+}
+
+// let the engine play a move for the side to move, and get it in SAN, or None if there is no move
+fn engine_move(_game: &mut Game, _engine: &Engine) -> Option<String> {
+    if _game.get_outcome().is_some() { return None }
+    let best = _engine.search(_game)?.line.remove(0);
+    _game.make_move_promoting(best.from, best.to, best.promotion)?;
+    Some(best.san)
+}
+
+// answer a chess interface speaking CECP on standard input and output, until it quits
+fn xboard() {
+    let mut cecp = Cecp::new();
+    for line in io::stdin().lock().lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(_) => break
+        };
+        match cecp.handle(&line) {
+            Some(answers) => answers.iter().for_each(|a| println!("{}", a)),
+            None => break
+        }
+        io::stdout().flush().unwrap();
+    }
+}
+
+fn main() {
+    let mut game = Game::new();
+    let mut engine = Engine::default();
+    // the side the engine plays, if any
+    let mut engine_side: Option<Colour> = None;
+    // colour the board only when it is shown in a terminal
+    let mut renderer = Renderer { colour: io::stdout().is_terminal(), ..Renderer::default() };
+
+    println!("osveijer-chess, type \"help\" for a list of commands");
+    println!("\n{}", renderer.render(&game));
+    print_status(&game);
+
+    let stdin = io::stdin();
+    loop {
+        print!("> ");
+        io::stdout().flush().unwrap();
+        let mut line = String::default();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => ()
+        }
+        let line = line.trim();
+        let (command, argument) = match line.split_once(' ') {
+            Some((c, a)) => (c, a.trim()),
+            None => (line, "")
+        };
+
+        match command {
+            "" => continue,
+            "quit" | "exit" => break,
+            "xboard" => {
+                xboard();
+                break
+            },
+            "help" => {
+                println!("{}", HELP);
+                continue
+            },
+            "moves" => {
+                match game.get_possible_moves(argument.to_owned()) {
+                    Some(mut moves) => {
+                        moves.sort();
+                        println!("{}", moves.join(" "));
+                    },
+                    None => println!("no piece of the side to move on {}", argument)
+                }
+                continue
+            },
+            "undo" => {
+                if let Some(m) = game.undo_move() {
+                    println!("took back {}", m.san);
+                } else {
+                    println!("no move to take back");
+                    continue
+                }
+                // the engine would only play its move again
+                if engine_side == Some(game.active) {
+                    if let Some(m) = game.undo_move() {
+                        println!("took back {}", m.san);
+                    }
+                }
+            },
+            "go" => {
+                match engine_move(&mut game, &engine) {
+                    Some(san) => println!("engine plays {}", san),
+                    None => {
+                        println!("no move to play");
+                        continue
+                    }
+                }
+            },
+            "engine" => {
+                engine_side = match argument {
+                    "white" => Some(Colour::White),
+                    "black" => Some(Colour::Black),
+                    "off" => None,
+                    _ => {
+                        println!("the engine plays white or black, or is off");
+                        continue
+                    }
+                };
+            },
+            "strength" => {
+                match argument.parse() {
+                    Ok(depth) if (1..=5).contains(&depth) => {
+                        engine.depth = depth;
+                        println!("the engine looks {} moves ahead", depth);
+                    },
+                    _ => println!("the strength goes from 1 to 5")
+                }
+                continue
+            },
+            "resign" => {
+                if game.resign(game.active).is_none() {
+                    println!("the game is over");
+                    continue
+                }
+            },
+            "draw" => {
+                let colour = game.active;
+                if game.accept_draw(colour).is_none() {
+                    match game.offer_draw(colour) {
+                        true => println!("draw offered"),
+                        false => println!("cannot offer a draw now")
+                    }
+                    continue
+                }
+            },
+            "decline" => {
+                match game.decline_draw(game.active) {
+                    true => println!("draw declined"),
+                    false => println!("no draw offer to decline")
+                }
+                continue
+            },
+            "claim" => {
+                if game.claim_draw().is_none() {
+                    println!("no draw to claim");
+                    continue
+                }
+            },
+            "flip" => {
+                renderer.orientation = match renderer.orientation {
+                    Colour::White => Colour::Black,
+                    Colour::Black => Colour::White
+                };
+            },
+            "fen" if argument.is_empty() => {
+                println!("{}", game.to_fen());
+                continue
+            },
+            "fen" => {
+                match Game::from_fen(argument) {
+                    Some(g) => game = g,
+                    None => {
+                        println!("invalid FEN");
+                        continue
+                    }
+                }
+            },
+            "save" => {
+                match fs::write(argument, game.to_pgn()) {
+                    Ok(_) => println!("saved to {}", argument),
+                    Err(e) => println!("could not save to {}: {}", argument, e)
+                }
+                continue
+            },
+            "load" => {
+                match fs::read_to_string(argument).ok().and_then(|pgn| Game::from_pgn(&pgn)) {
+                    Some(g) => game = g,
+                    None => {
+                        println!("could not load a game from {}", argument);
+                        continue
+                    }
+                }
+            },
+            "new" if argument.is_empty() => game = Game::new(),
+            "new" => {
+                match argument.parse().ok().and_then(Game::new_chess960) {
+                    Some(g) => game = g,
+                    None => {
+                        println!("Chess960 positions are numbered 0 to 959");
+                        continue
+                    }
+                }
+            },
+            _ => {
+                let result = match coordinate_move(&mut game, line) {
+                    Some(r) => r,
+                    None => game.make_san_move(line)
+                };
+                if result.is_none() {
+                    // coordinate moves can be explained, SAN moves do not say which piece was meant
+                    match coordinate_input(line).and_then(|m| game.explain_illegal(m[0..2].to_owned(), m[2..4].to_owned())) {
+                        Some(reason) => println!("illegal move: {} ({})", line, reason),
+                        None => println!("illegal move: {}", line)
+                    }
+                    continue
+                }
+            }
+        }
+
+        println!("\n{}", renderer.render(&game));
+        print_status(&game);
+
+        // the engine answers as soon as it is its turn
+        if engine_side == Some(game.active) {
+            if let Some(san) = engine_move(&mut game, &engine) {
+                println!("engine plays {}", san);
+                println!("\n{}", renderer.render(&game));
+                print_status(&game);
+            }
+        }
+    }
+}
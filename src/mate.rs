@@ -0,0 +1,196 @@
+//! Solving "mate in N" problems by searching every line of play.
+//!
+//! The side to move is the attacker. A key move solves the problem when every
+//! defence leaves the attacker a mate in the remaining moves. The search is
+//! exhaustive, so a problem without a key has no solution.
+
+use super::{after_move, code_moves, legal_moves, san, Colour, Game, GameState, Move, Piece};
+
+/// A move in a solution, with the moves that answer it.
+///
+/// The answers to an attacker's move are all the defences, and the answers to a
+/// defence are the attacker's moves that mate in the fewest moves left.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Variation {
+    pub played: Move,
+    pub continuations: Vec<Variation>
+}
+
+/// The solution of a "mate in N" problem.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MateSolution {
+    /// The number of moves the attacker was given to mate in.
+    pub moves: u32,
+    /// Every first move that forces mate in at most that many moves.
+    pub keys: Vec<Variation>
+}
+
+impl MateSolution {
+    /// Check whether the attacker can force mate.
+    pub fn is_solved(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// Check whether the problem has more than one key move.
+    pub fn is_cooked(&self) -> bool {
+        self.keys.len() > 1
+    }
+
+    /// Get the lines after which the attacker has more than one way to mate in the
+    /// fewest moves, as moves in SAN from the key to the defence.
+    pub fn duals(&self) -> Vec<Vec<String>> {
+        let mut duals: Vec<Vec<String>> = Vec::default();
+        for key in &self.keys {
+            find_duals(key, &mut Vec::default(), &mut duals);
+        }
+        duals
+    }
+}
+
+// collect the defences below an attacker's move that have more than one continuation
+fn find_duals(_attack: &Variation, _line: &mut Vec<String>, _duals: &mut Vec<Vec<String>>) {
+    _line.push(_attack.played.san.clone());
+    for defence in &_attack.continuations {
+        _line.push(defence.played.san.clone());
+        if defence.continuations.len() > 1 {
+            _duals.push(_line.clone());
+        }
+        for attack in &defence.continuations {
+            find_duals(attack, _line, _duals);
+        }
+        _line.pop();
+    }
+    _line.pop();
+}
+
+// play a move, returning it as a played move together with the game after it
+pub(crate) fn play(_game: &Game, _from: &[usize], _to: &[usize], _promotion: Piece) -> (Move, Game) {
+    let mut before = _game.scratch();
+    let piece = _game.board[_from[0]][_from[1]].unwrap();
+    let promoted = piece != _promotion;
+    if promoted {
+        match _game.active {
+            Colour::White => before.white_promotion = _promotion,
+            Colour::Black => before.black_promotion = _promotion
+        }
+    }
+    let mut san = san::move_body(&before, _from, _to);
+    let after = after_move(_game, _from, _to, _promotion);
+    match after.state {
+        GameState::Check => san.push('+'),
+        GameState::GameOver => san.push('#'),
+        GameState::InProgress => ()
+    }
+    let squares = code_moves(&vec![_from.to_vec(), _to.to_vec()]);
+    let played = Move {
+        from: squares[0].clone(),
+        to: squares[1].clone(),
+        promotion: if promoted { Some(_promotion) } else { None },
+        san
+    };
+    (played, after)
+}
+
+// check whether the side to move can force mate in at most the given number of moves
+fn forces_mate(_game: &Game, _moves: u32) -> bool {
+    let mut afters: Vec<Game> = legal_moves(_game).iter().map(|(from, to, promotion)| after_move(_game, from, to, *promotion)).collect();
+    // checks are tried first, as they leave the defender the fewest replies
+    afters.sort_by_key(|g| g.state == GameState::InProgress);
+    afters.iter().any(|g| mates_after(g, _moves))
+}
+
+// check that every defence to an attacker's move allows mate in the moves that are left
+fn mates_after(_after: &Game, _moves: u32) -> bool {
+    match _after.state {
+        GameState::GameOver => true,
+        // only a check can mate on the last move
+        _ if _moves <= 1 => false,
+        _ => {
+            let defences = legal_moves(_after);
+            // a defender without moves that is not mated has been stalemated
+            !defences.is_empty() && defences.iter().all(|(from, to, promotion)| forces_mate(&after_move(_after, from, to, *promotion), _moves - 1))
+        }
+    }
+}
+
+// get every attacker's move that forces mate in at most the given number of moves, with its defences
+fn keys(_game: &Game, _moves: u32) -> Vec<Variation> {
+    let mut keys: Vec<Variation> = Vec::default();
+    for (from, to, promotion) in legal_moves(_game) {
+        let (played, after) = play(_game, &from, &to, promotion);
+        if mates_after(&after, _moves) {
+            keys.push(Variation { played, continuations: defences(&after, _moves) });
+        }
+    }
+    keys
+}
+
+// get every defence to an attacker's move, each with the attacker's quickest mates
+fn defences(_after: &Game, _moves: u32) -> Vec<Variation> {
+    if _after.state == GameState::GameOver { return Vec::default() }
+    legal_moves(_after).iter().map(|(from, to, promotion)| {
+        let (played, game) = play(_after, from, to, *promotion);
+        let continuations = (1.._moves).map(|n| keys(&game, n)).find(|k| !k.is_empty()).unwrap_or_default();
+        Variation { played, continuations }
+    }).collect()
+}
+
+impl Game {
+    /// Find every way for the side to move to force mate in at most the given number of moves.
+    pub fn solve_mate(&self, _moves: u32) -> MateSolution {
+        let keys = match _moves {
+            0 => Vec::default(),
+            _ => match self.get_outcome() {
+                Some(_) => Vec::default(),
+                None => keys(self, _moves)
+            }
+        };
+        MateSolution { moves: _moves, keys }
+    }
+
+    /// Check whether the side to move can force mate in at most the given number of moves.
+    pub fn has_forced_mate(&self, _moves: u32) -> bool {
+        _moves > 0 && self.get_outcome().is_none() && forces_mate(self, _moves)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Game;
+
+    #[test]
+    fn mate_in_n() {
+        // Morphy's mate in two, where the rook is offered to free the pawn
+        let game = Game::from_fen("kbK5/pp6/1P6/8/8/8/8/R7 w - - 0 1").unwrap();
+        assert!(!game.has_forced_mate(1));
+        assert!(game.has_forced_mate(2));
+        let solution = game.solve_mate(2);
+        assert!(solution.is_solved() && !solution.is_cooked());
+        let key = &solution.keys[0];
+        assert_eq!(key.played.san, "Ra6");
+        let capture = key.continuations.iter().find(|d| d.played.san == "bxa6").unwrap();
+        assert_eq!(capture.continuations.len(), 1);
+        assert_eq!(capture.continuations[0].played.san, "b7#");
+
+        // a rook and a queen can both mate on the back rank
+        let solution = Game::from_fen("6k1/5ppp/8/8/8/8/8/R2QK3 w - - 0 1").unwrap().solve_mate(1);
+        assert!(solution.is_cooked());
+        let mut keys: Vec<&str> = solution.keys.iter().map(|k| k.played.san.as_str()).collect();
+        keys.sort();
+        assert_eq!(keys, vec!["Qd8#", "Ra8#"]);
+
+        // stalemating the defender is not mate
+        let game = Game::from_fen("k7/8/1K6/8/8/8/8/2Q5 w - - 0 1").unwrap();
+        let solution = game.solve_mate(1);
+        let keys: Vec<&str> = solution.keys.iter().map(|k| k.played.san.as_str()).collect();
+        assert!(keys.contains(&"Qc8#") && !keys.iter().any(|k| k.starts_with("Qc7")));
+
+        // after the king's move, the queen has several mates against the only defence
+        let solution = Game::from_fen("k7/8/2K5/8/8/8/8/1Q6 w - - 0 1").unwrap().solve_mate(2);
+        assert!(solution.is_cooked());
+        let key = solution.keys.iter().find(|k| k.played.san == "Kc7").unwrap();
+        assert_eq!(key.continuations.len(), 1);
+        assert_eq!(key.continuations[0].continuations.len(), 3);
+        assert_eq!(solution.duals(), vec![vec!["Kc7".to_string(), "Ka7".to_string()]]);
+    }
+}
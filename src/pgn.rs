@@ -0,0 +1,337 @@
+//! Portable Game Notation (PGN) for loading and saving games.
+
+use std::fmt;
+
+use super::fen::START_FEN;
+use super::{opponent, Colour, Game, Termination};
+
+/// A game in PGN: its tag pairs, its moves in standard algebraic notation and its result.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pgn {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<String>,
+    /// One of "1-0", "0-1", "1/2-1/2" or "*" for a game that has not finished.
+    pub result: String
+}
+
+impl Pgn {
+    /// Build the PGN of a game with the seven tag roster and the moves played so far.
+    pub fn from_game(_game: &Game) -> Pgn {
+        let result = match _game.get_outcome() {
+            Some(outcome) => match outcome.winner {
+                Some(Colour::White) => "1-0",
+                Some(Colour::Black) => "0-1",
+                None => "1/2-1/2"
+            },
+            None => "*"
+        }.to_owned();
+
+        let mut tags: Vec<(String, String)> = vec![
+            ("Event".to_owned(), "?".to_owned()),
+            ("Site".to_owned(), "?".to_owned()),
+            ("Date".to_owned(), "????.??.??".to_owned()),
+            ("Round".to_owned(), "?".to_owned()),
+            ("White".to_owned(), "?".to_owned()),
+            ("Black".to_owned(), "?".to_owned()),
+            ("Result".to_owned(), result.clone())
+        ];
+
+        // the tag only tells apart the standard kinds of ending, the result says the rest
+        if let Some(outcome) = _game.get_outcome() {
+            let termination = match outcome.termination {
+                Termination::Timeout => "time forfeit",
+                Termination::Abandonment => "abandoned",
+                Termination::Adjudication => "adjudication",
+                _ => "normal"
+            };
+            tags.push(("Termination".to_owned(), termination.to_owned()));
+        }
+        if let Some(opening) = _game.opening() {
+            tags.push(("ECO".to_owned(), opening.eco.to_owned()));
+            tags.push(("Opening".to_owned(), opening.name.to_owned()));
+        }
+
+        // games that do not start from the standard position need the position to be replayed
+        let start = match _game.history.first() {
+            Some(r) => r.before.to_fen(),
+            None => _game.to_fen()
+        };
+        if let Some(clock) = &_game.clock {
+            tags.push(("TimeControl".to_owned(), clock.get_time_control().to_string()));
+        }
+        if _game.chess960 {
+            tags.push(("Variant".to_owned(), "Chess960".to_owned()));
+        }
+        if start != START_FEN {
+            tags.push(("SetUp".to_owned(), "1".to_owned()));
+            tags.push(("FEN".to_owned(), start));
+        }
+
+        Pgn {
+            tags,
+            moves: _game.history.iter().map(|r| r.played.san.clone()).collect(),
+            result
+        }
+    }
+
+    /// Parse a single game in PGN. Comments, variations and annotations are skipped.
+    pub fn parse(_pgn: &str) -> Option<Pgn> {
+        let mut tags: Vec<(String, String)> = Vec::default();
+        let mut movetext = String::default();
+        for line in _pgn.lines() {
+            let line = line.trim();
+            if line.starts_with('[') {
+                tags.push(parse_tag(line)?);
+            } else if !line.starts_with('%') {
+                movetext += line;
+                movetext.push('\n');
+            }
+        }
+
+        let mut moves: Vec<String> = Vec::default();
+        let mut result = "*".to_owned();
+        for token in tokenize(&movetext)? {
+            match token.as_str() {
+                "1-0" | "0-1" | "1/2-1/2" | "*" => result = token,
+                _ if token.starts_with('$') => continue,
+                _ => {
+                    // drop move numbers such as "12." and "12...", which may be attached to the move
+                    let san = token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+                    if !san.is_empty() {
+                        moves.push(san.to_owned());
+                    }
+                }
+            }
+        }
+
+        Some(Pgn { tags, moves, result })
+    }
+
+    /// Parse every game in a collection of games in PGN, leaving out those that cannot be parsed.
+    pub fn parse_all(_pgns: &str) -> Vec<Pgn> {
+        let mut games: Vec<String> = Vec::default();
+        let mut current = String::default();
+        let mut movetext = false;
+        for line in _pgns.lines() {
+            let trimmed = line.trim();
+            // the tags of the next game start after the movetext of the last one
+            if trimmed.starts_with('[') && movetext {
+                games.push(std::mem::take(&mut current));
+                movetext = false;
+            } else if !trimmed.is_empty() && !trimmed.starts_with('[') {
+                movetext = true;
+            }
+            current += line;
+            current.push('\n');
+        }
+        games.push(current);
+        games.iter().filter(|g| !g.trim().is_empty()).filter_map(|g| Pgn::parse(g)).collect()
+    }
+
+    /// Get the value of a tag, if it is present.
+    pub fn get_tag(&self, _name: &str) -> Option<&str> {
+        self.tags.iter().find(|(n, _)| n == _name).map(|(_, v)| v.as_str())
+    }
+
+    /// Set the value of a tag, adding it if it is not present.
+    pub fn set_tag(&mut self, _name: &str, _value: &str) {
+        match self.tags.iter_mut().find(|(n, _)| n == _name) {
+            Some(tag) => tag.1 = _value.to_owned(),
+            None => self.tags.push((_name.to_owned(), _value.to_owned()))
+        }
+    }
+
+    /// Play the moves of the game from its starting position. Returns None if
+    /// the starting position or any of the moves is not legal. A game with a
+    /// result that did not end on the board is ended as the Result and
+    /// Termination tags say.
+    pub fn replay(&self) -> Option<Game> {
+        let mut game = match self.get_tag("FEN") {
+            Some(fen) => Game::from_fen(fen)?,
+            None => Game::new()
+        };
+        // castling is told apart from king moves by the variant, not only by the starting position
+        if let Some(variant) = self.get_tag("Variant") {
+            let variant = variant.to_lowercase();
+            if variant.contains("960") || variant.contains("fischer") {
+                game.chess960 = true;
+            }
+        }
+        for san in &self.moves {
+            game.make_san_move(san)?;
+        }
+
+        if game.get_outcome().is_none() {
+            let winner = match self.result.as_str() {
+                "1-0" => Some(Colour::White),
+                "0-1" => Some(Colour::Black),
+                "1/2-1/2" => None,
+                _ => return Some(game)
+            };
+            let termination = match (self.get_tag("Termination").map(|t| t.to_lowercase()).as_deref(), winner) {
+                (Some("time forfeit"), _) => Termination::Timeout,
+                (Some("abandoned"), _) => Termination::Abandonment,
+                (Some("adjudication"), _) => Termination::Adjudication,
+                (_, Some(_)) => Termination::Resignation,
+                // a draw that was not agreed must have been claimed
+                (_, None) if game.repetitions() >= 3 => Termination::Repetition,
+                (_, None) if game.halfmove_clock >= 100 => Termination::FiftyMoves,
+                (_, None) => Termination::Agreement
+            };
+            game.end(winner, termination);
+        }
+        Some(game)
+    }
+}
+
+// parse a tag pair such as [Event "Casual game"]
+pub(crate) fn parse_tag(_line: &str) -> Option<(String, String)> {
+    let inner = _line.strip_prefix('[')?.strip_suffix(']')?.trim();
+    let (name, value) = inner.split_once(char::is_whitespace)?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    Some((name.to_owned(), value.replace("\\\"", "\"").replace("\\\\", "\\")))
+}
+
+// split movetext into tokens, leaving out comments and variations
+fn tokenize(_movetext: &str) -> Option<Vec<String>> {
+    let mut tokens: Vec<String> = Vec::default();
+    let mut token = String::default();
+    let mut depth = 0;
+    let mut chars = _movetext.chars();
+    while let Some(c) = chars.next() {
+        // anything other than a plain character ends the current token
+        if (c.is_whitespace() || "{;()".contains(c)) && !token.is_empty() {
+            tokens.push(std::mem::take(&mut token));
+        }
+        match c {
+            // comments run to the closing brace or to the end of the line
+            '{' => { chars.by_ref().find(|&c| c == '}')?; },
+            ';' => { chars.by_ref().find(|&c| c == '\n'); },
+            '(' => depth += 1,
+            ')' => {
+                if depth == 0 { return None }
+                depth -= 1;
+            },
+            _ if depth > 0 || c.is_whitespace() => continue,
+            _ => token.push(c)
+        }
+    }
+    if depth != 0 { return None }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    Some(tokens)
+}
+
+/// Write the game in PGN export format, with the movetext wrapped at 80 columns.
+impl fmt::Display for Pgn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", write_tags(&self.tags))?;
+
+        // the move number and side to move of the first move come from the starting position
+        let (mut number, mut colour) = match self.get_tag("FEN").and_then(Game::from_fen) {
+            Some(g) => (g.fullmove_number, g.active),
+            None => (1, Colour::White)
+        };
+        let mut tokens: Vec<String> = Vec::default();
+        for (i, san) in self.moves.iter().enumerate() {
+            match colour {
+                Colour::White => tokens.push(format!("{}.", number)),
+                Colour::Black => if i == 0 { tokens.push(format!("{}...", number)) }
+            }
+            tokens.push(san.to_owned());
+            if colour == Colour::Black {
+                number += 1;
+            }
+            colour = opponent(colour);
+        }
+        tokens.push(self.result.clone());
+        write!(f, "{}", wrap(&tokens))
+    }
+}
+
+// write the tag pairs, followed by the blank line that ends them
+pub(crate) fn write_tags(_tags: &[(String, String)]) -> String {
+    let mut text = String::default();
+    for (name, value) in _tags {
+        text += &format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\""));
+    }
+    text + "\n"
+}
+
+// join movetext tokens into lines of at most 80 columns
+pub(crate) fn wrap(_tokens: &[String]) -> String {
+    let mut text = String::default();
+    let mut line = String::default();
+    for token in _tokens {
+        if !line.is_empty() && line.len() + token.len() + 1 > 80 {
+            text += &line;
+            text.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line += token;
+    }
+    text + &line + "\n"
+}
+
+impl Game {
+    /// Get the game so far in PGN.
+    pub fn to_pgn(&self) -> String {
+        Pgn::from_game(self).to_string()
+    }
+
+    /// Initialises a game by replaying a game given in PGN.
+    pub fn from_pgn(_pgn: &str) -> Option<Game> {
+        Pgn::parse(_pgn)?.replay()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Colour, Game, GameState, Outcome, Termination};
+    use super::Pgn;
+
+    #[test]
+    fn pgn_round_trip() {
+        let pgn = "[Event \"Casual game\"]\n[Result \"0-1\"]\n\n1. f3 {a weak move} e5 (1... e6) 2.g4 $4 Qh4# 0-1\n";
+        let parsed = Pgn::parse(pgn).unwrap();
+        assert_eq!(parsed.get_tag("Event"), Some("Casual game"));
+        assert_eq!(parsed.moves, vec!["f3", "e5", "g4", "Qh4#"]);
+        assert_eq!(parsed.result, "0-1");
+
+        let game = parsed.replay().unwrap();
+        assert_eq!(game.get_game_state(), GameState::GameOver);
+
+        let written = game.to_pgn();
+        assert!(written.contains("[Result \"0-1\"]\n[Termination \"normal\"]"));
+        assert!(written.ends_with("\n1. f3 e5 2. g4 Qh4# 0-1\n"));
+        assert_eq!(Game::from_pgn(&written).unwrap().to_fen(), game.to_fen());
+
+        // games from a set up position keep their starting position
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 10").unwrap();
+        game.make_san_move("Kd7");
+        let written = game.to_pgn();
+        assert!(written.contains("[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 10\"]"));
+        assert!(written.contains("\n10... Kd7 *\n"));
+        assert_eq!(Game::from_pgn(&written).unwrap().to_fen(), game.to_fen());
+
+        // games that did not end on the board take their outcome from the tags
+        let game = Game::from_pgn("[Result \"1-0\"]\n[Termination \"time forfeit\"]\n\n1. e4 1-0").unwrap();
+        assert_eq!(game.get_outcome(), Some(Outcome { winner: Some(Colour::White), termination: Termination::Timeout }));
+        let game = Game::from_pgn("[Result \"1/2-1/2\"]\n[Termination \"adjudication\"]\n\n1. e4 1/2-1/2").unwrap();
+        assert_eq!(game.get_outcome(), Some(Outcome { winner: None, termination: Termination::Adjudication }));
+        let game = Game::from_pgn("1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1 Ng8 1/2-1/2").unwrap();
+        assert_eq!(game.get_outcome().unwrap().termination, Termination::Repetition);
+        assert_eq!(Game::from_pgn("1. e4 0-1").unwrap().get_outcome().unwrap().termination, Termination::Resignation);
+
+        // Chess960 games are tagged with their variant
+        let mut game = Game::new_chess960(0).unwrap();
+        game.make_san_move("g4");
+        let written = game.to_pgn();
+        assert!(written.contains("[Variant \"Chess960\"]\n[SetUp \"1\"]\n[FEN \"bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1\"]"));
+        assert!(Game::from_pgn(&written).unwrap().chess960);
+    }
+}
@@ -0,0 +1,307 @@
+//! Finding puzzles in played games.
+//!
+//! Every position of a game in which a move was played is searched for a forced
+//! mate with the mate solver. The position is a puzzle when exactly one move
+//! mates within the number of moves searched, whether or not it was the move
+//! played. Positions without such a mate are searched by the built-in engine,
+//! and are puzzles when exactly one move scores at least 300 centipawns for the
+//! side to move, with the solution being the line the engine expects. Puzzles
+//! are tagged with their length, or with how much the solving move wins, and the
+//! tactics it makes, using the names of Lichess puzzle themes. A tactic counts
+//! only if it was not there before the move, and not when it is against a king
+//! the move mates.
+
+use std::cmp::Reverse;
+
+use super::engine::Engine;
+use super::mate::Variation;
+use super::pgn::Pgn;
+use super::tactics::Motif;
+use super::uci::Score;
+use super::{code_moves, get_king_pos, legal_moves, opponent, play_promotion, Colour, Game, GameState, Move};
+
+// the score in centipawns from which a move wins decisively, and from which it wins crushingly
+const DECISIVE: i32 = 300;
+const CRUSHING: i32 = 600;
+
+/// A position from a game with a single winning move.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Puzzle {
+    /// The index of the game in the collection it came from, 0 for a single game.
+    pub game: usize,
+    /// The number of moves played in the game before the position.
+    pub ply: usize,
+    pub fen: String,
+    /// The moves to mate from the solving move against the longest defence, or
+    /// the moves the engine expects after a move that wins material.
+    pub solution: Vec<Move>,
+    /// Whether the move played in the game was the solving move.
+    pub found: bool,
+    /// The themes of the puzzle, e.g. "mateIn2" or "crushing", and "fork".
+    pub themes: Vec<String>,
+    /// A rough rating of how hard the puzzle is, from about 800 up.
+    pub difficulty: u32
+}
+
+impl Puzzle {
+    /// Get the solution in SAN, e.g. "Ra6 bxa6 b7#".
+    pub fn solution_san(&self) -> String {
+        self.solution.iter().map(|m| m.san.as_str()).collect::<Vec<&str>>().join(" ")
+    }
+}
+
+// get the number of moves of both sides until mate, against the longest defence
+fn length(_attack: &Variation) -> usize {
+    1 + _attack.continuations.iter().map(|d| 1 + d.continuations.first().map(length).unwrap_or(0)).max().unwrap_or(0)
+}
+
+// add the moves to mate against the longest defence to the line
+fn solution_line(_attack: &Variation, _line: &mut Vec<Move>) {
+    _line.push(_attack.played.clone());
+    let defence = _attack.continuations.iter().min_by_key(|d| Reverse(d.continuations.first().map(length).unwrap_or(0)));
+    if let Some(defence) = defence {
+        _line.push(defence.played.clone());
+        if let Some(attack) = defence.continuations.first() {
+            solution_line(attack, _line);
+        }
+    }
+}
+
+fn theme(_motif: &Motif) -> &'static str {
+    match _motif {
+        Motif::Fork { .. } => "fork",
+        Motif::Pin { .. } => "pin",
+        Motif::Skewer { .. } => "skewer",
+        Motif::DiscoveredAttack { .. } => "discoveredAttack",
+        Motif::DiscoveredCheck { .. } => "discoveredCheck",
+        Motif::Hanging { .. } => "hangingPiece"
+    }
+}
+
+// check whether a tactic is against the piece on a square
+fn involves(_motif: &Motif, _square: &str) -> bool {
+    match _motif {
+        Motif::Fork { targets, .. } => targets.iter().any(|t| t == _square),
+        Motif::Pin { behind, .. } => behind == _square,
+        Motif::Skewer { front, .. } => front == _square,
+        Motif::DiscoveredCheck { king, .. } => king == _square,
+        _ => false
+    }
+}
+
+// check whether two tactics are the same, against the same pieces, as a pin kept by a pinner moving along the line is
+fn same(_a: &Motif, _b: &Motif) -> bool {
+    match (_a, _b) {
+        (Motif::Pin { pinned: a, behind: x, .. }, Motif::Pin { pinned: b, behind: y, .. }) => a == b && x == y,
+        (Motif::Skewer { front: a, behind: x, .. }, Motif::Skewer { front: b, behind: y, .. }) => a == b && x == y,
+        _ => _a == _b
+    }
+}
+
+// get the tactics a colour has after a move that it did not have before, leaving out those against a mated king
+fn new_motifs(_before: &Game, _after: &Game, _colour: Colour) -> Vec<Motif> {
+    let before = _before.tactics(_colour);
+    let mated = match _after.get_game_state() {
+        GameState::GameOver => code_moves(&vec![get_king_pos(_after, opponent(_colour))]).pop(),
+        _ => None
+    };
+    _after.tactics(_colour).into_iter()
+        .filter(|m| !before.iter().any(|b| same(b, m)) && !mated.as_ref().is_some_and(|k| involves(m, k)))
+        .collect()
+}
+
+// get a score in centipawns, with mates beyond any other score
+fn centipawns(_score: Score) -> i32 {
+    match _score {
+        Score::Centipawns(cp) => cp,
+        Score::Mate(moves) if moves > 0 => i32::MAX,
+        Score::Mate(_) => i32::MIN
+    }
+}
+
+// make a puzzle of a position from its solution, tagged with the theme of how it wins
+fn make_puzzle(_position: &Game, _played: &Move, _solution: Vec<Move>, _theme: String) -> Puzzle {
+    let moves = (_solution.len() as u32).div_ceil(2);
+    let solving = &_solution[0];
+    let found = (&_played.from, &_played.to, _played.promotion) == (&solving.from, &solving.to, solving.promotion);
+
+    let mut themes = vec![_theme];
+    let mut after = _position.clone();
+    if play_promotion(&mut after, solving.from.clone(), solving.to.clone(), solving.promotion).is_some() {
+        for motif in new_motifs(_position, &after, _position.active) {
+            let name = theme(&motif).to_owned();
+            if !themes.contains(&name) {
+                themes.push(name);
+            }
+        }
+    }
+
+    // longer solutions, quiet solving moves and many moves to choose from make a puzzle harder,
+    // and a player missing the solution suggests it is not obvious
+    let mut difficulty = 800 + 400 * (moves - 1) + 5 * legal_moves(_position).len() as u32;
+    if !solving.san.contains('+') && !solving.san.contains('#') {
+        difficulty += 300;
+    }
+    if !solving.san.contains('x') {
+        difficulty += 150;
+    }
+    if !found {
+        difficulty += 100;
+    }
+
+    Puzzle { game: 0, ply: 0, fen: _position.to_fen(), solution: _solution, found, themes, difficulty }
+}
+
+// make a puzzle of a position if the side to move has a single move that mates in at most the given number of moves
+fn mate_puzzle(_position: &Game, _played: &Move, _max_moves: u32) -> Option<Puzzle> {
+    if !_position.has_forced_mate(_max_moves) { return None }
+    let solution = _position.solve_mate(_max_moves);
+    let key = match solution.keys.as_slice() {
+        [key] => key,
+        _ => return None
+    };
+    let mut line: Vec<Move> = Vec::default();
+    solution_line(key, &mut line);
+    let moves = (line.len() as u32).div_ceil(2);
+    Some(make_puzzle(_position, _played, line, format!("mateIn{}", moves)))
+}
+
+// make a puzzle of a position if the engine finds a single move of the side to move that wins decisively
+fn winning_puzzle(_position: &Game, _played: &Move, _engine: &Engine) -> Option<Puzzle> {
+    let analyses = _engine.analyse(_position);
+    // a move that is the only one to play is no puzzle
+    let best = match analyses.as_slice() {
+        [best, second, ..] if centipawns(second.score) < DECISIVE => best,
+        _ => return None
+    };
+    let score = centipawns(best.score);
+    if score < DECISIVE { return None }
+    // the line ends with a move of the solving side
+    let mut line = best.line.clone();
+    line.truncate(line.len() - (1 - line.len() % 2));
+    let theme = match score >= CRUSHING {
+        true => "crushing",
+        false => "advantage"
+    };
+    Some(make_puzzle(_position, _played, line, theme.to_owned()))
+}
+
+impl Game {
+    /// Find the puzzles in the positions of the game where a move was played, with
+    /// mates in at most the given number of moves and moves that win decisively as
+    /// searched by the engine. Takes about as long as the engine analysing every
+    /// move of every position.
+    pub fn find_puzzles(&self, _max_moves: u32, _engine: &Engine) -> Vec<Puzzle> {
+        self.history.iter().enumerate().filter_map(|(ply, record)| {
+            let mut found = mate_puzzle(&record.before, &record.played, _max_moves)
+                .or_else(|| winning_puzzle(&record.before, &record.played, _engine))?;
+            found.ply = ply;
+            Some(found)
+        }).collect()
+    }
+}
+
+/// Find the puzzles in a collection of games in PGN, with mates in at most the
+/// given number of moves and moves the engine finds to win decisively. Games
+/// that cannot be replayed are left out.
+pub fn puzzles_from_pgn(_pgns: &str, _max_moves: u32, _engine: &Engine) -> Vec<Puzzle> {
+    let mut puzzles: Vec<Puzzle> = Vec::default();
+    for (index, pgn) in Pgn::parse_all(_pgns).iter().enumerate() {
+        if let Some(game) = pgn.replay() {
+            for mut puzzle in game.find_puzzles(_max_moves, _engine) {
+                puzzle.game = index;
+                puzzles.push(puzzle);
+            }
+        }
+    }
+    puzzles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::engine::Engine;
+    use super::super::tactics::Motif;
+    use super::super::{Colour, Game};
+    use super::{new_motifs, puzzles_from_pgn};
+
+    static PGNS: &str = "[Event \"1\"]\n\n1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0\n\n\
+        [Event \"2\"]\n[SetUp \"1\"]\n[FEN \"6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1\"]\n\n1. Kf1 Kf8 *\n";
+
+    // the game after moves in SAN from a position
+    fn after(_fen: &str, _moves: &[&str]) -> Game {
+        let mut game = Game::from_fen(_fen).unwrap();
+        for san in _moves {
+            game.make_san_move(san).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn found_puzzles() {
+        // the scholar's mate is found
+        let puzzles = puzzles_from_pgn(PGNS, 1, &Engine::new(2));
+        assert_eq!(puzzles.len(), 2);
+        assert_eq!((puzzles[0].game, puzzles[0].ply, puzzles[0].found), (0, 6, true));
+        assert_eq!(puzzles[0].fen, "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4");
+        assert_eq!(puzzles[0].solution_san(), "Qxf7#");
+    }
+
+    #[test]
+    fn missed_puzzles() {
+        // the back rank mate is missed, which makes it harder
+        let puzzles = puzzles_from_pgn(PGNS, 1, &Engine::new(2));
+        assert_eq!((puzzles[1].game, puzzles[1].ply, puzzles[1].found), (1, 0, false));
+        assert_eq!(puzzles[1].solution_san(), "Ra8#");
+        assert_eq!(puzzles[1].themes, vec!["mateIn1"]);
+        assert!(puzzles[1].difficulty > puzzles[0].difficulty);
+    }
+
+    #[test]
+    fn longer_mates() {
+        // a quiet rook move that lets a pawn mate
+        let puzzles = after("kbK5/pp6/1P6/8/8/8/8/R7 w - - 0 1", &["Ra6"]).find_puzzles(2, &Engine::new(2));
+        assert_eq!(puzzles.len(), 1);
+        assert!(puzzles[0].found);
+        assert_eq!(puzzles[0].solution.len(), 3);
+        assert!(puzzles[0].solution_san().starts_with("Ra6 ") && puzzles[0].solution_san().ends_with('#'));
+        assert!(puzzles[0].difficulty >= 1650);
+        // the rook keeps the pin it already had from a1
+        assert_eq!(puzzles[0].themes, vec!["mateIn2"]);
+    }
+
+    #[test]
+    fn winning_material() {
+        // a knight check forks king and queen, which is left hanging, and no other move wins anything
+        let puzzles = after("q3k3/8/8/3N4/8/8/8/4KB2 w - - 0 1", &["Nc7+"]).find_puzzles(1, &Engine::new(3));
+        assert_eq!(puzzles.len(), 1);
+        assert!(puzzles[0].found);
+        assert_eq!(puzzles[0].solution.len(), 3);
+        assert!(puzzles[0].solution_san().starts_with("Nc7+ ") && puzzles[0].solution_san().ends_with("Nxa8"));
+        assert_eq!(puzzles[0].themes, vec!["crushing", "fork", "hangingPiece"]);
+        // the same puzzle when the fork is missed
+        let missed = after("q3k3/8/8/3N4/8/8/8/4KB2 w - - 0 1", &["Ke2"]).find_puzzles(1, &Engine::new(3));
+        assert!(!missed[0].found);
+        assert_eq!(missed[0].solution_san(), puzzles[0].solution_san());
+    }
+
+    #[test]
+    fn themes_of_the_move() {
+        // a knight check forks king and rook, and the rook becomes a hanging piece
+        let fen = "r3k3/8/8/3N4/8/8/8/4K3 w - - 0 1";
+        assert_eq!(new_motifs(&after(fen, &[]), &after(fen, &["Nc7+"]), Colour::White), vec![
+            Motif::Fork { attacker: "c7".to_string(), targets: vec!["a8".to_string(), "e8".to_string()] },
+            Motif::Hanging { square: "a8".to_string() }
+        ]);
+    }
+
+    #[test]
+    fn mated_king_left_out() {
+        // the mating rook forks the king and a knight the bishop already left hanging
+        let fen = "1n4k1/5ppp/8/8/5B2/8/5PPP/4R1K1 w - - 0 1";
+        let mated = after(fen, &["Re8#"]);
+        assert!(mated.tactics(Colour::White).contains(&Motif::Fork { attacker: "e8".to_string(), targets: vec!["b8".to_string(), "g8".to_string()] }));
+        assert!(new_motifs(&after(fen, &[]), &mated, Colour::White).is_empty());
+        let puzzles = mated.find_puzzles(1, &Engine::new(2));
+        assert_eq!(puzzles[0].themes, vec!["mateIn1"]);
+    }
+}
@@ -0,0 +1,262 @@
+//! Player ratings, by the Elo system and by Glicko-2.
+//!
+//! Ratings are updated from the outcomes of finished games. Elo ratings change
+//! after every game. Glicko-2 ratings change once for each rating period, from
+//! all the games played in it, and keep track of how reliable a rating is
+//! (its deviation) and how much a player's results vary (their volatility), as
+//! described by Mark Glickman in "Example of the Glicko-2 system".
+
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use super::{Colour, Outcome};
+
+// the factor between Glicko and Glicko-2 ratings
+const GLICKO2_SCALE: f64 = 173.7178;
+
+/// Get the points a colour scored in a game: 1 for a win, 0.5 for a draw and 0 for a loss.
+pub fn points(_outcome: &Outcome, _colour: Colour) -> f64 {
+    match _outcome.winner {
+        Some(c) if c == _colour => 1.0,
+        Some(_) => 0.0,
+        None => 0.5
+    }
+}
+
+/// Get the score a player is expected to make against an opponent, by their Elo ratings.
+pub fn expected_score(_rating: f64, _opponent: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((_opponent - _rating) / 400.0))
+}
+
+/// The games of a rating period, between named players.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RatingPeriod {
+    games: Vec<(String, String, Outcome)>
+}
+
+impl RatingPeriod {
+    /// A rating period without any games.
+    pub fn new() -> RatingPeriod {
+        RatingPeriod::default()
+    }
+
+    /// Add a finished game between two players.
+    pub fn add_game(&mut self, _white: &str, _black: &str, _outcome: Outcome) {
+        self.games.push((_white.to_owned(), _black.to_owned(), _outcome));
+    }
+}
+
+/// The Elo system, with the number of points a rating can change by in a game.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Elo {
+    pub k: f64,
+    /// The rating of players who have not been rated yet.
+    pub initial: f64
+}
+
+impl Default for Elo {
+    fn default() -> Elo {
+        Elo { k: 20.0, initial: 1500.0 }
+    }
+}
+
+impl Elo {
+    /// Get the ratings of white and black after a game between them.
+    pub fn rate(&self, _white: f64, _black: f64, _outcome: &Outcome) -> (f64, f64) {
+        let change = self.k * (points(_outcome, Colour::White) - expected_score(_white, _black));
+        (_white + change, _black - change)
+    }
+
+    /// Update the ratings of the players of a rating period, game by game in the order they were added.
+    pub fn rate_period(&self, _ratings: &mut HashMap<String, f64>, _period: &RatingPeriod) {
+        for (white, black, outcome) in &_period.games {
+            let white_rating = *_ratings.get(white).unwrap_or(&self.initial);
+            let black_rating = *_ratings.get(black).unwrap_or(&self.initial);
+            let (white_rating, black_rating) = self.rate(white_rating, black_rating, outcome);
+            _ratings.insert(white.clone(), white_rating);
+            _ratings.insert(black.clone(), black_rating);
+        }
+    }
+}
+
+/// A Glicko-2 rating, on the same scale as Elo ratings.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rating {
+    pub rating: f64,
+    /// How uncertain the rating is, the width of one standard deviation.
+    pub deviation: f64,
+    /// How much the player's strength changes between rating periods.
+    pub volatility: f64
+}
+
+/// An unrated player.
+impl Default for Rating {
+    fn default() -> Rating {
+        Rating { rating: 1500.0, deviation: 350.0, volatility: 0.06 }
+    }
+}
+
+/// The Glicko-2 system, with the constant limiting how fast volatility changes,
+/// sensibly between 0.3 and 1.2.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Glicko2 {
+    pub tau: f64
+}
+
+impl Default for Glicko2 {
+    fn default() -> Glicko2 {
+        Glicko2 { tau: 0.5 }
+    }
+}
+
+// reduce the impact of a game by the deviation of the opponent's rating
+fn g(_phi: f64) -> f64 {
+    1.0 / (1.0 + 3.0 * _phi * _phi / (PI * PI)).sqrt()
+}
+
+impl Glicko2 {
+    /// Get a player's rating after a rating period, from the ratings of the
+    /// opponents they played and the points they scored against each. A player
+    /// without games only becomes less certain of their rating.
+    pub fn rate(&self, _player: &Rating, _games: &[(Rating, f64)]) -> Rating {
+        let mu = (_player.rating - 1500.0) / GLICKO2_SCALE;
+        let phi = _player.deviation / GLICKO2_SCALE;
+        let sigma = _player.volatility;
+        if _games.is_empty() {
+            return Rating { deviation: (phi * phi + sigma * sigma).sqrt() * GLICKO2_SCALE, ..*_player }
+        }
+
+        // the estimated variance of the rating from the games alone, and the estimated improvement
+        let mut variance = 0.0;
+        let mut improvement = 0.0;
+        for (opponent, score) in _games {
+            let (mu_j, phi_j) = ((opponent.rating - 1500.0) / GLICKO2_SCALE, opponent.deviation / GLICKO2_SCALE);
+            let expected = 1.0 / (1.0 + (-g(phi_j) * (mu - mu_j)).exp());
+            variance += g(phi_j).powi(2) * expected * (1.0 - expected);
+            improvement += g(phi_j) * (score - expected);
+        }
+        let v = 1.0 / variance;
+        let delta = v * improvement;
+
+        // find the new volatility by the Illinois algorithm
+        let a = (sigma * sigma).ln();
+        let tau = self.tau;
+        let f = |x: f64| -> f64 {
+            let ex = x.exp();
+            ex * (delta * delta - phi * phi - v - ex) / (2.0 * (phi * phi + v + ex).powi(2)) - (x - a) / (tau * tau)
+        };
+        let mut low = a;
+        let mut high = match delta * delta > phi * phi + v {
+            true => (delta * delta - phi * phi - v).ln(),
+            false => {
+                let mut k = 1.0;
+                while f(a - k * tau) < 0.0 {
+                    k += 1.0;
+                }
+                a - k * tau
+            }
+        };
+        let (mut f_low, mut f_high) = (f(low), f(high));
+        while (high - low).abs() > 0.000001 {
+            let c = low + (low - high) * f_low / (f_high - f_low);
+            let f_c = f(c);
+            if f_c * f_high <= 0.0 {
+                low = high;
+                f_low = f_high;
+            } else {
+                f_low /= 2.0;
+            }
+            high = c;
+            f_high = f_c;
+        }
+        let volatility = (low / 2.0).exp();
+
+        let phi_star = (phi * phi + volatility * volatility).sqrt();
+        let new_phi = 1.0 / (1.0 / (phi_star * phi_star) + 1.0 / v).sqrt();
+        let new_mu = mu + new_phi * new_phi * improvement;
+        Rating { rating: new_mu * GLICKO2_SCALE + 1500.0, deviation: new_phi * GLICKO2_SCALE, volatility }
+    }
+
+    /// Update the ratings of the players of a rating period, all from the
+    /// ratings they had at its start. Players who are not in the period become
+    /// less certain of their ratings.
+    pub fn rate_period(&self, _ratings: &mut HashMap<String, Rating>, _period: &RatingPeriod) {
+        let mut games: HashMap<String, Vec<(Rating, f64)>> = HashMap::default();
+        for name in _ratings.keys() {
+            games.insert(name.clone(), Vec::default());
+        }
+        for (white, black, outcome) in &_period.games {
+            let white_rating = _ratings.get(white).copied().unwrap_or_default();
+            let black_rating = _ratings.get(black).copied().unwrap_or_default();
+            games.entry(white.clone()).or_default().push((black_rating, points(outcome, Colour::White)));
+            games.entry(black.clone()).or_default().push((white_rating, points(outcome, Colour::Black)));
+        }
+        for (name, played) in games {
+            let rating = self.rate(&_ratings.get(&name).copied().unwrap_or_default(), &played);
+            _ratings.insert(name, rating);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::super::{Colour, Outcome, Termination};
+    use super::{Elo, Glicko2, Rating, RatingPeriod};
+
+    fn win(_winner: Colour) -> Outcome {
+        Outcome { winner: Some(_winner), termination: Termination::Checkmate }
+    }
+
+    // the player and the opponents of the worked example of Glickman's paper
+    fn example() -> (Rating, [(Rating, f64); 3]) {
+        let player = Rating { rating: 1500.0, deviation: 200.0, volatility: 0.06 };
+        let games = [
+            (Rating { rating: 1400.0, deviation: 30.0, volatility: 0.06 }, 1.0),
+            (Rating { rating: 1550.0, deviation: 100.0, volatility: 0.06 }, 0.0),
+            (Rating { rating: 1700.0, deviation: 300.0, volatility: 0.06 }, 0.0)
+        ];
+        (player, games)
+    }
+
+    #[test]
+    fn elo() {
+        let elo = Elo { k: 32.0, ..Elo::default() };
+        assert_eq!(elo.rate(1500.0, 1500.0, &win(Colour::White)), (1516.0, 1484.0));
+        let (white, black) = elo.rate(1600.0, 1200.0, &Outcome { winner: None, termination: Termination::Agreement });
+        assert!((white - 1586.91).abs() < 0.01 && (black - 1213.09).abs() < 0.01);
+    }
+
+    #[test]
+    fn glicko2() {
+        let (player, games) = example();
+        let rated = Glicko2::default().rate(&player, &games);
+        assert!((rated.rating - 1464.06).abs() < 0.01);
+        assert!((rated.deviation - 151.52).abs() < 0.01);
+        assert!((rated.volatility - 0.05999).abs() < 0.00001);
+    }
+
+    #[test]
+    fn glicko2_period() {
+        // the same example, from the outcomes of games between named players
+        let (player, games) = example();
+        let mut ratings: HashMap<String, Rating> = HashMap::default();
+        ratings.insert("player".to_string(), player);
+        for (i, (opponent, _)) in games.iter().enumerate() {
+            ratings.insert(i.to_string(), *opponent);
+        }
+        ratings.insert("absent".to_string(), player);
+        let mut period = RatingPeriod::new();
+        period.add_game("player", "0", win(Colour::White));
+        period.add_game("1", "player", win(Colour::White));
+        period.add_game("player", "2", Outcome { winner: Some(Colour::Black), termination: Termination::Resignation });
+        Glicko2::default().rate_period(&mut ratings, &period);
+        assert!((ratings["player"].rating - 1464.06).abs() < 0.01);
+        assert!(ratings["0"].rating < 1400.0 && ratings["2"].rating > 1700.0);
+
+        // a player without games only becomes less certain
+        assert_eq!(ratings["absent"].rating, 1500.0);
+        assert!((ratings["absent"].deviation - 200.27).abs() < 0.01);
+    }
+}
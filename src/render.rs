@@ -0,0 +1,193 @@
+//! Drawing the board as text, with Unicode figurines, ANSI colours and coordinates.
+
+use std::fmt;
+
+use super::{decode_position, get_king_pos, Colour, Game, GameState, Piece};
+
+// ANSI escape codes for the squares and pieces
+static LIGHT_SQUARE: &str = "\x1b[48;5;223m";
+static DARK_SQUARE: &str = "\x1b[48;5;137m";
+static LIGHT_MOVED: &str = "\x1b[48;5;186m";
+static DARK_MOVED: &str = "\x1b[48;5;143m";
+static CHECKED: &str = "\x1b[48;5;160m";
+static WHITE_PIECE: &str = "\x1b[1;97m";
+static BLACK_PIECE: &str = "\x1b[1;30m";
+static RESET: &str = "\x1b[0m";
+
+/// Options for drawing a board as text.
+///
+/// The default draws Unicode figurines with coordinates from white's side, without colours.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Renderer {
+    /// Draw pieces as Unicode figurines instead of letters.
+    pub unicode: bool,
+    /// Colour the squares with ANSI escape codes.
+    pub colour: bool,
+    /// Label the files a–h and the ranks 1–8.
+    pub coordinates: bool,
+    /// The side the board is seen from, which is drawn at the bottom.
+    pub orientation: Colour,
+    /// Highlight the squares of the last move played. Needs colour.
+    pub highlight_last_move: bool,
+    /// Highlight the king of the side to move when it is in check. Needs colour.
+    pub highlight_check: bool
+}
+
+impl Default for Renderer {
+    fn default() -> Renderer {
+        Renderer {
+            unicode: true,
+            colour: false,
+            coordinates: true,
+            orientation: Colour::White,
+            highlight_last_move: true,
+            highlight_check: true
+        }
+    }
+}
+
+// get the character for a piece
+fn symbol(_piece: Piece, _unicode: bool, _colour: bool) -> char {
+    if !_unicode {
+        return match _piece.unwrap() {
+            Colour::White => _piece.letter(),
+            Colour::Black => _piece.letter().to_ascii_lowercase()
+        }
+    }
+    // on coloured squares the filled figurines are used for both sides and told apart by colour
+    match (_piece, _colour) {
+        (Piece::King(Colour::White), false) => '♔',
+        (Piece::Queen(Colour::White), false) => '♕',
+        (Piece::Rook(Colour::White), false) => '♖',
+        (Piece::Bishop(Colour::White), false) => '♗',
+        (Piece::Knight(Colour::White), false) => '♘',
+        (Piece::Pawn(Colour::White), false) => '♙',
+        (Piece::King(_), _) => '♚',
+        (Piece::Queen(_), _) => '♛',
+        (Piece::Rook(_), _) => '♜',
+        (Piece::Bishop(_), _) => '♝',
+        (Piece::Knight(_), _) => '♞',
+        (Piece::Pawn(_), _) => '♟'
+    }
+}
+
+impl Renderer {
+    /// Draw the board of a game, one line per rank.
+    pub fn render(&self, _game: &Game) -> String {
+        let ranks: Vec<usize> = match self.orientation {
+            Colour::White => (0..8).rev().collect(),
+            Colour::Black => (0..8).collect()
+        };
+        let files: Vec<usize> = match self.orientation {
+            Colour::White => (0..8).collect(),
+            Colour::Black => (0..8).rev().collect()
+        };
+
+        let last_move: Vec<Vec<usize>> = match _game.history.last() {
+            Some(r) if self.highlight_last_move => vec![decode_position(&r.played.from), decode_position(&r.played.to)],
+            _ => Vec::default()
+        };
+        let checked: Option<Vec<usize>> = match _game.state {
+            GameState::Check | GameState::GameOver if self.highlight_check => Some(get_king_pos(_game, _game.active)),
+            _ => None
+        };
+
+        let mut out = String::default();
+        for &rank in &ranks {
+            if self.coordinates {
+                out += &format!("{} ", rank + 1);
+            }
+            for &file in &files {
+                let square = vec![rank, file];
+                let piece = match _game.board[rank][file] {
+                    Some(p) => symbol(p, self.unicode, self.colour),
+                    None if self.colour => ' ',
+                    None if self.unicode => '·',
+                    None => '.'
+                };
+
+                if self.colour {
+                    let light = (rank + file) % 2 == 1;
+                    let background = if checked.as_ref() == Some(&square) {
+                        CHECKED
+                    } else if last_move.contains(&square) {
+                        if light { LIGHT_MOVED } else { DARK_MOVED }
+                    } else if light {
+                        LIGHT_SQUARE
+                    } else {
+                        DARK_SQUARE
+                    };
+                    let foreground = match _game.board[rank][file].map(|p| p.unwrap()) {
+                        Some(Colour::Black) => BLACK_PIECE,
+                        _ => WHITE_PIECE
+                    };
+                    out += &format!("{}{} {} {}", background, foreground, piece, RESET);
+                } else {
+                    out.push(piece);
+                    out.push(' ');
+                }
+            }
+            // no trailing spaces on uncoloured boards
+            let trimmed = out.trim_end_matches(' ').len();
+            out.truncate(trimmed);
+            out.push('\n');
+        }
+
+        if self.coordinates {
+            out += "  ";
+            for &file in &files {
+                let letter = (b'a' + file as u8) as char;
+                match self.colour {
+                    true => out += &format!(" {} ", letter),
+                    false => out += &format!("{} ", letter)
+                }
+            }
+            let trimmed = out.trim_end_matches(' ').len();
+            out.truncate(trimmed);
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Draw the board with Unicode figurines and coordinates, seen from white's side.
+///
+/// Output example:
+/// 8 ♜ ♞ ♝ ♛ ♚ ♝ ♞ ♜
+/// 7 ♟ ♟ ♟ ♟ ♟ ♟ ♟ ♟
+/// 6 · · · · · · · ·
+/// 5 · · · · · · · ·
+/// 4 · · · · · · · ·
+/// 3 · · · · · · · ·
+/// 2 ♙ ♙ ♙ ♙ ♙ ♙ ♙ ♙
+/// 1 ♖ ♘ ♗ ♕ ♔ ♗ ♘ ♖
+///   a b c d e f g h
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Renderer::default().render(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Colour, Game};
+    use super::Renderer;
+
+    #[test]
+    fn render_board() {
+        let mut game = Game::new();
+        assert_eq!(game.to_string(), "8 ♜ ♞ ♝ ♛ ♚ ♝ ♞ ♜\n7 ♟ ♟ ♟ ♟ ♟ ♟ ♟ ♟\n6 · · · · · · · ·\n5 · · · · · · · ·\n4 · · · · · · · ·\n3 · · · · · · · ·\n2 ♙ ♙ ♙ ♙ ♙ ♙ ♙ ♙\n1 ♖ ♘ ♗ ♕ ♔ ♗ ♘ ♖\n  a b c d e f g h\n");
+
+        game.make_san_move("e4");
+        let renderer = Renderer { unicode: false, coordinates: false, orientation: Colour::Black, ..Renderer::default() };
+        assert_eq!(renderer.render(&game), "R N B K Q B N R\nP P P . P P P P\n. . . . . . . .\n. . . P . . . .\n. . . . . . . .\n. . . . . . . .\np p p p p p p p\nr n b k q b n r\n");
+
+        // the last move and the checked king are highlighted
+        game.make_san_move("f5");
+        game.make_san_move("Qh5+");
+        let board = Renderer { colour: true, ..Renderer::default() }.render(&game);
+        assert!(board.contains("\x1b[48;5;160m\x1b[1;30m ♚ \x1b[0m"));
+        assert!(board.contains("\x1b[48;5;186m\x1b[1;97m ♛ \x1b[0m"));
+        assert!(board.contains("\x1b[48;5;186m\x1b[1;97m   \x1b[0m"));
+    }
+}
@@ -0,0 +1,209 @@
+//! Standard algebraic notation (SAN), e.g. "e4", "Nbd7", "exd5" and "e8=Q+".
+
+use super::{castling_side, check_position, code_moves, decode_position, get_king_pos, play_promotion, Colour, Game, GameState, Piece, FILES};
+
+// build the SAN of a legal move without the check suffix, from the position before the move
+pub(crate) fn move_body(_game: &Game, _from: &[usize], _to: &[usize]) -> String {
+    let piece = _game.board[_from[0]][_from[1]].unwrap();
+    let target = code_moves(&vec![_to.to_vec()])[0].clone();
+    let capture = _game.board[_to[0]][_to[1]].is_some();
+
+    match castling_side(_game, _from, _to) {
+        Some(0) => return "O-O".to_owned(),
+        Some(_) => return "O-O-O".to_owned(),
+        None => ()
+    }
+
+    match piece {
+        Piece::Pawn(c) => {
+            // a pawn changing file always takes, if need be en passant
+            let mut san = match capture || _from[1] != _to[1] {
+                true => format!("{}x{}", FILES[_from[1]], target),
+                false => target
+            };
+            // a pawn reaching the last rank is promoted to the piece set for its colour
+            if (c == Colour::White && _to[0] == 7) || (c == Colour::Black && _to[0] == 0) {
+                let promotion = match c {
+                    Colour::White => _game.white_promotion,
+                    Colour::Black => _game.black_promotion
+                };
+                san.push('=');
+                san.push(promotion.letter());
+            }
+            san
+        },
+        _ => {
+            // other pieces of the same kind that can reach the same square
+            let mut others: Vec<Vec<usize>> = Vec::default();
+            for (rank, row) in _game.board.iter().enumerate() {
+                for (file, square) in row.iter().enumerate() {
+                    if *square == Some(piece) && (rank != _from[0] || file != _from[1]) {
+                        let position = code_moves(&vec![vec![rank, file]])[0].clone();
+                        if let Some(moves) = _game.get_possible_moves(position) {
+                            if moves.contains(&target) {
+                                others.push(vec![rank, file]);
+                            }
+                        }
+                    }
+                }
+            }
+
+            let mut san = piece.letter().to_string();
+            if !others.is_empty() {
+                if others.iter().all(|o| o[1] != _from[1]) {
+                    san.push(FILES[_from[1]]);
+                } else if others.iter().all(|o| o[0] != _from[0]) {
+                    san += &(_from[0] + 1).to_string();
+                } else {
+                    san.push(FILES[_from[1]]);
+                    san += &(_from[0] + 1).to_string();
+                }
+            }
+            if capture {
+                san.push('x');
+            }
+            san + &target
+        }
+    }
+}
+
+impl Game {
+    /// Find the legal move described by a move in standard algebraic notation.
+    /// Returns the squares moved from and to, and the piece a pawn is promoted to, if any.
+    pub fn parse_san(&self, _san: &str) -> Option<(String, String, Option<Piece>)> {
+        if self.state == GameState::GameOver { return None }
+        let san = _san.trim_end_matches(['+', '#', '!', '?']);
+
+        // castling is written the same way whichever squares the king and rook are on
+        let side = match san {
+            "O-O" | "0-0" => Some(0),
+            "O-O-O" | "0-0-0" => Some(1),
+            _ => None
+        };
+        if let Some(side) = side {
+            let king = get_king_pos(self, self.active);
+            let from = code_moves(&vec![king.clone()])[0].clone();
+            let to = self.get_possible_moves(from.clone())?.into_iter()
+                .find(|m| castling_side(self, &king, &decode_position(m)) == Some(side))?;
+            return Some((from, to, None))
+        }
+
+        // split off the promotion, written as "e8=Q" or "e8Q"
+        let (san, promotion) = match san.char_indices().last() {
+            Some((i, c)) if i >= 2 && "QRBN".contains(c) => {
+                let promotion = Piece::from_letter(c, self.active);
+                (san[..i].trim_end_matches('='), promotion)
+            },
+            _ => (san, None)
+        };
+
+        let (piece, rest) = match san.chars().next() {
+            Some(c) if "KQRBN".contains(c) => (Piece::from_letter(c, self.active)?, &san[1..]),
+            _ => (Piece::Pawn(self.active), san)
+        };
+        let rest = rest.replace('x', "");
+        if rest.len() < 2 || !rest.is_ascii() { return None }
+        let (hint, target) = rest.split_at(rest.len() - 2);
+        if !check_position(target) { return None }
+        // only a pawn reaching the last rank is promoted
+        let last_rank = match self.active {
+            Colour::White => '8',
+            Colour::Black => '1'
+        };
+        if promotion.is_some() && !(matches!(piece, Piece::Pawn(_)) && target.ends_with(last_rank)) { return None }
+
+        // the hint disambiguates between pieces by file, rank or both
+        let mut hint_file = None;
+        let mut hint_rank = None;
+        for c in hint.chars() {
+            match c {
+                'a'..='h' => hint_file = FILES.iter().position(|&f| f == c),
+                '1'..='8' => hint_rank = Some(c as usize - '1' as usize),
+                _ => return None
+            }
+        }
+
+        let mut candidates: Vec<String> = Vec::default();
+        for (rank, row) in self.board.iter().enumerate() {
+            for (file, square) in row.iter().enumerate() {
+                if *square != Some(piece) || hint_file.is_some_and(|f| f != file) || hint_rank.is_some_and(|r| r != rank) {
+                    continue
+                }
+                // pawns only leave their file when capturing, which is always written with the file
+                if let Piece::Pawn(_) = piece {
+                    if hint_file.is_none() && decode_position(target)[1] != file { continue }
+                }
+                let position = code_moves(&vec![vec![rank, file]])[0].clone();
+                if let Some(moves) = self.get_possible_moves(position.clone()) {
+                    if moves.iter().any(|m| m == target) {
+                        candidates.push(position);
+                    }
+                }
+            }
+        }
+
+        match candidates.len() {
+            1 => Some((candidates.remove(0), target.to_owned(), promotion)),
+            _ => None
+        }
+    }
+
+    /// If the move given in standard algebraic notation is legal, play it
+    /// and return the resulting state of the game.
+    pub fn make_san_move(&mut self, _san: &str) -> Option<GameState> {
+        let (from, to, promotion) = self.parse_san(_san)?;
+        play_promotion(self, from, to, promotion)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Game;
+
+    #[test]
+    fn san_round_trip() {
+        let mut game = Game::new();
+        for san in ["d4", "d5", "Nf3", "Nf6"] {
+            assert_ne!(game.make_san_move(san), None, "{}", san);
+        }
+
+        // ambiguous and illegal moves are rejected
+        assert_eq!(game.parse_san("Nd2"), None);
+        assert_eq!(game.parse_san("Ke4"), None);
+        assert_eq!(game.parse_san("Nbd2"), Some(("b1".to_string(), "d2".to_string(), None)));
+
+        for san in ["Nbd2", "Nbd7", "Ne5", "Nxe5", "dxe5", "Ng4", "h3", "Nxe5"] {
+            assert_ne!(game.make_san_move(san), None, "{}", san);
+        }
+        let history: Vec<String> = game.get_history().iter().map(|m| m.san.clone()).collect();
+        assert_eq!(history, vec!["d4", "d5", "Nf3", "Nf6", "Nbd2", "Nbd7", "Ne5", "Nxe5", "dxe5", "Ng4", "h3", "Nxe5"]);
+
+        let mut game = Game::from_fen("8/1P2k3/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(game.make_san_move("b8=N"), Some(super::super::GameState::InProgress));
+        assert_eq!(game.get_history()[0].san, "b8=N");
+        assert_eq!(game.board[7][1], Some(super::super::Piece::Knight(super::super::Colour::White)));
+
+        // castling and taking en passant
+        let mut game = Game::from_fen("r3k2r/8/8/8/4p3/8/3P4/R3K2R w KQkq - 0 1").unwrap();
+        for san in ["O-O", "O-O-O", "d4", "exd3", "Rf2", "Rd7"] {
+            assert_ne!(game.make_san_move(san), None, "{}", san);
+        }
+        let history: Vec<String> = game.get_history().iter().map(|m| m.san.clone()).collect();
+        assert_eq!(history, vec!["O-O", "O-O-O", "d4", "exd3", "Rf2", "Rd7"]);
+        assert_eq!(game.to_fen(), "2k4r/3r4/8/8/8/3p4/5R2/R5K1 w - - 2 4");
+    }
+
+    #[test]
+    fn promotion_suffixes() {
+        // a promotion piece is only accepted for a pawn reaching the last rank
+        let mut game = Game::from_fen("4k3/P7/8/8/8/8/7P/4K3 w - - 0 1").unwrap();
+        assert_eq!(game.make_san_move("Ke2N"), None);
+        assert_eq!(game.make_san_move("h3N"), None);
+        assert_eq!(game.make_san_move("Kd2=Q"), None);
+        assert_ne!(game.make_san_move("h3"), None);
+        assert_ne!(game.make_san_move("Kf7"), None);
+        // the rejected moves left the promotion setting alone
+        assert_ne!(game.make_move("a7".to_string(), "a8".to_string()), None);
+        assert_eq!(game.board[7][0], Some(super::super::Piece::Queen(super::super::Colour::White)));
+    }
+}
@@ -0,0 +1,200 @@
+//! Serialisation with serde, behind the `serde` feature.
+//!
+//! A game is written as the position it started from and the moves played,
+//! rather than as its board, so that the format does not depend on how the
+//! game is stored. In JSON:
+//!
+//! ```json
+//! {
+//!   "variant": "standard",
+//!   "start": "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+//!   "moves": [
+//!     { "from": "e2", "to": "e4", "promotion": null, "san": "e4" }
+//!   ],
+//!   "fen": "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
+//!   "outcome": null
+//! }
+//! ```
+//!
+//! - `variant` is "standard" or "chess960".
+//! - `start` is the FEN of the starting position.
+//! - `moves` are replayed by their squares and promotion piece. The SAN is
+//!   written for readers and ignored when reading. A promotion piece has to
+//!   be a queen, rook, bishop or knight of the side to move, given only for a
+//!   pawn reaching the last rank.
+//! - `fen` is the current position. It can be left out when reading, and a
+//!   game whose moves do not lead to it is rejected.
+//! - `outcome` ends the game when it did not end on the board, e.g. by
+//!   resignation, with a winner of "white", "black" or null for a draw.
+//!
+//! Pieces are written as their FEN letter, e.g. "N" for a white knight and "n"
+//! for a black one. The clock and any pending draw offer are not kept.
+
+use serde::de::{self, Deserializer};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+
+use super::{play_promotion, Colour, Game, Move, Outcome, Piece};
+
+impl Serialize for Piece {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let letter = match self.unwrap() {
+            Colour::White => self.letter(),
+            Colour::Black => self.letter().to_ascii_lowercase()
+        };
+        serializer.serialize_char(letter)
+    }
+}
+
+impl<'de> Deserialize<'de> for Piece {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Piece, D::Error> {
+        let letter = char::deserialize(deserializer)?;
+        let colour = match letter.is_ascii_uppercase() {
+            true => Colour::White,
+            false => Colour::Black
+        };
+        Piece::from_letter(letter, colour).ok_or_else(|| de::Error::custom(format!("not a piece: {}", letter)))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Variant {
+    Standard,
+    Chess960
+}
+
+// the serialised form of a game
+#[derive(Serialize, Deserialize)]
+struct GameData {
+    variant: Variant,
+    start: String,
+    moves: Vec<Move>,
+    #[serde(default)]
+    fen: Option<String>,
+    #[serde(default)]
+    outcome: Option<Outcome>
+}
+
+impl Serialize for Game {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let start = match self.history.first() {
+            Some(r) => r.before.to_fen(),
+            None => self.to_fen()
+        };
+        GameData {
+            variant: if self.chess960 { Variant::Chess960 } else { Variant::Standard },
+            start,
+            moves: self.get_history(),
+            fen: Some(self.to_fen()),
+            outcome: self.get_outcome()
+        }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Game {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Game, D::Error> {
+        let data = GameData::deserialize(deserializer)?;
+        let mut game = Game::from_fen(&data.start).ok_or_else(|| de::Error::custom(format!("invalid FEN: {}", data.start)))?;
+        if let Variant::Chess960 = data.variant {
+            game.chess960 = true;
+        }
+        for m in &data.moves {
+            // a promotion piece has to be one the side to move can promote a pawn to, on a move that promotes
+            if play_promotion(&mut game, m.from.clone(), m.to.clone(), m.promotion).is_none() {
+                return Err(match m.promotion {
+                    Some(p) => de::Error::custom(format!("illegal promotion: {}{}={}", m.from, m.to, p.letter())),
+                    None => de::Error::custom(format!("illegal move: {}{}", m.from, m.to))
+                })
+            }
+        }
+        if let Some(fen) = &data.fen {
+            if *fen != game.to_fen() {
+                return Err(de::Error::custom(format!("the moves do not lead to {}", fen)))
+            }
+        }
+        if let (None, Some(outcome)) = (game.get_outcome(), data.outcome) {
+            game.end(outcome.winner, outcome.termination);
+        }
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Colour, Game, Piece, Termination};
+
+    // a game ending in a promotion to a knight and a resignation
+    fn promoted() -> Game {
+        let mut game = Game::new();
+        for san in ["e4", "d5", "exd5", "c6", "dxc6", "Qd7", "cxb7", "Kd8"] {
+            game.make_san_move(san).unwrap();
+        }
+        game.set_promotion("kn".to_string());
+        game.make_move("b7".to_string(), "a8".to_string()).unwrap();
+        game.resign(Colour::Black);
+        game
+    }
+
+    // a game in JSON from a position where white can promote on b8, with a single move
+    fn one_move(_from: &str, _to: &str, _promotion: &str) -> String {
+        format!(
+            r#"{{"variant": "standard", "start": "8/1P6/8/8/8/8/k7/4K3 w - - 0 1", "moves": [{{"from": "{}", "to": "{}", "promotion": {}, "san": ""}}]}}"#,
+            _from, _to, _promotion
+        )
+    }
+
+    #[test]
+    fn json_format() {
+        let value = serde_json::to_value(promoted()).unwrap();
+        assert_eq!(value["variant"], "standard");
+        assert_eq!(value["moves"][8]["promotion"], "N");
+        assert_eq!(value["moves"][8]["san"], "bxa8=N");
+        assert_eq!(value["outcome"]["winner"], "white");
+        assert_eq!(value["outcome"]["termination"], "resignation");
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let game = promoted();
+        let read: Game = serde_json::from_str(&serde_json::to_string(&game).unwrap()).unwrap();
+        assert_eq!(read.to_fen(), game.to_fen());
+        assert_eq!(read.get_history(), game.get_history());
+        assert_eq!(read.get_outcome(), game.get_outcome());
+        assert_eq!(read.get_outcome().unwrap().termination, Termination::Resignation);
+    }
+
+    #[test]
+    fn current_position() {
+        // the current position may be left out, but has to match if it is given
+        let start = r#"{"variant": "standard", "start": "8/8/8/4k3/8/8/8/4K2R w K - 0 1", "moves": [{"from": "e1", "to": "g1", "promotion": null, "san": ""}]"#;
+        let read: Game = serde_json::from_str(&format!("{}}}", start)).unwrap();
+        assert_eq!(read.to_fen(), "8/8/8/4k3/8/8/8/5RK1 b - - 1 1");
+        assert!(serde_json::from_str::<Game>(&format!(r#"{}, "fen": "8/8/8/4k3/8/8/8/4K2R b - - 1 1"}}"#, start)).is_err());
+        assert!(serde_json::from_str::<Game>(&format!("{}}}", start.replace("g1", "h3"))).is_err());
+    }
+
+    #[test]
+    fn promotions() {
+        let read: Game = serde_json::from_str(&one_move("b7", "b8", r#""R""#)).unwrap();
+        assert_eq!(read.get_history()[0].promotion, Some(Piece::Rook(Colour::White)));
+        let read: Game = serde_json::from_str(&one_move("b7", "b8", "null")).unwrap();
+        assert_eq!(read.get_history()[0].promotion, Some(Piece::Queen(Colour::White)));
+    }
+
+    #[test]
+    fn rejected_promotions() {
+        // a piece of the other side, a king or a pawn, and a piece on a move that does not promote
+        for (from, to, promotion) in [("b7", "b8", r#""q""#), ("b7", "b8", r#""K""#), ("b7", "b8", r#""P""#), ("e1", "e2", r#""Q""#)] {
+            let error = serde_json::from_str::<Game>(&one_move(from, to, promotion)).unwrap_err();
+            assert!(error.to_string().starts_with("illegal promotion"));
+        }
+    }
+
+    #[test]
+    fn pieces() {
+        assert_eq!(serde_json::to_string(&Piece::Knight(Colour::Black)).unwrap(), r#""n""#);
+        assert_eq!(serde_json::from_str::<Piece>(r#""Q""#).unwrap(), Piece::Queen(Colour::White));
+        assert!(serde_json::from_str::<Piece>(r#""x""#).is_err());
+    }
+}
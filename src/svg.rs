@@ -0,0 +1,216 @@
+//! Board diagrams as self-contained SVG images.
+//!
+//! The pieces are drawn from shapes defined in the image itself, so the output
+//! does not depend on fonts or any other external files.
+
+use super::{decode_position, Colour, Game, Piece};
+
+// size of a square in SVG units, and of the margin holding the coordinates
+const SQUARE: usize = 45;
+const MARGIN: usize = 15;
+
+// the outlines of the pieces, drawn in a 45x45 square
+static PIECES: &str = r##"<g id="pawn"><circle cx="22.5" cy="13" r="5"/><path d="M16.5 33C17 27 19.5 21 22.5 21C25.5 21 28 27 28.5 33Z"/><rect x="13" y="33" width="19" height="5" rx="1.5"/></g>
+<g id="rook"><path d="M11 9H15V12H20V9H25V12H30V9H34V17H11Z"/><rect x="14" y="17" width="17" height="17"/><rect x="10" y="34" width="25" height="5" rx="1.5"/></g>
+<g id="knight"><path d="M19 10L20 6L23 10C32 11 37 18 36 38H14C14 30 22 28 20 23C16 26 13 28 11 26C9 24 10 20 13 17C16 14 17 12 19 10Z"/><circle cx="17" cy="16" r="1.2"/></g>
+<g id="bishop"><circle cx="22.5" cy="7" r="2.5"/><path d="M22.5 9.5C16 14 14 20 16.5 26H28.5C31 20 29 14 22.5 9.5Z"/><path d="M24.5 14L20.5 20" fill="none"/><path d="M16.5 26H28.5L29.5 35H15.5Z"/><rect x="10" y="35" width="25" height="4" rx="1.5"/></g>
+<g id="queen"><path d="M9 27L8 14L15 24L15.5 11L20 23L22.5 9L25 23L29.5 11L30 24L37 14L36 27Z"/><circle cx="8" cy="13" r="2"/><circle cx="15.5" cy="10" r="2"/><circle cx="22.5" cy="8" r="2"/><circle cx="29.5" cy="10" r="2"/><circle cx="37" cy="13" r="2"/><path d="M9 27C10 31 11 33 11 37H34C34 33 35 31 36 27Z"/></g>
+<g id="king"><path d="M22.5 5V12M19.5 8H25.5" fill="none"/><path d="M22.5 24C20 18 20 13 22.5 13C25 13 25 18 22.5 24Z"/><path d="M11 37H34C35 31 39 26 37 21C35 16 28 17 22.5 24C17 17 10 16 8 21C6 26 10 31 11 37Z"/></g>"##;
+
+/// Colours used to draw a diagram, as SVG colour values.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub light: String,
+    pub dark: String,
+    /// Colour of highlighted squares, drawn half transparent over the square.
+    pub highlight: String,
+    pub arrow: String,
+    pub coordinates: String
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme {
+            light: "#f0d9b5".to_owned(),
+            dark: "#b58863".to_owned(),
+            highlight: "#9bc700".to_owned(),
+            arrow: "#15781b".to_owned(),
+            coordinates: "#333333".to_owned()
+        }
+    }
+}
+
+/// Options for drawing a board as an SVG image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagram {
+    /// Width and height of the image in pixels.
+    pub size: usize,
+    /// The side the board is seen from, which is drawn at the bottom.
+    pub orientation: Colour,
+    /// Label the files a–h and the ranks 1–8 around the board.
+    pub coordinates: bool,
+    /// Squares to highlight, e.g. the moves from `Game::get_possible_moves`.
+    pub highlights: Vec<String>,
+    /// Arrows to draw, from one square to another.
+    pub arrows: Vec<(String, String)>,
+    pub theme: Theme
+}
+
+impl Default for Diagram {
+    fn default() -> Diagram {
+        Diagram {
+            size: 400,
+            orientation: Colour::White,
+            coordinates: true,
+            highlights: Vec::default(),
+            arrows: Vec::default(),
+            theme: Theme::default()
+        }
+    }
+}
+
+impl Diagram {
+    // get the top left corner of a square in the image
+    fn corner(&self, _position: &[usize]) -> (usize, usize) {
+        let (column, row) = match self.orientation {
+            Colour::White => (_position[1], 7 - _position[0]),
+            Colour::Black => (7 - _position[1], _position[0])
+        };
+        let margin = if self.coordinates { MARGIN } else { 0 };
+        (margin + column * SQUARE, row * SQUARE)
+    }
+
+    /// Draw the board of a game as an SVG image.
+    pub fn render(&self, _game: &Game) -> String {
+        let margin = if self.coordinates { MARGIN } else { 0 };
+        let extent = 8 * SQUARE + margin;
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\" version=\"1.1\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+            self.size, self.size, extent, extent
+        );
+        svg += "<defs>\n";
+        svg += PIECES;
+        svg += &format!(
+            "\n<marker id=\"arrowhead\" markerWidth=\"3\" markerHeight=\"3\" refX=\"0\" refY=\"1.5\" orient=\"auto\"><path d=\"M0 0L3 1.5L0 3Z\" fill=\"{}\"/></marker>\n",
+            self.theme.arrow
+        );
+        svg += "</defs>\n";
+
+        // squares, highlights and pieces
+        for rank in 0..8 {
+            for file in 0..8 {
+                let position = vec![rank, file];
+                let (x, y) = self.corner(&position);
+                let fill = match (rank + file) % 2 {
+                    1 => &self.theme.light,
+                    _ => &self.theme.dark
+                };
+                svg += &format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n", x, y, SQUARE, SQUARE, fill);
+            }
+        }
+        for square in &self.highlights {
+            if super::check_position(square) {
+                let (x, y) = self.corner(&decode_position(square));
+                svg += &format!(
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" fill-opacity=\"0.5\"/>\n",
+                    x, y, SQUARE, SQUARE, self.theme.highlight
+                );
+            }
+        }
+        for (rank, row) in _game.board.iter().enumerate() {
+            for (file, square) in row.iter().enumerate() {
+                if let Some(p) = square {
+                    let (x, y) = self.corner(&[rank, file]);
+                    let shape = match p {
+                        Piece::King(_) => "king",
+                        Piece::Queen(_) => "queen",
+                        Piece::Rook(_) => "rook",
+                        Piece::Bishop(_) => "bishop",
+                        Piece::Knight(_) => "knight",
+                        Piece::Pawn(_) => "pawn"
+                    };
+                    let (fill, stroke) = match p.unwrap() {
+                        Colour::White => ("#ffffff", "#000000"),
+                        Colour::Black => ("#000000", "#ffffff")
+                    };
+                    svg += &format!(
+                        "<use xlink:href=\"#{}\" transform=\"translate({},{})\" fill=\"{}\" stroke=\"{}\" stroke-width=\"1.5\" stroke-linejoin=\"round\"/>\n",
+                        shape, x, y, fill, stroke
+                    );
+                }
+            }
+        }
+
+        // arrows run between the centres of the squares, stopping short for the head
+        for (from, to) in &self.arrows {
+            if !super::check_position(from) || !super::check_position(to) || from == to { continue }
+            let (x1, y1) = self.corner(&decode_position(from));
+            let (x2, y2) = self.corner(&decode_position(to));
+            let half = SQUARE as f64 / 2.0;
+            let (x1, y1) = (x1 as f64 + half, y1 as f64 + half);
+            let (x2, y2) = (x2 as f64 + half, y2 as f64 + half);
+            let length = ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt();
+            let head = 21.0;
+            let (x2, y2) = (x2 - (x2 - x1) * head / length, y2 - (y2 - y1) * head / length);
+            svg += &format!(
+                "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\" stroke-width=\"7\" stroke-opacity=\"0.8\" stroke-linecap=\"round\" marker-end=\"url(#arrowhead)\"/>\n",
+                x1, y1, x2, y2, self.theme.arrow
+            );
+        }
+
+        // coordinates in the margin to the left and below the board
+        if self.coordinates {
+            for i in 0..8 {
+                let (x, y) = self.corner(&[i, i]);
+                svg += &format!(
+                    "<text x=\"{}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"11\" text-anchor=\"middle\" fill=\"{}\">{}</text>\n",
+                    MARGIN / 2, y + SQUARE / 2 + 4, self.theme.coordinates, i + 1
+                );
+                svg += &format!(
+                    "<text x=\"{}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"11\" text-anchor=\"middle\" fill=\"{}\">{}</text>\n",
+                    x + SQUARE / 2, 8 * SQUARE + MARGIN - 4, self.theme.coordinates, super::FILES[i]
+                );
+            }
+        }
+
+        svg += "</svg>\n";
+        svg
+    }
+}
+
+impl Game {
+    /// Draw the board as an SVG image with the default diagram options.
+    pub fn to_svg(&self) -> String {
+        Diagram::default().render(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Colour, Game};
+    use super::Diagram;
+
+    #[test]
+    fn svg_diagram() {
+        let game = Game::new();
+        let svg = game.to_svg();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<use ").count(), 32);
+        // the white king stands on e1, in the bottom row of the board
+        assert!(svg.contains("<use xlink:href=\"#king\" transform=\"translate(195,315)\" fill=\"#ffffff\""));
+
+        let diagram = Diagram {
+            orientation: Colour::Black,
+            coordinates: false,
+            highlights: game.get_possible_moves("g1".to_string()).unwrap(),
+            arrows: vec![("g1".to_string(), "f3".to_string())],
+            ..Diagram::default()
+        };
+        let svg = diagram.render(&game);
+        assert!(svg.contains("<use xlink:href=\"#king\" transform=\"translate(135,0)\" fill=\"#ffffff\""));
+        assert_eq!(svg.matches("fill-opacity=\"0.5\"").count(), 2);
+        assert_eq!(svg.matches("<line ").count(), 1);
+        assert!(!svg.contains("<text"));
+    }
+}
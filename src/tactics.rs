@@ -0,0 +1,221 @@
+//! Finding the tactics in a position.
+//!
+//! Tactics are found for one side at a time, in the position as it stands,
+//! whichever side is to move. Pieces are valued at 1 for a pawn, 3 for a knight
+//! or bishop, 5 for a rook and 9 for a queen, and a king is worth more than all
+//! of them. A piece is a target when it is worth more than the piece attacking
+//! it, or is not defended at all.
+
+use super::attacks::{attackers, directions};
+use super::{code_moves, game_state_change, get_availble_moves, get_king_takes, get_knight_takes, get_line, get_pawn_takes, opponent, Colour, Game, Piece};
+
+/// A tactic, with the squares of the pieces involved.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Motif {
+    /// A piece attacks two or more targets at once.
+    Fork { attacker: String, targets: Vec<String> },
+    /// A piece cannot move off a line without exposing a more valuable piece, or the king, behind it.
+    Pin { pinner: String, pinned: String, behind: String },
+    /// A valuable piece, or the king, is attacked, and moving it off the line exposes a target behind it.
+    Skewer { attacker: String, front: String, behind: String },
+    /// A piece can move off the line of a sliding piece of its own side, which then attacks a target.
+    DiscoveredAttack { mover: String, attacker: String, target: String },
+    /// A piece can move off the line of a sliding piece of its own side, which then gives check.
+    DiscoveredCheck { mover: String, attacker: String, king: String },
+    /// A piece is attacked and not defended, or is attacked by a piece worth less.
+    Hanging { square: String }
+}
+
+fn value(_piece: Piece) -> u32 {
+    match _piece {
+        Piece::Pawn(_) => 1,
+        Piece::Knight(_) | Piece::Bishop(_) => 3,
+        Piece::Rook(_) => 5,
+        Piece::Queen(_) => 9,
+        Piece::King(_) => 100
+    }
+}
+
+fn square(_position: &[usize]) -> String {
+    code_moves(&vec![_position.to_vec()])[0].clone()
+}
+
+// get the first two pieces along a line out from a square
+fn first_two(_game: &Game, _position: &Vec<usize>, _direction: (isize, isize)) -> Vec<Vec<usize>> {
+    get_line(_position, _direction.0, _direction.1)[1..].iter()
+        .filter(|p| _game.board[p[0]][p[1]].is_some())
+        .take(2)
+        .cloned()
+        .collect()
+}
+
+// get the squares the piece on a square attacks
+fn attacked(_game: &Game, _position: &Vec<usize>) -> Vec<Vec<usize>> {
+    match _game.board[_position[0]][_position[1]] {
+        Some(Piece::Pawn(c)) => get_pawn_takes(_position, c),
+        Some(Piece::Knight(_)) => get_knight_takes(_position),
+        Some(Piece::King(_)) => get_king_takes(_position),
+        Some(piece) => directions(piece).iter().flat_map(|d| {
+            let line = get_line(_position, d.0, d.1);
+            let end = line[1..].iter().position(|p| _game.board[p[0]][p[1]].is_some()).map(|i| i + 2).unwrap_or(line.len());
+            line[1..end].to_vec()
+        }).collect(),
+        None => Vec::default()
+    }
+}
+
+// get the game with a colour to move, so that its moves can be found whichever side is to move
+fn with_move(_game: &Game, _colour: Colour) -> Game {
+    let mut game = _game.scratch();
+    if game.active != _colour {
+        game.en_passant = None;
+        game.active = opponent(_colour);
+        game_state_change(&mut game);
+        game.active = _colour;
+    }
+    game
+}
+
+// check whether the piece on a square has a legal move off the given squares
+fn can_leave(_game: &Game, _position: &Vec<usize>, _line: &[Vec<usize>]) -> bool {
+    match _game.board[_position[0]][_position[1]] {
+        Some(piece) => get_availble_moves(piece, _position, _game, true).iter().any(|m| !_line.contains(m)),
+        None => false
+    }
+}
+
+fn is_defended(_game: &Game, _position: &[usize], _colour: Colour) -> bool {
+    !attackers(_game, _position, _colour).0.is_empty()
+}
+
+// check whether an opposing piece is a target for a piece worth the given value
+fn is_target(_game: &Game, _position: &[usize], _value: u32) -> bool {
+    match _game.board[_position[0]][_position[1]] {
+        Some(piece) => value(piece) > _value || !is_defended(_game, _position, piece.unwrap()),
+        None => false
+    }
+}
+
+impl Game {
+    /// Find the tactics a colour has against the other in the current position.
+    pub fn tactics(&self, _colour: Colour) -> Vec<Motif> {
+        let mut motifs: Vec<Motif> = Vec::default();
+        let their = opponent(_colour);
+        let moving = with_move(self, _colour);
+        let mut pieces: Vec<(Vec<usize>, Piece)> = Vec::default();
+        for (rank, row) in self.board.iter().enumerate() {
+            for (file, piece) in row.iter().enumerate() {
+                if let Some(p) = piece {
+                    pieces.push((vec![rank, file], *p));
+                }
+            }
+        }
+
+        for (position, piece) in pieces.iter().filter(|(_, p)| p.unwrap() == _colour) {
+            let mut targets: Vec<String> = attacked(self, position).iter()
+                .filter(|p| self.board[p[0]][p[1]].map(|t| t.unwrap()) == Some(their) && is_target(self, p, value(*piece)))
+                .map(|p| square(p))
+                .collect();
+            if targets.len() >= 2 {
+                targets.sort();
+                motifs.push(Motif::Fork { attacker: square(position), targets });
+            }
+
+            // look along the lines of sliding pieces for the two pieces nearest
+            for direction in directions(*piece) {
+                let (front, behind) = match first_two(self, position, direction).as_slice() {
+                    [front, behind] => (front.clone(), behind.clone()),
+                    _ => continue
+                };
+                let (front_piece, behind_piece) = (self.board[front[0]][front[1]].unwrap(), self.board[behind[0]][behind[1]].unwrap());
+                if behind_piece.unwrap() != their { continue }
+                let target = is_target(self, &behind, value(*piece));
+                // a piece of its own only uncovers the line if it can move off the squares between
+                let line = get_line(position, direction.0, direction.1);
+                let between = &line[1..line.iter().position(|p| p == &behind).unwrap()];
+                let uncovers = front_piece.unwrap() == _colour && can_leave(&moving, &front, between);
+                let (attacker, front, behind) = (square(position), square(&front), square(&behind));
+                if front_piece.unwrap() == their {
+                    if value(behind_piece) > value(front_piece) {
+                        motifs.push(Motif::Pin { pinner: attacker, pinned: front, behind });
+                    } else if value(front_piece) > value(behind_piece) && target {
+                        motifs.push(Motif::Skewer { attacker, front, behind });
+                    }
+                } else if !uncovers {
+                    continue
+                } else if behind_piece == Piece::King(their) {
+                    motifs.push(Motif::DiscoveredCheck { mover: front, attacker, king: behind });
+                } else if target {
+                    motifs.push(Motif::DiscoveredAttack { mover: front, attacker, target: behind });
+                }
+            }
+        }
+
+        for (position, piece) in pieces.iter().filter(|(_, p)| p.unwrap() == their && !matches!(p, Piece::King(_))) {
+            let attacking = attackers(self, position, _colour).0;
+            let cheapest = attacking.iter().filter_map(|p| self.board[p[0]][p[1]]).map(value).min();
+            if let Some(cheapest) = cheapest {
+                if cheapest < value(*piece) || !is_defended(self, position, their) {
+                    motifs.push(Motif::Hanging { square: square(position) });
+                }
+            }
+        }
+        motifs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Colour, Game};
+    use super::Motif;
+
+    #[test]
+    fn forks() {
+        // a knight forks king and rook, and the rook is left hanging
+        let game = Game::from_fen("r3k3/2N5/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(game.tactics(Colour::White), vec![
+            Motif::Fork { attacker: "c7".to_string(), targets: vec!["a8".to_string(), "e8".to_string()] },
+            Motif::Hanging { square: "a8".to_string() }
+        ]);
+        assert!(game.tactics(Colour::Black).is_empty());
+    }
+
+    #[test]
+    fn pins() {
+        // a rook pinned to its king
+        let game = Game::from_fen("4k3/4r3/8/8/8/8/4R3/4K3 w - - 0 1").unwrap();
+        assert_eq!(game.tactics(Colour::White), vec![
+            Motif::Pin { pinner: "e2".to_string(), pinned: "e7".to_string(), behind: "e8".to_string() }
+        ]);
+    }
+
+    #[test]
+    fn skewers() {
+        // a bishop skewers the king and the rook behind it
+        let game = Game::from_fen("8/5r2/8/3k4/8/1B6/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(game.tactics(Colour::White), vec![
+            Motif::Skewer { attacker: "b3".to_string(), front: "d5".to_string(), behind: "f7".to_string() }
+        ]);
+    }
+
+    #[test]
+    fn discoveries() {
+        // a knight and a bishop can uncover a check and an attack on the queen, whichever side is to move
+        for fen in ["3qk3/8/8/8/8/8/3BN3/3RR1K1 w - - 0 1", "3qk3/8/8/8/8/8/3BN3/3RR1K1 b - - 0 1"] {
+            let game = Game::from_fen(fen).unwrap();
+            assert_eq!(game.tactics(Colour::White), vec![
+                Motif::DiscoveredAttack { mover: "d2".to_string(), attacker: "d1".to_string(), target: "d8".to_string() },
+                Motif::DiscoveredCheck { mover: "e2".to_string(), attacker: "e1".to_string(), king: "e8".to_string() }
+            ]);
+        }
+    }
+
+    #[test]
+    fn movers_kept_on_the_line() {
+        // a pawn can only move along the file, and a knight pinned to its king cannot move at all
+        let game = Game::from_fen("3qk3/8/8/8/8/8/3P4/3R2K1 w - - 0 1").unwrap();
+        assert!(game.tactics(Colour::White).is_empty());
+        let game = Game::from_fen("3qk3/b7/8/8/3N4/8/8/3R2K1 w - - 0 1").unwrap();
+        assert!(!game.tactics(Colour::White).iter().any(|m| matches!(m, Motif::DiscoveredAttack { .. })));
+    }
+}
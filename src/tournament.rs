@@ -0,0 +1,373 @@
+//! Round-robin and Swiss tournaments.
+//!
+//! Players get pairing numbers by rating, the highest rated first. A round-robin
+//! is paired in advance by the Berger tables. A Swiss tournament is paired a
+//! round at a time by the basics of the FIDE Dutch system: players meet others
+//! on the same score where they can, the top half of a score group meets the
+//! bottom half, nobody meets the same opponent twice, colours are balanced, and
+//! with an odd number of players the lowest placed player gets a bye, at most
+//! once each. A bye is worth a point.
+
+use super::ratings::points;
+use super::{Colour, Outcome};
+
+/// A player in a tournament.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entrant {
+    pub name: String,
+    pub rating: u32
+}
+
+/// A game of a round, or a bye, with players given by their index in the tournament.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Pairing {
+    Game { white: usize, black: usize },
+    Bye(usize)
+}
+
+/// A player's place in the tournament, with their tiebreaks.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Standing {
+    pub player: usize,
+    pub score: f64,
+    /// The points scored against the players on the same score.
+    pub direct_encounter: f64,
+    /// The sum of the scores of the opponents.
+    pub buchholz: f64,
+    /// The sum of the scores of the opponents beaten, and half the scores of those drawn.
+    pub sonneborn_berger: f64
+}
+
+/// Get the rounds of a round-robin by the Berger tables, with players given by
+/// their pairing numbers from 0. With an odd number of players, the player who
+/// would have played the last one gets a bye.
+pub fn berger_tables(_players: usize) -> Vec<Vec<Pairing>> {
+    let n = _players + _players % 2;
+    if n < 2 { return Vec::default() }
+    let m = n - 1;
+    (0..m).map(|round| {
+        // the player meeting the last player moves on by half the players each round, and the
+        // rest meet in pairs around them, with the last player changing colour every round
+        let p = round * n / 2 % m;
+        let mut pairs = vec![match round % 2 {
+            0 => (p, m),
+            _ => (m, p)
+        }];
+        for k in 1..n / 2 {
+            pairs.push(((p + k) % m, (p + m - k) % m));
+        }
+        pairs.iter().map(|&(white, black)| match (white == _players, black == _players) {
+            (true, _) => Pairing::Bye(black),
+            (_, true) => Pairing::Bye(white),
+            _ => Pairing::Game { white, black }
+        }).collect()
+    }).collect()
+}
+
+// pair players in order of score and pairing number without rematches, getting the pairs with
+// the higher placed player first, or none if they cannot be paired
+fn pair_players(_players: &[usize], _scores: &[f64], _met: &[Vec<usize>], _preferences: &[i32]) -> Option<Vec<(usize, usize)>> {
+    let (&top, rest) = match _players.split_first() {
+        Some(s) => s,
+        None => return Some(Vec::default())
+    };
+    // the top player's score group is split in half, and they meet the top of the bottom half, then the
+    // rest of the bottom half, then the top half from its bottom, before anyone on a lower score
+    let group = 1 + rest.iter().take_while(|&&p| _scores[p] == _scores[top]).count();
+    let half = (group / 2).max(1);
+    let mut candidates: Vec<usize> = (half..group).chain((1..half).rev()).chain(group.._players.len()).collect();
+    // within the group, players who must have the same colour as the top player come last
+    let clash = |p: usize| _preferences[top].abs() == 3 && _preferences[p] == _preferences[top];
+    candidates.sort_by_key(|&i| (i >= group, i < group && clash(_players[i])));
+
+    for i in candidates {
+        let opponent = _players[i];
+        if _met[top].contains(&opponent) { continue }
+        let remaining: Vec<usize> = rest.iter().copied().filter(|&p| p != opponent).collect();
+        if let Some(mut pairs) = pair_players(&remaining, _scores, _met, _preferences) {
+            pairs.insert(0, (top, opponent));
+            return Some(pairs)
+        }
+    }
+    None
+}
+
+// check whether the higher placed player of a pair gets white, from the colour preferences
+// of both and the board they play on
+fn gets_white(_preference: i32, _opponent: i32, _board: usize) -> bool {
+    if _preference == 0 && _opponent == 0 {
+        // in the first round the colours alternate down the boards
+        _board.is_multiple_of(2)
+    } else if _preference.signum() != _opponent.signum() {
+        _preference > _opponent
+    } else if _preference.abs() != _opponent.abs() {
+        // the stronger preference is granted
+        match _preference.abs() > _opponent.abs() {
+            true => _preference > 0,
+            false => _opponent < 0
+        }
+    } else {
+        _preference > 0
+    }
+}
+
+/// A tournament, with its rounds and results.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Tournament {
+    players: Vec<Entrant>,
+    // the players in order of pairing number
+    ranking: Vec<usize>,
+    rounds: Vec<Vec<(Pairing, Option<Outcome>)>>
+}
+
+impl Tournament {
+    /// A tournament between the players, without any rounds.
+    pub fn new(_players: Vec<Entrant>) -> Tournament {
+        let mut ranking: Vec<usize> = (0.._players.len()).collect();
+        ranking.sort_by_key(|&p| std::cmp::Reverse(_players[p].rating));
+        Tournament { players: _players, ranking, rounds: Vec::default() }
+    }
+
+    /// A round-robin between the players, with every round paired.
+    pub fn round_robin(_players: Vec<Entrant>) -> Tournament {
+        let mut tournament = Tournament::new(_players);
+        for round in berger_tables(tournament.players.len()) {
+            let pairings = round.iter().map(|p| match *p {
+                Pairing::Game { white, black } => Pairing::Game { white: tournament.ranking[white], black: tournament.ranking[black] },
+                Pairing::Bye(player) => Pairing::Bye(tournament.ranking[player])
+            }).collect();
+            tournament.add_round(pairings);
+        }
+        tournament
+    }
+
+    /// Get the players of the tournament.
+    pub fn get_players(&self) -> &[Entrant] {
+        &self.players
+    }
+
+    /// Get the number of rounds paired.
+    pub fn rounds(&self) -> usize {
+        self.rounds.len()
+    }
+
+    /// Get the pairings of a round, numbered from 0.
+    pub fn pairings(&self, _round: usize) -> Option<Vec<Pairing>> {
+        Some(self.rounds.get(_round)?.iter().map(|(p, _)| *p).collect())
+    }
+
+    /// Add a round that has been paired by hand.
+    pub fn add_round(&mut self, _pairings: Vec<Pairing>) {
+        self.rounds.push(_pairings.into_iter().map(|p| (p, None)).collect());
+    }
+
+    /// Record the outcome of the game on a board of a round, both numbered from 0.
+    /// Returns false if there is no game there.
+    pub fn record(&mut self, _round: usize, _board: usize, _outcome: Outcome) -> bool {
+        match self.rounds.get_mut(_round).and_then(|r| r.get_mut(_board)) {
+            Some((Pairing::Game { .. }, outcome)) => {
+                *outcome = Some(_outcome);
+                true
+            },
+            _ => false
+        }
+    }
+
+    // get the games of a player, as their opponent, their colour and the outcome if recorded
+    fn games(&self, _player: usize) -> Vec<(usize, Colour, Option<Outcome>)> {
+        let mut games: Vec<(usize, Colour, Option<Outcome>)> = Vec::default();
+        for (pairing, outcome) in self.rounds.iter().flatten() {
+            match *pairing {
+                Pairing::Game { white, black } if white == _player => games.push((black, Colour::White, *outcome)),
+                Pairing::Game { white, black } if black == _player => games.push((white, Colour::Black, *outcome)),
+                _ => ()
+            }
+        }
+        games
+    }
+
+    fn byes(&self, _player: usize) -> usize {
+        self.rounds.iter().flatten().filter(|(p, _)| *p == Pairing::Bye(_player)).count()
+    }
+
+    /// Get the points a player has scored.
+    pub fn score(&self, _player: usize) -> f64 {
+        let points: f64 = self.games(_player).iter().filter_map(|(_, colour, outcome)| Some(points(&(*outcome)?, *colour))).sum();
+        points + self.byes(_player) as f64
+    }
+
+    /// Get the sum of the scores of a player's opponents.
+    pub fn buchholz(&self, _player: usize) -> f64 {
+        self.games(_player).iter().map(|(opponent, _, _)| self.score(*opponent)).sum()
+    }
+
+    /// Get the sum of the scores of the opponents a player beat, and half the scores of those they drew.
+    pub fn sonneborn_berger(&self, _player: usize) -> f64 {
+        self.games(_player).iter().filter_map(|(opponent, colour, outcome)| {
+            Some(points(&(*outcome)?, *colour) * self.score(*opponent))
+        }).sum()
+    }
+
+    /// Get the points a player scored against the players on the same score.
+    pub fn direct_encounter(&self, _player: usize) -> f64 {
+        let score = self.score(_player);
+        self.games(_player).iter().filter(|(opponent, _, _)| self.score(*opponent) == score).filter_map(|(_, colour, outcome)| {
+            Some(points(&(*outcome)?, *colour))
+        }).sum()
+    }
+
+    /// Get the standings, ordered by score, then direct encounter, Buchholz,
+    /// Sonneborn-Berger and pairing number.
+    pub fn standings(&self) -> Vec<Standing> {
+        let mut standings: Vec<Standing> = self.ranking.iter().map(|&player| Standing {
+            player,
+            score: self.score(player),
+            direct_encounter: self.direct_encounter(player),
+            buchholz: self.buchholz(player),
+            sonneborn_berger: self.sonneborn_berger(player)
+        }).collect();
+        standings.sort_by(|a, b| {
+            let key = |s: &Standing| [s.score, s.direct_encounter, s.buchholz, s.sonneborn_berger];
+            key(b).partial_cmp(&key(a)).unwrap()
+        });
+        standings
+    }
+
+    // get how much a player wants white, positive, or black, negative: 3 when they must
+    // have it, having had the other colour twice more or twice in a row, 2 when they had it
+    // once more, 1 to alternate, and 0 before their first game
+    fn colour_preference(&self, _player: usize) -> i32 {
+        let colours: Vec<Colour> = self.games(_player).iter().map(|(_, c, _)| *c).collect();
+        let last = match colours.last() {
+            Some(&c) => c,
+            None => return 0
+        };
+        let difference = colours.iter().map(|&c| if c == Colour::White { 1 } else { -1 }).sum::<i32>();
+        let sign = match (difference, last) {
+            (0, Colour::White) => -1,
+            (0, Colour::Black) => 1,
+            (d, _) => -d.signum()
+        };
+        let twice = colours.len() >= 2 && colours[colours.len() - 2] == last;
+        sign * match difference.abs() {
+            d if d >= 2 || twice => 3,
+            1 => 2,
+            _ => 1
+        }
+    }
+
+    /// Pair the next round of a Swiss tournament, and get its pairings with the top
+    /// board first and any bye last. Returns None if the players cannot be paired
+    /// without rematches.
+    pub fn pair_swiss(&mut self) -> Option<Vec<Pairing>> {
+        let players = self.players.len();
+        let scores: Vec<f64> = (0..players).map(|p| self.score(p)).collect();
+        let met: Vec<Vec<usize>> = (0..players).map(|p| self.games(p).iter().map(|(o, _, _)| *o).collect()).collect();
+        let preferences: Vec<i32> = (0..players).map(|p| self.colour_preference(p)).collect();
+        let mut order = self.ranking.clone();
+        order.sort_by(|a, b| scores[*b].partial_cmp(&scores[*a]).unwrap());
+
+        // the bye goes to the lowest placed player who has not had one
+        let byes: Vec<Option<usize>> = match players % 2 {
+            0 => vec![None],
+            _ => order.iter().rev().filter(|&&p| self.byes(p) == 0).map(|&p| Some(p)).collect()
+        };
+        for bye in byes {
+            let rest: Vec<usize> = order.iter().copied().filter(|&p| Some(p) != bye).collect();
+            if let Some(pairs) = pair_players(&rest, &scores, &met, &preferences) {
+                let mut pairings: Vec<Pairing> = pairs.iter().enumerate().map(|(board, &(a, b))| {
+                    match gets_white(preferences[a], preferences[b], board) {
+                        true => Pairing::Game { white: a, black: b },
+                        false => Pairing::Game { white: b, black: a }
+                    }
+                }).collect();
+                pairings.extend(bye.map(Pairing::Bye));
+                self.add_round(pairings.clone());
+                return Some(pairings)
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Colour, Outcome, Termination};
+    use super::{berger_tables, Entrant, Pairing, Tournament};
+
+    fn game(_white: usize, _black: usize) -> Pairing {
+        Pairing::Game { white: _white, black: _black }
+    }
+
+    fn entrants(_ratings: &[u32]) -> Vec<Entrant> {
+        _ratings.iter().enumerate().map(|(i, &rating)| Entrant { name: i.to_string(), rating }).collect()
+    }
+
+    fn win(_winner: Colour) -> Outcome {
+        Outcome { winner: Some(_winner), termination: Termination::Resignation }
+    }
+
+    fn draw() -> Outcome {
+        Outcome { winner: None, termination: Termination::Agreement }
+    }
+
+    // a round-robin of four, where players 0 and 2 tie on points
+    fn finished_round_robin() -> Tournament {
+        let mut tournament = Tournament::round_robin(entrants(&[2000, 1900, 1800, 1700]));
+        for (round, board, outcome) in [(0, 0, win(Colour::White)), (0, 1, draw()), (1, 0, win(Colour::White)), (1, 1, draw()), (2, 0, win(Colour::White)), (2, 1, win(Colour::White))] {
+            assert!(tournament.record(round, board, outcome));
+        }
+        tournament
+    }
+
+    #[test]
+    fn berger() {
+        // the Berger tables for six players, numbered from 0
+        let tables = berger_tables(6);
+        assert_eq!(tables[0], vec![game(0, 5), game(1, 4), game(2, 3)]);
+        assert_eq!(tables[1], vec![game(5, 3), game(4, 2), game(0, 1)]);
+        assert_eq!(tables[4], vec![game(2, 5), game(3, 1), game(4, 0)]);
+        assert_eq!(berger_tables(3)[0], vec![Pairing::Bye(0), game(1, 2)]);
+    }
+
+    #[test]
+    fn round_robin() {
+        let tournament = Tournament::round_robin(entrants(&[2000, 1900, 1800, 1700]));
+        assert_eq!(tournament.rounds(), 3);
+        assert_eq!(tournament.pairings(2), Some(vec![game(1, 3), game(2, 0)]));
+    }
+
+    #[test]
+    fn tie_breaks() {
+        let tournament = finished_round_robin();
+        assert_eq!((tournament.score(0), tournament.score(2)), (1.5, 1.5));
+        assert_eq!((tournament.buchholz(0), tournament.buchholz(2)), (4.5, 4.5));
+        assert_eq!((tournament.sonneborn_berger(0), tournament.sonneborn_berger(2)), (2.0, 2.5));
+    }
+
+    #[test]
+    fn standings() {
+        let tournament = finished_round_robin();
+        assert_eq!(tournament.standings().iter().map(|s| s.player).collect::<Vec<usize>>(), vec![1, 2, 0, 3]);
+    }
+
+    #[test]
+    fn swiss() {
+        // the bye goes to the lowest placed player and nobody meets twice
+        let mut tournament = Tournament::new(entrants(&[1600, 2000, 1900, 1800, 1700]));
+        assert_eq!(tournament.pair_swiss(), Some(vec![game(1, 3), game(4, 2), Pairing::Bye(0)]));
+        tournament.record(0, 0, win(Colour::White));
+        tournament.record(0, 1, draw());
+        assert_eq!(tournament.pair_swiss(), Some(vec![game(2, 1), game(0, 4), Pairing::Bye(3)]));
+    }
+
+    #[test]
+    fn record_results() {
+        // there are only two games in a round of five players
+        let mut tournament = Tournament::new(entrants(&[1600, 2000, 1900, 1800, 1700]));
+        tournament.pair_swiss();
+        assert!(tournament.record(0, 1, draw()));
+        assert!(!tournament.record(0, 2, draw()));
+        assert!(!tournament.record(1, 0, draw()));
+    }
+}
@@ -0,0 +1,584 @@
+//! Game trees with variations and annotations, for analysing games.
+//!
+//! A tree holds the position a game starts from and a node for every move
+//! played from it. The first child of a node continues the main line and the
+//! others are variations. A node is found by its path, the index of the child
+//! taken at each step down from the root, so the main line is a path of zeros.
+
+use super::fen::START_FEN;
+use super::pgn::{parse_tag, wrap, write_tags, Pgn};
+use super::{check_position, decode_position, play_promotion, Colour, Game, GameState, Move, Piece};
+
+/// The colour of an arrow or a highlighted square.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MarkColour {
+    Green,
+    Red,
+    Yellow,
+    Blue
+}
+
+impl MarkColour {
+    // the letter used for the colour in the [%cal] and [%csl] commands of PGN comments
+    fn letter(&self) -> char {
+        match self {
+            MarkColour::Green => 'G',
+            MarkColour::Red => 'R',
+            MarkColour::Yellow => 'Y',
+            MarkColour::Blue => 'B'
+        }
+    }
+
+    fn from_letter(_letter: char) -> Option<MarkColour> {
+        match _letter {
+            'G' => Some(MarkColour::Green),
+            'R' => Some(MarkColour::Red),
+            'Y' => Some(MarkColour::Yellow),
+            'B' => Some(MarkColour::Blue),
+            _ => None
+        }
+    }
+}
+
+/// An arrow drawn on the board from one square to another.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Arrow {
+    pub from: String,
+    pub to: String,
+    pub colour: MarkColour
+}
+
+/// A square marked on the board.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Highlight {
+    pub square: String,
+    pub colour: MarkColour
+}
+
+/// A position in the tree, with the move that led to it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Node {
+    /// The move that led to the position, or None for the starting position.
+    pub played: Option<Move>,
+    pub comment: Option<String>,
+    /// Numeric annotation glyphs, e.g. 1 for "!", 4 for "??" and 14 for a slight advantage to white.
+    pub nags: Vec<u8>,
+    pub arrows: Vec<Arrow>,
+    pub highlights: Vec<Highlight>,
+    /// The moves played from the position, the first of them continuing the main line.
+    pub children: Vec<Node>
+}
+
+/// A game with its variations.
+#[derive(Clone)]
+pub struct GameTree {
+    pub tags: Vec<(String, String)>,
+    /// One of "1-0", "0-1", "1/2-1/2" or "*", as in PGN.
+    pub result: String,
+    start: Game,
+    root: Node
+}
+
+// the glyphs that can be written in place of the first numeric annotation glyphs
+static GLYPHS: [(&str, u8); 13] = [
+    ("!", 1), ("?", 2), ("!!", 3), ("??", 4), ("!?", 5), ("?!", 6), ("=", 10),
+    ("+=", 14), ("=+", 15), ("+/-", 16), ("-/+", 17), ("+-", 18), ("-+", 19)
+];
+
+fn glyph(_text: &str) -> Option<u8> {
+    GLYPHS.iter().find(|(g, _)| *g == _text).map(|(_, n)| *n)
+}
+
+// play a move that has already been played elsewhere
+fn play(_game: &mut Game, _move: &Move) -> Option<GameState> {
+    play_promotion(_game, _move.from.clone(), _move.to.clone(), _move.promotion)
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Move(String),
+    Nag(u8),
+    Comment(String),
+    Open,
+    Close,
+    Result(String)
+}
+
+// split a word of movetext into a move and the glyph after it, leaving out move numbers
+fn read_word(_word: &str, _tokens: &mut Vec<Token>) -> Option<()> {
+    if ["1-0", "0-1", "1/2-1/2", "*"].contains(&_word) {
+        _tokens.push(Token::Result(_word.to_owned()));
+    } else if let Some(n) = _word.strip_prefix('$') {
+        _tokens.push(Token::Nag(n.parse().ok()?));
+    } else if let Some(n) = glyph(_word) {
+        _tokens.push(Token::Nag(n));
+    } else {
+        let word = _word.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+        let san = word.trim_end_matches(['!', '?']);
+        if !san.is_empty() {
+            _tokens.push(Token::Move(san.to_owned()));
+        }
+        if san.len() < word.len() {
+            _tokens.push(Token::Nag(glyph(&word[san.len()..])?));
+        }
+    }
+    Some(())
+}
+
+// split movetext into moves, annotations, comments and the brackets around variations
+fn tokenize(_movetext: &str) -> Option<Vec<Token>> {
+    let mut tokens: Vec<Token> = Vec::default();
+    let mut word = String::default();
+    let mut chars = _movetext.chars();
+    while let Some(c) = chars.next() {
+        if (c.is_whitespace() || "{;()".contains(c)) && !word.is_empty() {
+            read_word(&std::mem::take(&mut word), &mut tokens)?;
+        }
+        match c {
+            '{' => {
+                let comment: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                tokens.push(Token::Comment(comment));
+            },
+            ';' => tokens.push(Token::Comment(chars.by_ref().take_while(|&c| c != '\n').collect())),
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            _ if c.is_whitespace() => continue,
+            _ => word.push(c)
+        }
+    }
+    if !word.is_empty() {
+        read_word(&word, &mut tokens)?;
+    }
+    Some(tokens)
+}
+
+// add a comment to a node, taking out the arrows and highlights in it
+fn read_comment(_node: &mut Node, _comment: &str) {
+    let mut text = String::default();
+    let mut rest = _comment;
+    while let Some(start) = rest.find("[%") {
+        let end = match rest[start..].find(']') {
+            Some(e) => start + e,
+            None => break
+        };
+        text += &rest[..start];
+        let marks: Vec<&str> = match rest[start + 2..end].split_once(' ') {
+            Some(("cal", marks)) | Some(("csl", marks)) => marks.split(',').map(str::trim).collect(),
+            // other commands, e.g. clock times, are kept in the comment
+            _ => {
+                text += &rest[start..=end];
+                Vec::default()
+            }
+        };
+        for mark in marks {
+            let colour = match mark.chars().next().and_then(MarkColour::from_letter) {
+                Some(c) => c,
+                None => continue
+            };
+            match mark.get(1..) {
+                Some(s) if s.len() == 2 && check_position(s) => _node.highlights.push(Highlight { square: s.to_owned(), colour }),
+                Some(s) if s.len() == 4 && s.is_ascii() && check_position(&s[..2]) && check_position(&s[2..]) => {
+                    _node.arrows.push(Arrow { from: s[..2].to_owned(), to: s[2..].to_owned(), colour })
+                },
+                _ => ()
+            }
+        }
+        rest = &rest[end + 1..];
+    }
+    text += rest;
+
+    let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+    if !text.is_empty() {
+        _node.comment = Some(match _node.comment.take() {
+            Some(c) => c + " " + &text,
+            None => text
+        });
+    }
+}
+
+// write the comment of a node together with its arrows and highlights
+fn write_comment(_node: &Node) -> Option<String> {
+    let mut parts: Vec<String> = Vec::default();
+    if !_node.highlights.is_empty() {
+        let marks: Vec<String> = _node.highlights.iter().map(|h| format!("{}{}", h.colour.letter(), h.square)).collect();
+        parts.push(format!("[%csl {}]", marks.join(",")));
+    }
+    if !_node.arrows.is_empty() {
+        let marks: Vec<String> = _node.arrows.iter().map(|a| format!("{}{}{}", a.colour.letter(), a.from, a.to)).collect();
+        parts.push(format!("[%cal {}]", marks.join(",")));
+    }
+    if let Some(c) = &_node.comment {
+        parts.push(c.clone());
+    }
+    match parts.is_empty() {
+        true => None,
+        false => Some(format!("{{{}}}", parts.join(" ")))
+    }
+}
+
+impl GameTree {
+    /// A tree for a game from the standard starting position, without any moves.
+    pub fn new() -> GameTree {
+        GameTree::from_game(&Game::new())
+    }
+
+    /// A tree for a game, with the moves played so far as the main line.
+    pub fn from_game(_game: &Game) -> GameTree {
+        let start = match _game.history.first() {
+            Some(r) => r.before.scratch(),
+            None => _game.scratch()
+        };
+        let pgn = Pgn::from_game(_game);
+        let mut line: Option<Node> = None;
+        for played in _game.get_history().into_iter().rev() {
+            line = Some(Node { played: Some(played), children: line.into_iter().collect(), ..Node::default() });
+        }
+        GameTree {
+            tags: pgn.tags,
+            result: pgn.result,
+            start,
+            root: Node { children: line.into_iter().collect(), ..Node::default() }
+        }
+    }
+
+    /// Get the node of the starting position.
+    pub fn root(&self) -> &Node {
+        &self.root
+    }
+
+    /// Get the node at the end of a path, if there is one.
+    pub fn get(&self, _path: &[usize]) -> Option<&Node> {
+        let mut node = &self.root;
+        for &i in _path {
+            node = node.children.get(i)?;
+        }
+        Some(node)
+    }
+
+    /// Get the node at the end of a path to annotate it, if there is one.
+    pub fn get_mut(&mut self, _path: &[usize]) -> Option<&mut Node> {
+        let mut node = &mut self.root;
+        for &i in _path {
+            node = node.children.get_mut(i)?;
+        }
+        Some(node)
+    }
+
+    /// Get the game played up to the node at the end of a path, if there is one.
+    pub fn game_at(&self, _path: &[usize]) -> Option<Game> {
+        let mut game = self.start.clone();
+        let mut node = &self.root;
+        for &i in _path {
+            node = node.children.get(i)?;
+            play(&mut game, node.played.as_ref()?)?;
+        }
+        Some(game)
+    }
+
+    /// Get the path of the last move of the main line.
+    pub fn mainline(&self) -> Vec<usize> {
+        let mut path: Vec<usize> = Vec::default();
+        let mut node = &self.root;
+        while let Some(child) = node.children.first() {
+            path.push(0);
+            node = child;
+        }
+        path
+    }
+
+    /// Get the game played along the main line.
+    pub fn mainline_game(&self) -> Game {
+        self.game_at(&self.mainline()).unwrap()
+    }
+
+    // add a played move below a node, or find it if it is already there
+    fn insert(&mut self, _path: &[usize], _played: Move) -> Option<Vec<usize>> {
+        let node = self.get_mut(_path)?;
+        let index = match node.children.iter().position(|c| {
+            c.played.as_ref().map(|m| (&m.from, &m.to, m.promotion)) == Some((&_played.from, &_played.to, _played.promotion))
+        }) {
+            Some(i) => i,
+            None => {
+                node.children.push(Node { played: Some(_played), ..Node::default() });
+                node.children.len() - 1
+            }
+        };
+        let mut path = _path.to_vec();
+        path.push(index);
+        Some(path)
+    }
+
+    /// Add a move after the node at the end of a path, as a new variation if the node
+    /// already has a continuation. Pawns reaching the last rank become queens unless
+    /// another piece is given. Returns the path of the move, or None if it is not
+    /// legal or the piece is not one the pawn can become. A move that is already
+    /// in the tree is not added again.
+    pub fn add_move(&mut self, _path: &[usize], _from: String, _to: String, _promotion: Option<Piece>) -> Option<Vec<usize>> {
+        let mut game = self.game_at(_path)?;
+        if !check_position(&_from) || !check_position(&_to) { return None }
+        let promotion = _promotion.or_else(|| {
+            let (from, to) = (decode_position(&_from), decode_position(&_to));
+            match game.board[from[0]][from[1]] {
+                Some(Piece::Pawn(c)) if to[0] == 0 || to[0] == 7 => Some(Piece::Queen(c)),
+                _ => None
+            }
+        });
+        play(&mut game, &Move { from: _from, to: _to, promotion, san: String::default() })?;
+        self.insert(_path, game.history.pop()?.played)
+    }
+
+    /// Add a move given in standard algebraic notation, as `add_move` does.
+    pub fn add_san_move(&mut self, _path: &[usize], _san: &str) -> Option<Vec<usize>> {
+        let (from, to, promotion) = self.game_at(_path)?.parse_san(_san)?;
+        self.add_move(_path, from, to, promotion)
+    }
+
+    /// Make the move at the end of a path the main continuation of the move before it.
+    /// Returns the new path of the move, or None if there is no move at the path.
+    pub fn promote_variation(&mut self, _path: &[usize]) -> Option<Vec<usize>> {
+        let (&index, parent) = _path.split_last()?;
+        let node = self.get_mut(parent)?;
+        if index >= node.children.len() { return None }
+        let child = node.children.remove(index);
+        node.children.insert(0, child);
+        let mut path = parent.to_vec();
+        path.push(0);
+        Some(path)
+    }
+
+    /// Make the line leading to the move at the end of a path the main line.
+    /// Returns the new path of the move, or None if there is no move at the path.
+    pub fn make_mainline(&mut self, _path: &[usize]) -> Option<Vec<usize>> {
+        self.get(_path)?;
+        for depth in 1.._path.len() + 1 {
+            self.promote_variation(&[vec![0; depth - 1], vec![_path[depth - 1]]].concat())?;
+        }
+        Some(vec![0; _path.len()])
+    }
+
+    /// Remove the move at the end of a path together with everything after it.
+    pub fn delete(&mut self, _path: &[usize]) -> Option<Node> {
+        let (&index, parent) = _path.split_last()?;
+        let node = self.get_mut(parent)?;
+        if index >= node.children.len() { return None }
+        Some(node.children.remove(index))
+    }
+
+    // write a move with its number, annotations and comment
+    fn write_move(&self, _node: &Node, _ply: u32, _number: bool, _tokens: &mut Vec<String>) {
+        let index = _ply + if self.start.active == Colour::Black { 1 } else { 0 };
+        let number = self.start.fullmove_number + index / 2;
+        if index.is_multiple_of(2) {
+            _tokens.push(format!("{}.", number));
+        } else if _number {
+            _tokens.push(format!("{}...", number));
+        }
+        if let Some(m) = &_node.played {
+            _tokens.push(m.san.clone());
+        }
+        for nag in &_node.nags {
+            _tokens.push(format!("${}", nag));
+        }
+        if let Some(comment) = write_comment(_node) {
+            _tokens.push(comment);
+        }
+    }
+
+    // write the moves after a node, with the variations at each move after the move of the main line
+    fn write_continuation(&self, _node: &Node, _ply: u32, _number: bool, _tokens: &mut Vec<String>) {
+        let main = match _node.children.first() {
+            Some(m) => m,
+            None => return
+        };
+        self.write_move(main, _ply, _number, _tokens);
+        for variation in &_node.children[1..] {
+            _tokens.push("(".to_owned());
+            self.write_move(variation, _ply, true, _tokens);
+            self.write_continuation(variation, _ply + 1, write_comment(variation).is_some(), _tokens);
+            _tokens.push(")".to_owned());
+        }
+        // black's moves are numbered again after a comment or a variation
+        let number = _node.children.len() > 1 || write_comment(main).is_some();
+        self.write_continuation(main, _ply + 1, number, _tokens);
+    }
+
+    /// Write the tree in PGN, with the variations in brackets.
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = Pgn { tags: self.tags.clone(), moves: Vec::default(), result: self.result.clone() };
+        if self.start.chess960 {
+            pgn.set_tag("Variant", "Chess960");
+        }
+        let start = self.start.to_fen();
+        if start != START_FEN {
+            pgn.set_tag("SetUp", "1");
+            pgn.set_tag("FEN", &start);
+        }
+
+        let mut tokens: Vec<String> = Vec::default();
+        if let Some(comment) = write_comment(&self.root) {
+            tokens.push(comment);
+        }
+        self.write_continuation(&self.root, 0, true, &mut tokens);
+        tokens.push(self.result.clone());
+
+        // brackets are written against the moves they enclose
+        let mut joined: Vec<String> = Vec::default();
+        let mut open = false;
+        for token in tokens {
+            match token.as_str() {
+                "(" => open = true,
+                ")" => if let Some(last) = joined.last_mut() { last.push(')') },
+                _ if open => {
+                    joined.push(format!("({}", token));
+                    open = false;
+                },
+                _ => joined.push(token)
+            }
+        }
+        write_tags(&pgn.tags) + &wrap(&joined)
+    }
+
+    /// Read a game in PGN with its variations, comments and annotations. Returns
+    /// None if the PGN is malformed or any of the moves is not legal.
+    pub fn from_pgn(_pgn: &str) -> Option<GameTree> {
+        let mut tags: Vec<(String, String)> = Vec::default();
+        let mut movetext = String::default();
+        for line in _pgn.lines() {
+            let line = line.trim();
+            if line.starts_with('[') {
+                tags.push(parse_tag(line)?);
+            } else if !line.starts_with('%') {
+                movetext += line;
+                movetext.push('\n');
+            }
+        }
+
+        // the tags give the starting position and the variant
+        let header = Pgn { tags, moves: Vec::default(), result: "*".to_owned() };
+        let start = header.replay()?;
+        let mut tree = GameTree { tags: header.tags, result: header.result, start: start.scratch(), root: Node::default() };
+
+        // a variation replaces the last move, so it starts from the game before that move
+        let mut path: Vec<usize> = Vec::default();
+        let mut game = start;
+        let mut stack: Vec<(Vec<usize>, Game)> = Vec::default();
+        for token in tokenize(&movetext)? {
+            match token {
+                Token::Move(san) => {
+                    let (from, to, promotion) = game.parse_san(&san)?;
+                    play_promotion(&mut game, from, to, promotion)?;
+                    path = tree.insert(&path, game.get_history().pop()?)?;
+                },
+                Token::Nag(n) => tree.get_mut(&path)?.nags.push(n),
+                Token::Comment(c) => read_comment(tree.get_mut(&path)?, &c),
+                Token::Open => {
+                    stack.push((path.clone(), game.clone()));
+                    path.pop()?;
+                    game.undo_move()?;
+                },
+                Token::Close => (path, game) = stack.pop()?,
+                Token::Result(r) => tree.result = r
+            }
+        }
+        match stack.is_empty() {
+            true => Some(tree),
+            false => None
+        }
+    }
+}
+
+impl Default for GameTree {
+    fn default() -> GameTree {
+        GameTree::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Colour, Game, Piece};
+    use super::{Arrow, GameTree, Highlight, MarkColour};
+
+    static RUY_LOPEZ: &str = "[Event \"Analysis\"]\n\n{The Ruy Lopez} 1. e4 e5 2. Nf3 Nc6 (2... d6 {Philidor} 3. d4 (3. Bc4)) 3. Bb5 $1 {[%csl Gb5][%cal Rb5c6,Gc6e5] pressure on the knight} a6!? *";
+
+    #[test]
+    fn read_variations() {
+        let tree = GameTree::from_pgn(RUY_LOPEZ).unwrap();
+        assert_eq!(tree.root().comment.as_deref(), Some("The Ruy Lopez"));
+        assert_eq!(tree.mainline(), vec![0; 6]);
+        assert_eq!(tree.mainline_game().to_fen(), "r1bqkbnr/1ppp1ppp/p1n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 0 4");
+
+        let philidor = tree.get(&[0, 0, 0, 1]).unwrap();
+        assert_eq!(philidor.played.as_ref().unwrap().san, "d6");
+        assert_eq!(philidor.comment.as_deref(), Some("Philidor"));
+        assert_eq!(philidor.children.len(), 2);
+    }
+
+    #[test]
+    fn read_annotations() {
+        let tree = GameTree::from_pgn(RUY_LOPEZ).unwrap();
+        let bishop = tree.get(&[0, 0, 0, 0, 0]).unwrap();
+        assert_eq!(bishop.nags, vec![1]);
+        assert_eq!(bishop.highlights, vec![Highlight { square: "b5".to_string(), colour: MarkColour::Green }]);
+        assert_eq!(bishop.arrows[0], Arrow { from: "b5".to_string(), to: "c6".to_string(), colour: MarkColour::Red });
+        assert_eq!(tree.get(&[0, 0, 0, 0, 0, 0]).unwrap().nags, vec![5]);
+    }
+
+    #[test]
+    fn pgn_round_trip() {
+        let tree = GameTree::from_pgn(RUY_LOPEZ).unwrap();
+        let written = tree.to_pgn();
+        assert_eq!(written, "[Event \"Analysis\"]\n\n{The Ruy Lopez} 1. e4 e5 2. Nf3 Nc6 (2... d6 {Philidor} 3. d4 (3. Bc4)) 3. Bb5\n$1 {[%csl Gb5] [%cal Rb5c6,Gc6e5] pressure on the knight} 3... a6 $5 *\n");
+        let read = GameTree::from_pgn(&written).unwrap();
+        assert_eq!(read.root(), tree.root());
+    }
+
+    #[test]
+    fn add_moves() {
+        // adding a move that is already there finds it, a new one starts a variation
+        let mut tree = GameTree::from_pgn(RUY_LOPEZ).unwrap();
+        assert_eq!(tree.add_san_move(&[0, 0], "Nf3"), Some(vec![0, 0, 0]));
+        assert_eq!(tree.add_move(&[0, 0], "f2".to_string(), "f4".to_string(), None), Some(vec![0, 0, 1]));
+        assert_eq!(tree.add_san_move(&[0, 0], "Ke3"), None);
+    }
+
+    #[test]
+    fn add_promotions() {
+        let mut tree = GameTree::from_game(&Game::from_fen("8/1P6/8/8/8/8/k7/4K3 w - - 0 1").unwrap());
+        let b8 = |tree: &mut GameTree, promotion| tree.add_move(&[], "b7".to_string(), "b8".to_string(), promotion);
+        assert_eq!(b8(&mut tree, None), Some(vec![0]));
+        assert_eq!(tree.get(&[0]).unwrap().played.as_ref().unwrap().promotion, Some(Piece::Queen(Colour::White)));
+        assert_eq!(b8(&mut tree, Some(Piece::Knight(Colour::White))), Some(vec![1]));
+
+        // a piece of the other side, a king or a pawn, and a piece on a move that does not promote
+        assert_eq!(b8(&mut tree, Some(Piece::Rook(Colour::Black))), None);
+        assert_eq!(b8(&mut tree, Some(Piece::King(Colour::White))), None);
+        assert_eq!(b8(&mut tree, Some(Piece::Pawn(Colour::White))), None);
+        assert_eq!(tree.add_move(&[], "e1".to_string(), "e2".to_string(), Some(Piece::Queen(Colour::White))), None);
+        assert_eq!(tree.root().children.len(), 2);
+    }
+
+    #[test]
+    fn edit_variations() {
+        let mut tree = GameTree::from_pgn(RUY_LOPEZ).unwrap();
+        tree.add_move(&[0, 0], "f2".to_string(), "f4".to_string(), None);
+        // the Philidor becomes the main line, and the King's Gambit is cut
+        assert_eq!(tree.make_mainline(&[0, 0, 0, 1, 1]), Some(vec![0; 5]));
+        assert_eq!(tree.mainline_game().get_history().iter().map(|m| m.san.as_str()).collect::<Vec<&str>>(), vec!["e4", "e5", "Nf3", "d6", "Bc4"]);
+        assert_eq!(tree.get(&[0, 0, 0, 0]).unwrap().children[1].played.as_ref().unwrap().san, "d4");
+        assert_eq!(tree.delete(&[0, 0, 1]).unwrap().played.unwrap().san, "f4");
+        assert_eq!(tree.get(&[0, 0]).unwrap().children.len(), 1);
+        assert!(tree.delete(&[]).is_none());
+    }
+
+    #[test]
+    fn trees_from_games() {
+        // games become trees from the position they started in, with promotions kept
+        let mut game = Game::from_fen("8/1P6/8/8/8/8/k7/4K3 b - - 0 30").unwrap();
+        game.make_san_move("Ka1");
+        game.make_san_move("b8=R");
+        let tree = GameTree::from_game(&game);
+        assert_eq!(tree.get(&[0, 0]).unwrap().played.as_ref().unwrap().promotion, Some(Piece::Rook(Colour::White)));
+        assert!(tree.to_pgn().ends_with("\n30... Ka1 31. b8=R *\n"));
+        assert_eq!(GameTree::from_pgn(&tree.to_pgn()).unwrap().mainline_game().to_fen(), game.to_fen());
+    }
+}
@@ -0,0 +1,350 @@
+//! Playing games with engines over the Universal Chess Interface (UCI).
+//!
+//! An engine runs as a separate process, which is sent commands on its standard
+//! input and answers on its standard output. Its output is read on a thread of
+//! its own, so that an engine that does not answer in time can be told to stop.
+//! Every answer is waited for until a deadline, and an engine that misses one
+//! gets a `TimedOut` error, which loses the game it is playing on time.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::clock::Bonus;
+use super::fen::START_FEN;
+use super::{check_position, Colour, Game, Move, Piece};
+
+/// An evaluation of a position, from the point of view of the side to move.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Score {
+    Centipawns(i32),
+    /// Mate in the given number of moves, negative if the side to move is getting mated.
+    Mate(i32)
+}
+
+/// A move chosen by a player, with its evaluation if it gave one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reply {
+    pub from: String,
+    pub to: String,
+    pub promotion: Option<Piece>,
+    pub score: Option<Score>
+}
+
+impl Reply {
+    /// Read a move of a colour in the long algebraic notation of UCI, e.g. "e2e4" or "e7e8q".
+    pub fn from_uci(_move: &str, _colour: Colour) -> Option<Reply> {
+        if !(_move.len() == 4 || _move.len() == 5) || !_move.is_ascii() { return None }
+        let (from, to) = (&_move[0..2], &_move[2..4]);
+        if !check_position(from) || !check_position(to) { return None }
+        let promotion = match _move[4..].chars().next() {
+            Some(c) if "qrbn".contains(c) => Some(Piece::from_letter(c, _colour)?),
+            Some(_) => return None,
+            None => None
+        };
+        Some(Reply { from: from.to_owned(), to: to.to_owned(), promotion, score: None })
+    }
+}
+
+impl Move {
+    /// Get the move in the long algebraic notation of UCI, e.g. "e2e4" or "e7e8q".
+    pub fn to_uci(&self) -> String {
+        match self.promotion {
+            Some(p) => format!("{}{}{}", self.from, self.to, p.letter().to_ascii_lowercase()),
+            None => format!("{}{}", self.from, self.to)
+        }
+    }
+}
+
+/// Something that plays games, such as an engine.
+pub trait Player {
+    /// Get the name of the player.
+    fn name(&self) -> String;
+
+    /// Get ready for a new game.
+    fn new_game(&mut self) -> io::Result<()>;
+
+    /// Choose a move for the side to move, in the time left on the clock if the game is timed.
+    fn think(&mut self, _game: &Game) -> io::Result<Reply>;
+}
+
+/// How to start an engine.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EngineConfig {
+    /// The program to run.
+    pub command: String,
+    pub args: Vec<String>,
+    /// The name to give the engine, instead of the name it gives itself.
+    pub name: Option<String>,
+    /// Options to set before playing, as names and values, e.g. ("Hash", "64").
+    pub options: Vec<(String, String)>,
+    /// The time to think about each move in games without a clock. One second if not given.
+    pub move_time: Option<Duration>,
+    /// The time to wait for the engine to start, to get ready, and to give its move
+    /// once told to stop. Ten seconds if not given.
+    pub timeout: Option<Duration>
+}
+
+/// An engine running in its own process.
+pub struct UciEngine {
+    name: String,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    // whether the engine has been told to play Chess960
+    chess960: bool,
+    move_time: Duration,
+    timeout: Duration
+}
+
+impl UciEngine {
+    /// Start an engine, and wait for it to be ready with its options set.
+    pub fn start(_config: &EngineConfig) -> io::Result<UciEngine> {
+        let mut child = Command::new(&_config.command)
+            .args(&_config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(l) => if sender.send(l).is_err() { break },
+                    Err(_) => break
+                }
+            }
+        });
+
+        let mut engine = UciEngine {
+            name: _config.name.clone().unwrap_or_default(),
+            child,
+            stdin,
+            lines,
+            chess960: false,
+            move_time: _config.move_time.unwrap_or(Duration::from_secs(1)),
+            timeout: _config.timeout.unwrap_or(Duration::from_secs(10))
+        };
+        engine.send("uci")?;
+        let deadline = Instant::now() + engine.timeout;
+        loop {
+            let line = engine.read(deadline)?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                if engine.name.is_empty() {
+                    engine.name = name.trim().to_owned();
+                }
+            }
+            if line.trim() == "uciok" { break }
+        }
+        for (name, value) in &_config.options {
+            engine.send(&format!("setoption name {} value {}", name, value))?;
+        }
+        engine.ready()?;
+        Ok(engine)
+    }
+
+    fn send(&mut self, _command: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{}", _command)?;
+        self.stdin.flush()
+    }
+
+    // wait for the next line from the engine until a deadline, getting a TimedOut error if it passes
+    fn read(&mut self, _deadline: Instant) -> io::Result<String> {
+        self.read_until(_deadline)?.ok_or_else(|| io::Error::new(io::ErrorKind::TimedOut, "the engine did not answer in time"))
+    }
+
+    // wait for the next line from the engine until a deadline, getting None if it passes
+    fn read_until(&mut self, _deadline: Instant) -> io::Result<Option<String>> {
+        match self.lines.recv_timeout(_deadline.saturating_duration_since(Instant::now())) {
+            Ok(line) => Ok(Some(line)),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the engine has quit"))
+        }
+    }
+
+    fn ready(&mut self) -> io::Result<()> {
+        self.send("isready")?;
+        let deadline = Instant::now() + self.timeout;
+        while self.read(deadline)?.trim() != "readyok" {}
+        Ok(())
+    }
+}
+
+// read the score from an info line, e.g. "info depth 12 score cp 31 nodes 20000 pv e2e4"
+fn parse_score(_info: &str) -> Option<Score> {
+    let words: Vec<&str> = _info.split_whitespace().collect();
+    let i = words.iter().position(|&w| w == "score")?;
+    let value = words.get(i + 2)?.parse().ok()?;
+    match *words.get(i + 1)? {
+        "cp" => Some(Score::Centipawns(value)),
+        "mate" => Some(Score::Mate(value)),
+        _ => None
+    }
+}
+
+impl Player for UciEngine {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn new_game(&mut self) -> io::Result<()> {
+        self.send("ucinewgame")?;
+        self.ready()
+    }
+
+    fn think(&mut self, _game: &Game) -> io::Result<Reply> {
+        if _game.chess960 != self.chess960 {
+            self.send(&format!("setoption name UCI_Chess960 value {}", _game.chess960))?;
+            self.chess960 = _game.chess960;
+        }
+
+        let start = match _game.history.first() {
+            Some(r) => r.before.to_fen(),
+            None => _game.to_fen()
+        };
+        let mut position = match start == START_FEN && !_game.chess960 {
+            true => "position startpos".to_owned(),
+            false => format!("position fen {}", start)
+        };
+        if !_game.history.is_empty() {
+            position += " moves";
+            for record in &_game.history {
+                position.push(' ');
+                position += &record.played.to_uci();
+            }
+        }
+        self.send(&position)?;
+
+        // the engine is stopped once the time on the clock has run out
+        let (go, time) = match _game.get_clock() {
+            Some(clock) => {
+                let mut go = format!(
+                    "go wtime {} btime {}",
+                    clock.remaining(Colour::White).as_millis(),
+                    clock.remaining(Colour::Black).as_millis()
+                );
+                for (colour, name) in [(Colour::White, "winc"), (Colour::Black, "binc")] {
+                    if let Bonus::Fischer(increment) = clock.get_bonus(colour) {
+                        go += &format!(" {} {}", name, increment.as_millis());
+                    }
+                }
+                if let Some(moves) = clock.moves_to_go(_game.active) {
+                    go += &format!(" movestogo {}", moves);
+                }
+                (go, clock.remaining(_game.active))
+            },
+            None => (format!("go movetime {}", self.move_time.as_millis()), self.move_time)
+        };
+        self.send(&go)?;
+
+        // once stopped, the engine has until the second deadline to give its move
+        let mut deadline = Instant::now() + time;
+        let mut stopped = false;
+        let mut score = None;
+        loop {
+            let line = match stopped {
+                true => self.read(deadline)?,
+                false => match self.read_until(deadline)? {
+                    Some(l) => l,
+                    None => {
+                        self.send("stop")?;
+                        stopped = true;
+                        deadline = Instant::now() + self.timeout;
+                        continue
+                    }
+                }
+            };
+            if line.starts_with("info") {
+                score = parse_score(&line).or(score);
+            } else if let Some(rest) = line.strip_prefix("bestmove") {
+                let best = rest.split_whitespace().next().unwrap_or_default();
+                let mut reply = Reply::from_uci(best, _game.active)
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("not a move: {}", best)))?;
+                reply.score = score;
+                return Ok(reply)
+            }
+        }
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::time::{Duration, Instant};
+
+    use super::super::{Colour, Game, Piece};
+    use super::{parse_score, EngineConfig, Player, Reply, Score, UciEngine};
+
+    // an engine that gets ready but never moves, run by the shell
+    #[cfg(unix)]
+    fn silent_engine() -> EngineConfig {
+        EngineConfig {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), "while read l; do case $l in uci) echo uciok;; isready) echo readyok;; esac; done".to_string()],
+            move_time: Some(Duration::from_millis(50)),
+            timeout: Some(Duration::from_millis(200)),
+            ..EngineConfig::default()
+        }
+    }
+
+    #[test]
+    fn scores() {
+        assert_eq!(parse_score("info depth 20 seldepth 28 score cp -35 nodes 1000 pv d7d5"), Some(Score::Centipawns(-35)));
+        assert_eq!(parse_score("info depth 9 score mate 3 pv h5f7"), Some(Score::Mate(3)));
+        assert_eq!(parse_score("info string hello"), None);
+    }
+
+    #[test]
+    fn read_moves() {
+        let reply = Reply::from_uci("e7e8n", Colour::White).unwrap();
+        assert_eq!((reply.from.as_str(), reply.to.as_str(), reply.promotion), ("e7", "e8", Some(Piece::Knight(Colour::White))));
+        assert_eq!(Reply::from_uci("0000", Colour::White), None);
+        assert_eq!(Reply::from_uci("e7e8k", Colour::White), None);
+    }
+
+    #[test]
+    fn write_moves() {
+        let mut game = Game::from_fen("8/1P6/8/8/8/8/k7/4K3 w - - 0 1").unwrap();
+        game.set_promotion("r".to_string());
+        game.make_move("b7".to_string(), "b8".to_string());
+        assert_eq!(game.get_history()[0].to_uci(), "b7b8r");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn engine_that_never_answers() {
+        // a program that reads nothing and writes nothing is given up on when starting
+        let config = EngineConfig {
+            command: "sleep".to_string(),
+            args: vec!["10".to_string()],
+            timeout: Some(Duration::from_millis(200)),
+            ..EngineConfig::default()
+        };
+        let started = Instant::now();
+        let error = UciEngine::start(&config).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn engine_that_never_moves() {
+        let mut engine = UciEngine::start(&silent_engine()).unwrap();
+        engine.new_game().unwrap();
+        let started = Instant::now();
+        assert_eq!(engine.think(&Game::new()).unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}